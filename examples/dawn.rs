@@ -17,7 +17,7 @@ fn main() {
 
     let m = Box::new(HQZLegacy::default());
 
-    let o = Object::Line {
+    let o = Line {
         x0: Sample::Constant(0.0),
         y0: Sample::Constant(height * 0.75),
         dx: Sample::Constant(width),
@@ -47,7 +47,7 @@ fn main() {
     println!("Saving!");
     let path = Path::new(r"dawn.png");
     let file = File::create(path).unwrap();
-    let ref mut w = BufWriter::new(file);
+    let w = &mut BufWriter::new(file);
 
     let mut encoder = png::Encoder::new(w, width as u32, height as u32);
     encoder.set(png::ColorType::RGB).set(png::BitDepth::Eight);
@@ -31,44 +31,44 @@ fn main() {
     let wall_m = HQZLegacy::new(1.0, 0.0, 0.0);
     let floor_m = HQZLegacy::new(0.1, 0.3, 0.5);
 
-    let top = Object::Line {
+    let top = Line {
         x0: Sample::Constant(0.0),
         y0: Sample::Constant(0.0),
         dx: Sample::Constant(width),
         dy: Sample::Constant(0.0),
-        material: Box::new(wall_m.clone()),
+        material: Box::new(wall_m),
     };
 
-    let bottom = Object::Line {
+    let bottom = Line {
         x0: Sample::Constant(height),
         y0: Sample::Constant(0.0),
         dx: Sample::Constant(width),
         dy: Sample::Constant(0.0),
-        material: Box::new(wall_m.clone()),
+        material: Box::new(wall_m),
     };
 
-    let left = Object::Line {
+    let left = Line {
         x0: Sample::Constant(0.0),
         y0: Sample::Constant(0.0),
         dx: Sample::Constant(0.0),
         dy: Sample::Constant(height),
-        material: Box::new(wall_m.clone()),
+        material: Box::new(wall_m),
     };
 
-    let right = Object::Line {
+    let right = Line {
         x0: Sample::Constant(0.0),
         y0: Sample::Constant(width),
         dx: Sample::Constant(0.0),
         dy: Sample::Constant(height),
-        material: Box::new(wall_m.clone()),
+        material: Box::new(wall_m),
     };
 
-    let floor = Object::Line {
+    let floor = Line {
         x0: Sample::Constant(0.0),
         y0: Sample::Constant(height * 0.72),
         dx: Sample::Constant(width),
         dy: Sample::Range(70.0, -20.0),
-        material: Box::new(floor_m.clone()),
+        material: Box::new(floor_m),
     };
 
     println!("Tracing Rays!");
@@ -109,7 +109,7 @@ fn main() {
     println!("Saving!");
     let path = Path::new(r"laser-rainbow.png");
     let file = File::create(path).unwrap();
-    let ref mut w = BufWriter::new(file);
+    let w = &mut BufWriter::new(file);
 
     let mut encoder = png::Encoder::new(w, width as u32, height as u32);
     encoder.set(png::ColorType::RGB).set(png::BitDepth::Eight);
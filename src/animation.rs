@@ -0,0 +1,238 @@
+//! Keyframed animation of scene parameters.
+//!
+//! A `Timeline` holds a sorted list of `Keyframe`s and is used through
+//! `Sample::keyframes()`, so any parameter of a light or object can change over time.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// How the value moves from one keyframe to the next.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Interpolation {
+    /// Hold the value until the next keyframe.
+    Step,
+    /// Move at a constant rate.
+    Linear,
+    /// Accelerate out of the keyframe and slow into the next one (smoothstep).
+    EaseInOut,
+    /// A cubic bezier easing curve from (0, 0) to (1, 1), with control points
+    /// `(x1, y1)` and `(x2, y2)`, as used by CSS `cubic-bezier()`.
+    ///
+    /// `x1` and `x2` must be within 0.0 to 1.0, `y1` and `y2` may go outside
+    /// that range to overshoot.
+    Bezier(f64, f64, f64, f64),
+}
+
+/// A single value at a point in time.
+///
+/// `interpolation` defines how the value moves towards the following keyframe.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Keyframe {
+    /// Time of this keyframe, in whatever units the scene is rendered with.
+    pub time: f64,
+    /// Value at this keyframe.
+    pub value: f64,
+    /// Curve used between this keyframe and the next.
+    pub interpolation: Interpolation,
+}
+
+/// A set of keyframes sorted by time, build this with `Sample::keyframes()`.
+///
+/// Before the first keyframe the value of the first is held,
+/// after the last keyframe the value of the last is held.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Timeline {
+    keys: Vec<Keyframe>,
+}
+
+impl Timeline {
+    pub(crate) fn new(keys: &[Keyframe]) -> Self {
+        Self::try_new(keys).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// `new()`, returning why the keyframes are invalid instead of panicking.
+    pub(crate) fn try_new(keys: &[Keyframe]) -> Result<Self, &'static str> {
+        if keys.is_empty() {
+            return Err("A timeline needs at least one keyframe");
+        }
+        if keys.iter().any(|k| k.time.is_nan()) {
+            return Err("Keyframe time is NaN");
+        }
+        let mut keys = keys.to_vec();
+        keys.sort_by(|a, b| a.time.partial_cmp(&b.time).expect("Keyframe times were checked"));
+        Ok(Timeline { keys })
+    }
+
+    /// Keyframes of the timeline, sorted by time.
+    #[cfg(feature = "serde")]
+    pub(crate) fn keys(&self) -> &[Keyframe] {
+        &self.keys
+    }
+
+    /// Value of the timeline at the given time.
+    pub fn value_at(&self, time: f64) -> f64 {
+        let i = self.keys.partition_point(|k| k.time <= time);
+        if i == 0 {
+            return self.keys[0].value;
+        }
+        if i == self.keys.len() {
+            return self.keys[i - 1].value;
+        }
+        let (a, b) = (&self.keys[i - 1], &self.keys[i]);
+        let t = (time - a.time) / (b.time - a.time);
+        a.value + (b.value - a.value) * a.interpolation.progress(t)
+    }
+
+    /// Upper and lower bounds of the value over all time.
+    pub fn bounds(&self) -> (f64, f64) {
+        let mut upper = f64::NEG_INFINITY;
+        let mut lower = f64::INFINITY;
+        for k in &self.keys {
+            upper = upper.max(k.value);
+            lower = lower.min(k.value);
+        }
+        // Bezier curves can overshoot, their control points bound them.
+        for w in self.keys.windows(2) {
+            if let Interpolation::Bezier(_, y1, _, y2) = w[0].interpolation {
+                let d = w[1].value - w[0].value;
+                for p in &[y1, y2] {
+                    let v = w[0].value + d * p;
+                    upper = upper.max(v);
+                    lower = lower.min(v);
+                }
+            }
+        }
+        (upper, lower)
+    }
+}
+
+impl Interpolation {
+    /// Maps linear progress between two keyframes (0.0 to 1.0) to eased progress.
+    fn progress(&self, t: f64) -> f64 {
+        match self {
+            Interpolation::Step => 0.0,
+            Interpolation::Linear => t,
+            Interpolation::EaseInOut => t * t * (3.0 - 2.0 * t),
+            Interpolation::Bezier(x1, y1, x2, y2) => {
+                let s = bezier_solve(*x1, *x2, t);
+                bezier(*y1, *y2, s)
+            }
+        }
+    }
+}
+
+/// One axis of a cubic bezier running from 0.0 to 1.0 with control values `c1` and `c2`.
+fn bezier(c1: f64, c2: f64, s: f64) -> f64 {
+    let inv = 1.0 - s;
+    3.0 * inv * inv * s * c1 + 3.0 * inv * s * s * c2 + s * s * s
+}
+
+fn bezier_slope(c1: f64, c2: f64, s: f64) -> f64 {
+    let inv = 1.0 - s;
+    3.0 * inv * inv * c1 + 6.0 * inv * s * (c2 - c1) + 3.0 * s * s * (1.0 - c2)
+}
+
+/// Finds the curve parameter where the x axis reaches `t`.
+///
+/// Newton's method converges in a few steps for sane curves, bisection
+/// is the fallback for flat spots.
+fn bezier_solve(x1: f64, x2: f64, t: f64) -> f64 {
+    let mut s = t;
+    for _ in 0..8 {
+        let err = bezier(x1, x2, s) - t;
+        if err.abs() < 1e-9 {
+            return s;
+        }
+        let slope = bezier_slope(x1, x2, s);
+        if slope.abs() < 1e-6 {
+            break;
+        }
+        s -= err / slope;
+    }
+
+    let (mut lo, mut hi) = (0.0, 1.0);
+    s = t;
+    for _ in 0..50 {
+        if bezier(x1, x2, s) < t {
+            lo = s;
+        } else {
+            hi = s;
+        }
+        s = (lo + hi) / 2.0;
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Interpolation, Keyframe, Timeline};
+
+    fn key(time: f64, value: f64, interpolation: Interpolation) -> Keyframe {
+        Keyframe {
+            time,
+            value,
+            interpolation,
+        }
+    }
+
+    #[test]
+    fn linear_between_keys() {
+        let t = Timeline::new(&[
+            key(1.0, 10.0, Interpolation::Linear),
+            key(3.0, 20.0, Interpolation::Linear),
+        ]);
+        assert_eq!(t.value_at(0.0), 10.0);
+        assert_eq!(t.value_at(2.0), 15.0);
+        assert_eq!(t.value_at(4.0), 20.0);
+        assert_eq!(t.bounds(), (20.0, 10.0));
+    }
+
+    #[test]
+    fn keys_are_sorted() {
+        let t = Timeline::new(&[
+            key(2.0, 5.0, Interpolation::Step),
+            key(0.0, 1.0, Interpolation::Step),
+        ]);
+        assert_eq!(t.value_at(1.0), 1.0);
+        assert_eq!(t.value_at(2.0), 5.0);
+    }
+
+    #[test]
+    fn ease_is_symmetric() {
+        let t = Timeline::new(&[
+            key(0.0, 0.0, Interpolation::EaseInOut),
+            key(1.0, 1.0, Interpolation::EaseInOut),
+        ]);
+        assert_eq!(t.value_at(0.5), 0.5);
+        assert!(t.value_at(0.1) < 0.1);
+        assert!(t.value_at(0.9) > 0.9);
+    }
+
+    #[test]
+    fn bezier_linear_curve() {
+        // Control points on the diagonal make a straight line.
+        let b = Interpolation::Bezier(1.0 / 3.0, 1.0 / 3.0, 2.0 / 3.0, 2.0 / 3.0);
+        for i in 0..=10 {
+            let t = i as f64 / 10.0;
+            assert!((b.progress(t) - t).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn bezier_overshoot_bounds() {
+        let t = Timeline::new(&[
+            key(0.0, 0.0, Interpolation::Bezier(0.3, -0.5, 0.7, 1.5)),
+            key(1.0, 10.0, Interpolation::Linear),
+        ]);
+        let (upper, lower) = t.bounds();
+        for i in 0..=100 {
+            let v = t.value_at(i as f64 / 100.0);
+            assert!(v <= upper);
+            assert!(v >= lower);
+        }
+        assert!(lower < 0.0);
+        assert!(upper > 10.0);
+    }
+}
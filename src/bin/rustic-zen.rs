@@ -0,0 +1,387 @@
+//! Command line renderer for scene files, built with the `cli` feature.
+//!
+//! Renders a JSON scene file, as read by `SceneFile`, to a PNG or Radiance HDR image:
+//! ```text
+//! rustic-zen scene.json --rays 10000000 --exposure 0.6 --output scene.png
+//! ```
+
+extern crate png;
+extern crate rustic_zen;
+
+use png::HasParameters;
+use rustic_zen::{Image, LightDesc, MaterialRegistry, ObjectDesc, SceneFile, ToneMap};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "Usage: rustic-zen <scene.json> [options]
+
+Options:
+  -r, --rays <count>       Number of rays to trace, 1000000 unless --time is given
+  -t, --time <seconds>     Trace rays until this much time has passed
+  -s, --seed <number>      Seed for the render, overrides the scene file's seed
+  -e, --exposure <value>   Exposure of the output, default 0.5
+  -g, --gamma <value>      Gamma of the output, default 2.2
+  -m, --tone-map <curve>   clip, reinhard or exponential, default clip
+  -j, --threads <count>    Number of threads to render with, default every core
+  -o, --output <path>      Image to write, .png or .hdr, default the scene name as .png
+  -h, --help               Show this message";
+
+/// Rays each thread traces between checking the budget and reporting progress.
+const CHUNK: usize = 10_000;
+
+/// Rays traced if neither a ray count or a time is given.
+const DEFAULT_RAYS: usize = 1_000_000;
+
+/// Everything given on the command line.
+#[derive(Debug, PartialEq)]
+struct Options {
+    scene: PathBuf,
+    rays: Option<usize>,
+    time: Option<Duration>,
+    seed: Option<u128>,
+    exposure: f64,
+    gamma: f64,
+    tone_map: ToneMap,
+    threads: usize,
+    output: PathBuf,
+}
+
+impl Options {
+    /// Reads the options from the command line arguments, not including the program name.
+    fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+        let mut scene = None;
+        let mut options = Options {
+            scene: PathBuf::new(),
+            rays: None,
+            time: None,
+            seed: None,
+            exposure: 0.5,
+            gamma: 2.2,
+            tone_map: ToneMap::Clip,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            output: PathBuf::new(),
+        };
+        let mut output = None;
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+            match arg.as_str() {
+                "-r" | "--rays" => options.rays = Some(number(&arg, &value()?)?),
+                "-t" | "--time" => {
+                    let seconds = number(&arg, &value()?)?;
+                    let time = Duration::try_from_secs_f64(seconds)
+                        .map_err(|_| format!("{} must be a finite number of seconds, not negative, got {}", arg, seconds))?;
+                    options.time = Some(time);
+                }
+                "-s" | "--seed" => options.seed = Some(number(&arg, &value()?)?),
+                "-e" | "--exposure" => options.exposure = number(&arg, &value()?)?,
+                "-g" | "--gamma" => options.gamma = number(&arg, &value()?)?,
+                "-m" | "--tone-map" => {
+                    options.tone_map = match value()?.as_str() {
+                        "clip" => ToneMap::Clip,
+                        "reinhard" => ToneMap::Reinhard,
+                        "exponential" => ToneMap::Exponential,
+                        other => return Err(format!("Unknown tone map \"{}\"", other)),
+                    }
+                }
+                "-j" | "--threads" => options.threads = number(&arg, &value()?)?,
+                "-o" | "--output" => output = Some(PathBuf::from(value()?)),
+                "-h" | "--help" => return Err(USAGE.to_string()),
+                _ if arg.starts_with('-') => return Err(format!("Unknown option {}", arg)),
+                _ if scene.is_none() => scene = Some(PathBuf::from(arg)),
+                _ => return Err(format!("Only one scene can be rendered, got {}", arg)),
+            }
+        }
+
+        options.scene = scene.ok_or_else(|| "No scene file given".to_string())?;
+        options.output = output.unwrap_or_else(|| options.scene.with_extension("png"));
+        if options.rays.is_none() && options.time.is_none() {
+            options.rays = Some(DEFAULT_RAYS);
+        }
+        if options.threads == 0 {
+            return Err("At least one thread is needed".to_string());
+        }
+        if options.exposure.is_nan() || options.exposure < 0.0 {
+            return Err("Exposure must not be negative".to_string());
+        }
+        if options.gamma <= 0.0 {
+            return Err("Gamma must be positive".to_string());
+        }
+        Ok(options)
+    }
+}
+
+/// Parses the value of option `name`.
+fn number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} expects a number, got \"{}\"", name, value))
+}
+
+/// Seed for chunk `n` of the render, so every chunk traces different rays.
+fn chunk_seed(seed: u128, n: usize) -> u128 {
+    let s = seed ^ (n as u128 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15_F39C_C060_5CED_C835);
+    // PCG can't be seeded with 0.
+    if s == 0 {
+        1
+    } else {
+        s
+    }
+}
+
+/// Counts the lights in the scene, including those held by groups.
+fn count_lights(lights: &[LightDesc], objects: &[ObjectDesc]) -> usize {
+    lights.len()
+        + objects
+            .iter()
+            .map(|o| match o {
+                ObjectDesc::Group { objects, lights, .. } => count_lights(lights, objects),
+                _ => 0,
+            })
+            .sum::<usize>()
+}
+
+/// Renders the scene on every thread in chunks, until the ray count or time runs out.
+fn render(file: SceneFile, options: &Options) -> Result<Option<Image>, Box<dyn Error>> {
+    let file = Arc::new(file);
+    let next_chunk = Arc::new(AtomicUsize::new(0));
+    let traced = Arc::new(AtomicUsize::new(0));
+    let seed = options.seed.or(file.seed).unwrap_or(1);
+    let start = Instant::now();
+
+    let workers: Vec<_> = (0..options.threads)
+        .map(|_| {
+            let (file, next_chunk, traced) = (file.clone(), next_chunk.clone(), traced.clone());
+            let (total, budget) = (options.rays, options.time);
+            thread::spawn(move || {
+                // Scenes can't be shared between threads, so each builds its own once
+                // and reseeds it for every chunk.
+                let mut scene = file
+                    .to_scene(&MaterialRegistry::new())
+                    .expect("Scene was checked before rendering");
+                let mut image: Option<Image> = None;
+                loop {
+                    if budget.is_some_and(|b| start.elapsed() >= b) {
+                        break;
+                    }
+                    let n = next_chunk.fetch_add(1, Ordering::SeqCst);
+                    let rays = match total {
+                        Some(total) if n * CHUNK >= total => break,
+                        Some(total) => CHUNK.min(total - n * CHUNK),
+                        None => CHUNK,
+                    };
+                    scene = scene.with_seed(chunk_seed(seed, n));
+                    let part = scene.render_at(0.0, rays);
+                    traced.fetch_add(rays, Ordering::SeqCst);
+                    match image {
+                        Some(ref mut i) => i.merge(&part),
+                        None => image = Some(part),
+                    }
+                }
+                image
+            })
+        })
+        .collect();
+
+    while workers.iter().any(|w| !w.is_finished()) {
+        thread::sleep(Duration::from_millis(250));
+        let done = traced.load(Ordering::SeqCst);
+        let elapsed = start.elapsed().as_secs_f64();
+        let progress = match (options.rays, options.time) {
+            (Some(total), _) => format!("{:5.1}%", 100.0 * done as f64 / total as f64),
+            (None, Some(budget)) => format!("{:5.1}%", 100.0 * (elapsed / budget.as_secs_f64()).min(1.0)),
+            (None, None) => unreachable!("Options always set a ray count or time"),
+        };
+        eprint!("\r{} {} rays in {:.1}s", progress, done, elapsed);
+        let _ = io::stderr().flush();
+    }
+    eprintln!();
+
+    let mut image: Option<Image> = None;
+    for w in workers {
+        if let Some(part) = w.join().map_err(|_| "A render thread panicked")? {
+            match image {
+                Some(ref mut i) => i.merge(&part),
+                None => image = Some(part),
+            }
+        }
+    }
+    Ok(image)
+}
+
+/// Writes the image as an 8 bit PNG, with the tone map and gamma applied.
+fn write_png(image: &Image, width: usize, height: usize, options: &Options) -> Result<(), Box<dyn Error>> {
+    let data = image.to_rgb8_tone_mapped(options.exposure, 1.0 / options.gamma, options.tone_map);
+    let w = BufWriter::new(File::create(&options.output)?);
+    let mut encoder = png::Encoder::new(w, width as u32, height as u32);
+    encoder.set(png::ColorType::RGB).set(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    Ok(())
+}
+
+/// Writes the image as a linear Radiance HDR file, with only the exposure applied.
+fn write_hdr(image: &Image, width: usize, height: usize, options: &Options) -> Result<(), Box<dyn Error>> {
+    let data = image.to_rgb_f32(options.exposure);
+    let mut w = BufWriter::new(File::create(&options.output)?);
+    write!(w, "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n", height, width)?;
+    for row in data.chunks(width * 3) {
+        let pixels: Vec<[u8; 4]> = row.chunks(3).map(|p| rgbe(p[0], p[1], p[2])).collect();
+        if !(8..0x8000).contains(&width) {
+            // Too narrow or wide for run length encoding, write it flat.
+            for p in pixels.iter() {
+                w.write_all(p)?;
+            }
+            continue;
+        }
+        // Run length encoded scanline, written without runs as it rarely helps noisy renders.
+        w.write_all(&[2, 2, (width >> 8) as u8, (width & 0xFF) as u8])?;
+        for c in 0..4 {
+            for run in pixels.chunks(128) {
+                w.write_all(&[run.len() as u8])?;
+                for p in run {
+                    w.write_all(&[p[c]])?;
+                }
+            }
+        }
+    }
+    w.flush()?;
+    Ok(())
+}
+
+/// Shared exponent encoding of a linear colour used by Radiance HDR files.
+fn rgbe(r: f32, g: f32, b: f32) -> [u8; 4] {
+    let v = r.max(g).max(b);
+    if v < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+    // v = m * 2^e with m in [0.5, 1)
+    let e = v.log2().floor() as i32 + 1;
+    let scale = 256.0 / 2f32.powi(e);
+    let byte = |c: f32| (c * scale).min(255.0) as u8;
+    [byte(r), byte(g), byte(b), (e + 128) as u8]
+}
+
+fn run(options: &Options) -> Result<(), Box<dyn Error>> {
+    let json = fs::read_to_string(&options.scene)
+        .map_err(|e| format!("Can't read {}: {}", options.scene.display(), e))?;
+    let file = SceneFile::from_json(&json)?;
+    // Building the scene once up front reports problems before any threads start.
+    file.to_scene(&MaterialRegistry::new())?;
+    let (width, height) = file.resolution;
+    if width == 0 || height == 0 {
+        return Err("The scene's resolution must not be zero".into());
+    }
+    if count_lights(&file.lights, &file.objects) == 0 {
+        return Err("The scene has no lights, so would render black".into());
+    }
+
+    let extension = options
+        .output
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+    let write = match extension.as_deref() {
+        Some("png") => write_png,
+        Some("hdr") => write_hdr,
+        _ => return Err(format!("Can't write {}, use a .png or .hdr file", options.output.display()).into()),
+    };
+
+    let image = render(file, options)?.ok_or("No rays were traced")?;
+    write(&image, width, height, options)?;
+    eprintln!("Wrote {}", Path::new(&options.output).display());
+    Ok(())
+}
+
+fn main() {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(o) => o,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
+        }
+    };
+    if let Err(e) = run(&options) {
+        eprintln!("rustic-zen: {}", e);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chunk_seed, count_lights, rgbe, Options};
+    use rustic_zen::{SceneFile, ToneMap};
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    fn parse(args: &str) -> Result<Options, String> {
+        Options::parse(args.split_whitespace().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn parses_options() {
+        let o = parse("scenes/dawn.json -r 5000 --seed 7 -e 0.7 -g 1.8 -m reinhard -j 3").unwrap();
+        assert_eq!(o.scene, PathBuf::from("scenes/dawn.json"));
+        assert_eq!(o.output, PathBuf::from("scenes/dawn.png"));
+        assert_eq!((o.rays, o.time, o.seed), (Some(5000), None, Some(7)));
+        assert_eq!((o.exposure, o.gamma, o.tone_map, o.threads), (0.7, 1.8, ToneMap::Reinhard, 3));
+
+        let o = parse("--time 2.5 scene.json -o out.hdr").unwrap();
+        assert_eq!((o.rays, o.time), (None, Some(Duration::from_millis(2500))));
+        assert_eq!(o.output, PathBuf::from("out.hdr"));
+
+        assert_eq!(parse("scene.json").unwrap().rays, Some(super::DEFAULT_RAYS));
+        assert!(parse("").is_err());
+        assert!(parse("scene.json -r").is_err());
+        assert!(parse("scene.json -r lots").is_err());
+        assert!(parse("scene.json -m sepia").is_err());
+        assert!(parse("scene.json -j 0").is_err());
+        assert!(parse("scene.json -t -1").is_err());
+        assert!(parse("scene.json -t inf").is_err());
+        assert!(parse("scene.json -e -0.5").is_err());
+    }
+
+    #[test]
+    fn encodes_rgbe() {
+        assert_eq!(rgbe(0.0, 0.0, 0.0), [0, 0, 0, 0]);
+        assert_eq!(rgbe(1.0, 0.5, 0.25), [128, 64, 32, 129]);
+        assert_eq!(rgbe(0.75, 0.0, 3.0), [48, 0, 192, 130]);
+    }
+
+    #[test]
+    fn chunks_never_share_a_seed() {
+        let seeds: Vec<u128> = (0..100).map(|n| chunk_seed(1, n)).collect();
+        for (i, s) in seeds.iter().enumerate() {
+            assert_ne!(*s, 0);
+            assert!(!seeds[i + 1..].contains(s));
+        }
+    }
+
+    #[test]
+    fn counts_lights_in_groups() {
+        let light = r#"{ "type": "Beam", "x": 0.0, "y": 0.0, "direction": 0.0, "width": 1.0, "wavelength": 500.0 }"#;
+        let identity = r#"{ "matrix": { "a1": 1.0, "b1": 0.0, "a2": 0.0, "b2": 1.0 },
+                            "offset": { "x": 0.0, "y": 0.0 } }"#;
+        let group = |objects: &str, lights: &str| {
+            format!(
+                r#"{{ "type": "Group", "transform": {}, "objects": [{}], "lights": [{}] }}"#,
+                identity, objects, lights
+            )
+        };
+        let count = |lights: &str, objects: &str| {
+            let json = format!(r#"{{ "resolution": [10, 10], "lights": [{}], "objects": [{}] }}"#, lights, objects);
+            let file = SceneFile::from_json(&json).unwrap();
+            count_lights(&file.lights, &file.objects)
+        };
+        assert_eq!(count("", ""), 0);
+        assert_eq!(count(light, &group("", "")), 1);
+        assert_eq!(count("", &group(&group("", light), light)), 2);
+    }
+}
@@ -0,0 +1,219 @@
+//! Bounding volume hierarchy used to find the closest object a ray hits.
+
+use geom::{Point, Rect, Vector};
+use object::{Hit, Object};
+use pcg_rand::Pcg64Fast;
+use std::cmp::Ordering;
+
+/// Most objects tested directly in one leaf.
+const LEAF_SIZE: usize = 4;
+
+/// Node bounds are padded by this, so rays grazing an edge still reach the objects inside.
+const PADDING: f64 = 1e-6;
+
+/// Binary tree of bounding boxes over a list of objects.
+///
+/// The tree only holds indices, so it must be queried with the same list it
+/// was built from. Objects which are themselves collections, such as a `Group`
+/// or an `Instance`, keep their own tree, making a two level hierarchy.
+pub(crate) struct Bvh {
+    nodes: Vec<Node>,
+    order: Vec<usize>,
+}
+
+/// A leaf when `count > 0`, holding `order[first..first + count]`,
+/// otherwise a branch whose children are `nodes[first]` and `nodes[first + 1]`.
+#[derive(Copy, Clone)]
+struct Node {
+    bounds: Rect,
+    first: usize,
+    count: usize,
+}
+
+impl Bvh {
+    /// Builds a tree over the bounds of each object.
+    pub(crate) fn new(objects: &[Box<dyn Object>]) -> Self {
+        let mut items: Vec<(usize, Rect)> = objects.iter().map(|o| o.bounds()).enumerate().collect();
+        let mut bvh = Bvh {
+            nodes: vec![],
+            order: Vec::with_capacity(items.len()),
+        };
+        if !items.is_empty() {
+            bvh.nodes.push(Node {
+                bounds: Rect::null(),
+                first: 0,
+                count: 0,
+            });
+            bvh.build(0, &mut items);
+        }
+        bvh
+    }
+
+    fn build(&mut self, node: usize, items: &mut [(usize, Rect)]) {
+        let bounds = items[1..]
+            .iter()
+            .fold(items[0].1, |b, i| b.union_with(&i.1))
+            .expand(PADDING, PADDING, PADDING, PADDING);
+
+        if items.len() <= LEAF_SIZE {
+            self.nodes[node] = Node {
+                bounds,
+                first: self.order.len(),
+                count: items.len(),
+            };
+            self.order.extend(items.iter().map(|i| i.0));
+            return;
+        }
+
+        // Split at the median along the longer side.
+        let wide = bounds.width() >= bounds.height();
+        let centre = |r: &Rect| {
+            let m = r.midpoint();
+            if wide {
+                m.x
+            } else {
+                m.y
+            }
+        };
+        items.sort_by(|a, b| centre(&a.1).partial_cmp(&centre(&b.1)).unwrap_or(Ordering::Equal));
+
+        let left = self.nodes.len();
+        self.nodes.push(self.nodes[node]);
+        self.nodes.push(self.nodes[node]);
+        self.nodes[node] = Node {
+            bounds,
+            first: left,
+            count: 0,
+        };
+        let (a, b) = items.split_at_mut(items.len() / 2);
+        self.build(left, a);
+        self.build(left + 1, b);
+    }
+
+    /**
+     * Finds the closest hit along the ray from `origin` in direction `dir`.
+     *
+     * Distances are measured in multiples of `dir`, which don't change when the
+     * ray is transformed, and hits closer than `min_t` are ignored. Returns the
+     * distance with the hit.
+     */
+    pub(crate) fn closest_hit<'a>(
+        &self,
+        objects: &'a [Box<dyn Object>],
+        origin: &Point,
+        dir: &Vector,
+        time: f64,
+        rng: &mut Pcg64Fast,
+        min_t: f64,
+    ) -> Option<(f64, Hit<'a>)> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let length_2 = dir.dot(dir);
+        let mut closest: Option<(f64, Hit<'a>)> = None;
+        let mut stack = vec![0];
+        while let Some(n) = stack.pop() {
+            let node = self.nodes[n];
+            let limit = closest.map_or(f64::INFINITY, |(t, _)| t);
+            if !Self::enters(&node.bounds, origin, dir, limit) {
+                continue;
+            }
+            if node.count == 0 {
+                stack.push(node.first);
+                stack.push(node.first + 1);
+                continue;
+            }
+            for &i in self.order[node.first..node.first + node.count].iter() {
+                if let Some(hit) = objects[i].get_hit_after(origin, dir, time, rng, min_t) {
+                    let t = (hit.point - *origin).dot(dir) / length_2;
+                    if t >= min_t && closest.is_none_or(|(c, _)| t < c) {
+                        closest = Some((t, hit));
+                    }
+                }
+            }
+        }
+        closest
+    }
+
+    /// Slab test, does the ray pass through `bounds` before distance `limit`?
+    fn enters(bounds: &Rect, origin: &Point, dir: &Vector, limit: f64) -> bool {
+        let mut near = 0.0f64;
+        let mut far = limit;
+        for &(o, d, lo, hi) in [
+            (origin.x, dir.x, bounds.left(), bounds.right()),
+            (origin.y, dir.y, bounds.top(), bounds.bottom()),
+        ]
+        .iter()
+        {
+            if d == 0.0 {
+                if o < lo || o > hi {
+                    return false;
+                }
+                continue;
+            }
+            let (a, b) = ((lo - o) / d, (hi - o) / d);
+            near = near.max(a.min(b));
+            far = far.min(a.max(b));
+            if near > far {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Bvh;
+    use geom::{Point, Vector};
+    use material::HQZLegacy;
+    use object::{Line, Object};
+    use pcg_rand::Pcg64Fast;
+    use rand::prelude::*;
+    use sampler::Sample;
+
+    #[test]
+    fn finds_closest_of_many() {
+        let mut rng = Pcg64Fast::from_entropy();
+        // A grid of short vertical walls, shuffled so the tree has to sort them.
+        let mut walls: Vec<Box<dyn Object>> = vec![];
+        for i in 0..20 {
+            for j in 0..20 {
+                let (i, j) = ((i * 7) % 20, (j * 13) % 20);
+                walls.push(Box::new(Line {
+                    x0: Sample::Constant(10.0 * i as f64),
+                    y0: Sample::Constant(10.0 * j as f64),
+                    dx: Sample::Constant(0.0),
+                    dy: Sample::Constant(5.0),
+                    material: Box::new(HQZLegacy::default()),
+                }));
+            }
+        }
+        let bvh = Bvh::new(&walls);
+
+        let dir = Vector { x: 2.0, y: 0.0 };
+        let (t, hit) = bvh
+            .closest_hit(&walls, &Point { x: 15.0, y: 42.0 }, &dir, 0.0, &mut rng, 0.0)
+            .expect("Ray should hit a wall");
+        assert_eq!(hit.point, Point { x: 20.0, y: 42.0 });
+        assert_eq!(t, 2.5);
+
+        // Starting on a wall, it is skipped when closer than min_t.
+        let (_, hit) = bvh
+            .closest_hit(&walls, &Point { x: 20.0, y: 42.0 }, &dir, 0.0, &mut rng, 1.0)
+            .expect("Ray should hit the next wall");
+        assert_eq!(hit.point, Point { x: 30.0, y: 42.0 });
+
+        // Between the rows of walls there's nothing to hit.
+        let miss = bvh.closest_hit(&walls, &Point { x: -5.0, y: 47.5 }, &dir, 0.0, &mut rng, 0.0);
+        assert!(miss.is_none());
+    }
+
+    #[test]
+    fn empty_tree_misses() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let bvh = Bvh::new(&[]);
+        let hit = bvh.closest_hit(&[], &Point { x: 0.0, y: 0.0 }, &Vector { x: 1.0, y: 0.0 }, 0.0, &mut rng, 0.0);
+        assert!(hit.is_none());
+    }
+}
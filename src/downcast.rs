@@ -0,0 +1,17 @@
+use std::any::Any;
+
+/// Recovers the concrete type behind a trait object, so scenes built from the
+/// built in types can be saved.
+///
+/// Every `'static` type gets this for free. The module is private, so the
+/// trait can't be named, implemented or called from outside the crate.
+pub trait AsAny {
+    /// Returns `self` as `Any`, to be downcast to its concrete type.
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
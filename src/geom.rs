@@ -2,20 +2,25 @@
 
 use std::ops::{Add, Neg, Sub, Mul};
 use std::option::Option;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 #[derive(PartialOrd, PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Point {
     pub x: f64,
     pub y: f64,
 }
 
 #[derive(PartialOrd, PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Vector {
     pub x: f64,
     pub y: f64,
 }
 
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Matrix {
     pub a1: f64, pub b1: f64,
     pub a2: f64, pub b2: f64,
@@ -118,6 +123,141 @@ impl Matrix {
             } * (1.0/det))
         }
     } 
+
+    pub fn transpose(self) -> Matrix {
+        Matrix {
+            a1: self.a1, b1: self.a2,
+            a2: self.b1, b2: self.b2,
+        }
+    }
+}
+
+/*
+ * Affine transform, a linear map followed by a translation: `matrix * p + offset`
+ *
+ * Transforms are built from the constructors below and combined with `then()`
+ * or `*`, where `a * b` applies `b` first, as with matrices.
+ */
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Transform {
+    pub matrix: Matrix,
+    pub offset: Vector,
+}
+
+impl Transform {
+    pub fn identity() -> Transform {
+        Transform::scaling(1.0, 1.0)
+    }
+
+    pub fn translation(x: f64, y: f64) -> Transform {
+        Transform {
+            offset: Vector { x, y },
+            ..Transform::identity()
+        }
+    }
+
+    /*
+     * Rotates about the origin by `degrees`, clockwise on screen as y points down
+     */
+    pub fn rotation(degrees: f64) -> Transform {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Transform::linear(Matrix {
+            a1: cos, b1: -sin,
+            a2: sin, b2: cos,
+        })
+    }
+
+    pub fn scaling(x: f64, y: f64) -> Transform {
+        Transform::linear(Matrix {
+            a1: x, b1: 0.0,
+            a2: 0.0, b2: y,
+        })
+    }
+
+    /*
+     * Shifts x by `x` times y, and y by `y` times x
+     */
+    pub fn shear(x: f64, y: f64) -> Transform {
+        Transform::linear(Matrix {
+            a1: 1.0, b1: x,
+            a2: y, b2: 1.0,
+        })
+    }
+
+    pub fn linear(matrix: Matrix) -> Transform {
+        Transform {
+            matrix,
+            offset: Vector { x: 0.0, y: 0.0 },
+        }
+    }
+
+    /*
+     * Applies this transform, then `next`
+     */
+    pub fn then(&self, next: &Transform) -> Transform {
+        Transform {
+            matrix: next.matrix * self.matrix,
+            offset: next.apply_vector(&self.offset) + next.offset,
+        }
+    }
+
+    pub fn inverse(&self) -> Option<Transform> {
+        let matrix = self.matrix.inverse()?;
+        Some(Transform {
+            matrix,
+            offset: -(matrix * self.offset),
+        })
+    }
+
+    pub fn apply_point(&self, p: &Point) -> Point {
+        (self.matrix * *p) + self.offset
+    }
+
+    /*
+     * Directions are not translated
+     */
+    pub fn apply_vector(&self, v: &Vector) -> Vector {
+        self.matrix * *v
+    }
+
+    /*
+     * Normals stay perpendicular to their surface under non-uniform scaling and
+     * shearing by using the inverse transpose, the result is *not* normalized
+     */
+    pub fn apply_normal(&self, n: &Vector) -> Vector {
+        match self.matrix.inverse() {
+            Some(m) => m.transpose() * *n,
+            None => *n,
+        }
+    }
+
+    /*
+     * Factor lengths are scaled by, exact for rotations and uniform scaling and
+     * the average over every direction otherwise
+     */
+    pub fn length_scale(&self) -> f64 {
+        let m = self.matrix;
+        (m.a1 * m.b2 - m.b1 * m.a2).abs().sqrt()
+    }
+
+    /*
+     * Smallest rectangle enclosing the transformed rectangle
+     */
+    pub fn apply_rect(&self, r: &Rect) -> Rect {
+        let mut out = Rect::null_at(&self.apply_point(&r.top_left()));
+        out.expand_to_include(&self.apply_point(&r.top_right()));
+        out.expand_to_include(&self.apply_point(&r.bottom_left()));
+        out.expand_to_include(&self.apply_point(&r.bottom_right()));
+        out
+    }
+}
+
+impl Mul<Transform> for Transform {
+    type Output = Transform;
+    fn mul(self, rhs: Transform) -> Transform {
+        rhs.then(&self)
+    }
 }
 
 impl Sub<Vector> for Point {
@@ -160,6 +300,16 @@ impl Add<Point> for Vector {
     }
 }
 
+impl Add<Vector> for Vector {
+    type Output = Vector;
+    fn add(self, rhs: Vector) -> Vector {
+        Vector {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+        }
+    }
+}
+
 impl Sub<Point> for Point {
     type Output = Vector;
     fn sub(self, rhs: Point) -> Vector {
@@ -180,8 +330,8 @@ impl Rect {
     }
 
     pub fn from_points(p1: &Point, p2: &Point) -> Rect {
-        let mut r = Rect::null_at(&p1);
-        r.expand_to_include(&p2);
+        let mut r = Rect::null_at(p1);
+        r.expand_to_include(p2);
         r
     }
 
@@ -195,7 +345,7 @@ impl Rect {
     }
 
     pub fn null() -> Rect {
-        let nan = ::std::f64::NAN;
+        let nan = f64::NAN;
         Rect {
             top_left: Point { x: nan, y: nan },
             bottom_right: Point { x: nan, y: nan },
@@ -296,7 +446,7 @@ impl Rect {
     }
 
     pub fn expanded_by(&self, point: &Point) -> Rect {
-        let mut r = self.clone();
+        let mut r = *self;
         r.expand_to_include(point);
         r
     }
@@ -319,7 +469,7 @@ impl Rect {
             if a < b {
                 return a;
             }
-            return b;
+            b
         }
 
         fn max(a: f64, b: f64) -> f64 {
@@ -332,7 +482,7 @@ impl Rect {
             if a > b {
                 return a;
             }
-            return b;
+            b
         }
 
         self.top_left.x = min(self.top_left.x, point.x);
@@ -343,7 +493,7 @@ impl Rect {
     }
 
     pub fn union_with(&self, other: &Rect) -> Rect {
-        let mut r = self.clone();
+        let mut r = *self;
         r.expand_to_include(&other.top_left);
         r.expand_to_include(&other.bottom_right);
         r
@@ -499,6 +649,17 @@ impl Vector {
         self.x * other.x + self.y * other.y
     }
 
+    /*
+     * Rotates anticlockwise by `radians` (with y down, clockwise on screen)
+     */
+    pub fn rotate(&self, radians: f64) -> Vector {
+        let (sin, cos) = radians.sin_cos();
+        Vector {
+            x: self.x * cos - self.y * sin,
+            y: self.y * cos + self.x * sin,
+        }
+    }
+
     /*
      * Does *not* require 'normal' to already be normalized
      */
@@ -563,10 +724,39 @@ mod tests {
             a2: 0.0, b2: 0.0, 
         };
         let n = m.inverse();
-        if n.is_none() {
-            return;
-        } else {
-            panic!("This should be singular");
-        }
+        assert!(n.is_none(), "This should be singular");
+    }
+
+    #[test]
+    fn transform_compose_and_invert() {
+        use geom::{Point, Transform, Vector};
+
+        let t = Transform::scaling(2.0, 1.0)
+            .then(&Transform::rotation(90.0))
+            .then(&Transform::translation(10.0, 0.0));
+        let p = t.apply_point(&Point { x: 1.0, y: 1.0 });
+        // (1, 1) -> (2, 1) -> (-1, 2) -> (9, 2)
+        assert!(p.close_to(&Point { x: 9.0, y: 2.0 }, 1e-9));
+
+        let back = t.inverse().expect("This should have an inverse").apply_point(&p);
+        assert!(back.close_to(&Point { x: 1.0, y: 1.0 }, 1e-9));
+
+        let m = Transform::translation(10.0, 0.0) * Transform::rotation(90.0);
+        let q = m.apply_point(&Point { x: 1.0, y: 0.0 });
+        assert!(q.close_to(&Point { x: 10.0, y: 1.0 }, 1e-9));
+
+        assert!(Transform::scaling(0.0, 1.0).inverse().is_none());
+        assert_eq!(t.apply_vector(&Vector { x: 0.0, y: 0.0 }), Vector { x: 0.0, y: 0.0 });
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn transform_normals_stay_perpendicular() {
+        use geom::{Transform, Vector};
+
+        let t = Transform::shear(0.7, 0.0).then(&Transform::scaling(3.0, 0.5));
+        let along = Vector { x: 1.0, y: 2.0 };
+        let normal = Vector { x: -2.0, y: 1.0 };
+        let d = t.apply_vector(&along).dot(&t.apply_normal(&normal));
+        assert!(d.abs() < 1e-9);
+    }
+}
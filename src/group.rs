@@ -0,0 +1,286 @@
+//! Groups of objects and lights sharing a transform.
+
+use bvh::Bvh;
+use geom::{Point, Rect, Transform, Vector};
+use light::{Emission, Emitter};
+use object::{length_of, surface_of, Hit, Object};
+use pcg_rand::Pcg64Fast;
+use ray::SELF_HIT_DISTANCE;
+use sequence::DIMENSIONS;
+use std::cell::OnceCell;
+
+/// A set of objects and lights moved together by a `Transform`.
+///
+/// Children are defined in the group's own coordinates, rays are transformed
+/// into those coordinates to be tested against them, and hits transformed
+/// back. Groups can be nested, so a motif can be built once and repeated.
+///
+/// # Example
+/// Ten mirrors fanned around a point:
+/// ```
+/// use rustic_zen::geom::Transform;
+/// use rustic_zen::prelude::{Group, HQZLegacy, Line, Sample, Scene};
+///
+/// let mut scene = Scene::new(1000, 1000);
+/// for i in 0..10 {
+///     let mirror = Line {
+///         x0: Sample::Constant(100.0),
+///         y0: Sample::Constant(-20.0),
+///         dx: Sample::Constant(0.0),
+///         dy: Sample::Constant(40.0),
+///         material: Box::new(HQZLegacy::new(0.0, 1.0, 0.0)),
+///     };
+///     let place = Transform::rotation(36.0 * i as f64).then(&Transform::translation(500.0, 500.0));
+///     scene = scene.with_group(Group::new(place).with_object(mirror));
+/// }
+/// ```
+pub struct Group {
+    pub(crate) transform: Transform,
+    inverse: Transform,
+    pub(crate) objects: Vec<Box<dyn Object>>,
+    lights: Vec<Box<dyn Emitter>>,
+    bvh: OnceCell<Bvh>,
+}
+
+impl Group {
+    /// Creates an empty group placed by `transform`.
+    ///
+    /// Panics if the transform can't be inverted, such as a scale of zero.
+    pub fn new(transform: Transform) -> Self {
+        let inverse = transform
+            .inverse()
+            .expect("Group transform must be invertible");
+        Self {
+            transform,
+            inverse,
+            objects: vec![],
+            lights: vec![],
+            bvh: OnceCell::new(),
+        }
+    }
+
+    /// Adds an object to the group - Chainable varient
+    pub fn with_object<O: Object + 'static>(self, object: O) -> Self {
+        self.with_boxed_object(Box::new(object))
+    }
+
+    /// Adds an already boxed object to the group - Chainable varient
+    pub(crate) fn with_boxed_object(mut self, object: Box<dyn Object>) -> Self {
+        self.objects.push(object);
+        self
+    }
+
+    /// Adds a light to the group - Chainable varient
+    ///
+    /// The light's power is unchanged by the transform.
+    pub fn with_light<E: Emitter + 'static>(self, light: E) -> Self {
+        self.with_boxed_light(Box::new(light))
+    }
+
+    /// Adds an already boxed light to the group - Chainable varient
+    pub(crate) fn with_boxed_light(mut self, light: Box<dyn Emitter>) -> Self {
+        self.lights.push(light);
+        self
+    }
+
+    /// Adds a group inside this group, with its lights - Chainable varient
+    ///
+    /// The child's transform is applied first, then this group's. A group of
+    /// only lights adds no geometry.
+    pub fn with_group(mut self, mut group: Group) -> Self {
+        for light in group.lights.drain(..) {
+            self.lights.push(Box::new(Transformed {
+                transform: group.transform,
+                light,
+            }));
+        }
+        if !group.objects.is_empty() {
+            self.objects.push(Box::new(group));
+        }
+        self
+    }
+
+    /// Splits off the group's lights, transformed into scene coordinates.
+    pub(crate) fn take_lights(&mut self) -> Vec<Box<dyn Emitter>> {
+        let transform = self.transform;
+        self.lights
+            .drain(..)
+            .map(|light| Box::new(Transformed { transform, light }) as Box<dyn Emitter>)
+            .collect()
+    }
+}
+
+impl Object for Group {
+    fn get_hit(&self, origin: &Point, dir: &Vector, time: f64, rng: &mut Pcg64Fast) -> Option<Hit<'_>> {
+        // Skip the surface a ray is leaving, so it doesn't hide the rest of the group.
+        self.get_hit_after(origin, dir, time, rng, SELF_HIT_DISTANCE / dir.magnitude())
+    }
+
+    fn get_hit_after(
+        &self,
+        origin: &Point,
+        dir: &Vector,
+        time: f64,
+        rng: &mut Pcg64Fast,
+        min_t: f64,
+    ) -> Option<Hit<'_>> {
+        let bvh = self.bvh.get_or_init(|| Bvh::new(&self.objects));
+        let (_, hit) = bvh.closest_hit(
+            &self.objects,
+            &self.inverse.apply_point(origin),
+            &self.inverse.apply_vector(dir),
+            time,
+            rng,
+            min_t,
+        )?;
+        Some(Hit {
+            point: self.transform.apply_point(&hit.point),
+            normal: self.transform.apply_normal(&hit.normal),
+            ..hit
+        })
+    }
+
+    fn bounds(&self) -> Rect {
+        let mut bounds: Option<Rect> = None;
+        for o in self.objects.iter() {
+            let b = self.transform.apply_rect(&o.bounds());
+            bounds = Some(bounds.map_or(b, |r| r.union_with(&b)));
+        }
+        bounds.unwrap_or_else(|| Rect::null_at(&self.transform.apply_point(&Point { x: 0.0, y: 0.0 })))
+    }
+
+    fn surface_at(&self, alpha: f64, time: f64, rng: &mut Pcg64Fast) -> Option<Hit<'_>> {
+        let hit = surface_of(&self.objects, alpha, time, rng)?;
+        Some(Hit {
+            point: self.transform.apply_point(&hit.point),
+            normal: self.transform.apply_normal(&hit.normal),
+            ..hit
+        })
+    }
+
+    fn length(&self, time: f64) -> f64 {
+        length_of(&self.objects, time) * self.transform.length_scale()
+    }
+}
+
+/// A light moved by a group's transform.
+pub(crate) struct Transformed {
+    pub(crate) transform: Transform,
+    pub(crate) light: Box<dyn Emitter>,
+}
+
+impl Emitter for Transformed {
+    fn power(&self, time: f64) -> f64 {
+        self.light.power(time)
+    }
+
+    fn emit(&self, time: f64, u: &[f64; DIMENSIONS], rng: &mut Pcg64Fast) -> Emission {
+        let e = self.light.emit(time, u, rng);
+        Emission {
+            origin: self.transform.apply_point(&e.origin),
+            direction: self.transform.apply_vector(&e.direction).normalized(),
+            ..e
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Group;
+    use geom::{Point, Transform, Vector};
+    use light::Light;
+    use material::HQZLegacy;
+    use object::{Line, Object};
+    use pcg_rand::Pcg64Fast;
+    use rand::prelude::*;
+    use sampler::Sample;
+    use sequence::DIMENSIONS;
+
+    fn wall(x: f64) -> Line {
+        Line {
+            x0: Sample::Constant(x),
+            y0: Sample::Constant(-10.0),
+            dx: Sample::Constant(0.0),
+            dy: Sample::Constant(20.0),
+            material: Box::new(HQZLegacy::default()),
+        }
+    }
+
+    #[test]
+    fn hits_in_local_space() {
+        let mut rng = Pcg64Fast::from_entropy();
+        // A wall at local x = 10, moved to world x = 110 and stretched along x.
+        let g = Group::new(Transform::scaling(2.0, 1.0).then(&Transform::translation(90.0, 0.0)))
+            .with_object(wall(10.0))
+            .with_object(wall(20.0));
+
+        let origin = Point { x: 0.0, y: 0.0 };
+        let dir = Vector { x: 1.0, y: 0.0 };
+        let hit = g.get_hit(&origin, &dir, 0.0, &mut rng).expect("Ray should hit the group");
+        assert!(hit.point.close_to(&Point { x: 110.0, y: 0.0 }, 1e-9));
+        assert_eq!(hit.alpha, 0.5);
+
+        // Leaving the first wall, the ray carries on to the second.
+        let hit = g.get_hit(&hit.point, &dir, 0.0, &mut rng).expect("Ray should hit the second wall");
+        assert!(hit.point.close_to(&Point { x: 130.0, y: 0.0 }, 1e-9));
+
+        let b = g.bounds();
+        assert_eq!((b.left(), b.right(), b.top(), b.bottom()), (110.0, 130.0, -10.0, 10.0));
+    }
+
+    #[test]
+    fn nested_groups_skip_by_world_distance() {
+        let mut rng = Pcg64Fast::from_entropy();
+        // Walls 0.1 apart locally, 10 apart once the outer group scales them up.
+        let inner = Group::new(Transform::scaling(0.1, 0.1))
+            .with_object(wall(1.0))
+            .with_object(wall(2.0));
+        let outer = Group::new(Transform::scaling(100.0, 100.0)).with_group(inner);
+
+        let dir = Vector { x: 1.0, y: 0.0 };
+        let hit = outer
+            .get_hit(&Point { x: 0.0, y: 0.0 }, &dir, 0.0, &mut rng)
+            .expect("Ray should hit the first wall");
+        assert!(hit.point.close_to(&Point { x: 10.0, y: 0.0 }, 1e-9));
+        let hit = outer
+            .get_hit(&hit.point, &dir, 0.0, &mut rng)
+            .expect("Ray should hit the second wall");
+        assert!(hit.point.close_to(&Point { x: 20.0, y: 0.0 }, 1e-9));
+    }
+
+    #[test]
+    fn normals_follow_rotation() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let g = Group::new(Transform::rotation(90.0)).with_object(wall(10.0));
+        // The wall now runs along y = 10
+        let hit = g
+            .get_hit(&Point { x: 0.0, y: 0.0 }, &Vector { x: 0.0, y: 1.0 }, 0.0, &mut rng)
+            .expect("Ray should hit the rotated wall");
+        assert!(hit.point.close_to(&Point { x: 0.0, y: 10.0 }, 1e-9));
+        let n = hit.normal.normalized();
+        assert!(n.x.abs() < 1e-9 && (n.y.abs() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn nested_lights_are_transformed() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let light = Light {
+            power: Sample::Constant(2.0),
+            x: Sample::Constant(1.0),
+            y: Sample::Constant(0.0),
+            polar_angle: Sample::Constant(0.0),
+            polar_distance: Sample::Constant(0.0),
+            ray_angle: Sample::Constant(0.0),
+            wavelength: Sample::Constant(500.0),
+        };
+        let inner = Group::new(Transform::rotation(90.0)).with_light(light);
+        let mut outer = Group::new(Transform::translation(100.0, 0.0)).with_group(inner);
+        let lights = outer.take_lights();
+        assert_eq!(lights.len(), 1);
+        assert_eq!(lights[0].power(0.0), 2.0);
+
+        let e = lights[0].emit(0.0, &[0.5; DIMENSIONS], &mut rng);
+        assert!(e.origin.close_to(&Point { x: 100.0, y: 1.0 }, 1e-9));
+        assert!(e.direction.x.abs() < 1e-9 && (e.direction.y - 1.0).abs() < 1e-9);
+    }
+}
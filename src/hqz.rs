@@ -0,0 +1,272 @@
+//! Export of scenes to the JSON format read by the original HQZ renderer.
+//!
+//! HQZ only knows its own lights, line segments and materials, so only `Light`s,
+//! `Line`s, `Curve`s and `HQZLegacy` materials carry over. Anything else in the
+//! scene is left out or approximated, and listed in `HqzExport::unsupported`.
+
+use geom::Rect;
+use light::{Emitter, Light};
+use material::{HQZLegacy, Material};
+use object::{Curve, Line, Object};
+use sampler::Sample;
+use serde_json::{self, json, Value};
+
+/// A scene written in HQZ's JSON schema, made by `Scene::to_hqz()`.
+#[derive(Clone, Debug)]
+pub struct HqzExport {
+    /// The scene as HQZ JSON.
+    pub json: String,
+    /// Descriptions of everything in the scene which HQZ can't represent,
+    /// empty if the export is exact.
+    pub unsupported: Vec<String>,
+}
+
+/// Builds up an `HqzExport` from the parts of a scene.
+pub(crate) struct HqzWriter {
+    root: Value,
+    lights: Vec<Value>,
+    objects: Vec<Value>,
+    materials: Vec<Value>,
+    /// Coefficients of each material written, so objects can share them.
+    material_keys: Vec<Option<(f64, f64, f64)>>,
+    unsupported: Vec<String>,
+}
+
+impl HqzWriter {
+    pub(crate) fn new(resolution: (usize, usize), viewport: &Rect, rays: usize, exposure: f64) -> Self {
+        Self {
+            root: json!({
+                "resolution": [resolution.0, resolution.1],
+                "viewport": [viewport.left(), viewport.top(), viewport.width(), viewport.height()],
+                "rays": rays,
+                "exposure": exposure,
+            }),
+            lights: vec![],
+            objects: vec![],
+            materials: vec![],
+            material_keys: vec![],
+            unsupported: vec![],
+        }
+    }
+
+    /// Notes a feature of the scene which can't be exported.
+    pub(crate) fn unsupported(&mut self, description: String) {
+        self.unsupported.push(description);
+    }
+
+    pub(crate) fn light(&mut self, index: usize, light: &dyn Emitter) {
+        let light = match light.as_any().downcast_ref::<Light>() {
+            Some(l) => l,
+            None => return self.unsupported(format!("Light {} is not a `Light`, left out", index)),
+        };
+        let name = |field: &str| format!("Light {} {}", index, field);
+        let light = json!([
+            self.sample(&light.power, name("power")),
+            self.sample(&light.x, name("x")),
+            self.sample(&light.y, name("y")),
+            self.sample(&light.polar_angle, name("polar_angle")),
+            self.sample(&light.polar_distance, name("polar_distance")),
+            self.sample(&light.ray_angle, name("ray_angle")),
+            self.sample(&light.wavelength, name("wavelength")),
+        ]);
+        self.lights.push(light);
+    }
+
+    pub(crate) fn object(&mut self, index: usize, object: &dyn Object) {
+        let object = object.as_any();
+        let name = |field: &str| format!("Object {} {}", index, field);
+        let value = if let Some(l) = object.downcast_ref::<Line>() {
+            json!([
+                self.material(index, l.material.as_ref()),
+                self.sample(&l.x0, name("x0")),
+                self.sample(&l.y0, name("y0")),
+                self.sample(&l.dx, name("dx")),
+                self.sample(&l.dy, name("dy")),
+            ])
+        } else if let Some(c) = object.downcast_ref::<Curve>() {
+            json!([
+                self.material(index, c.material.as_ref()),
+                self.sample(&c.x0, name("x0")),
+                self.sample(&c.y0, name("y0")),
+                self.sample(&c.a0, name("a0")),
+                self.sample(&c.dx, name("dx")),
+                self.sample(&c.dy, name("dy")),
+                self.sample(&c.da, name("da")),
+            ])
+        } else {
+            return self.unsupported(format!("Object {} is not a `Line` or `Curve`, left out", index));
+        };
+        self.objects.push(value);
+    }
+
+    pub(crate) fn finish(mut self) -> HqzExport {
+        self.root["lights"] = Value::Array(self.lights);
+        self.root["objects"] = Value::Array(self.objects);
+        self.root["materials"] = Value::Array(self.materials);
+        HqzExport {
+            json: serde_json::to_string_pretty(&self.root).expect("HQZ scenes always serialise"),
+            unsupported: self.unsupported,
+        }
+    }
+
+    /// Index of `material` in the HQZ material list, adding it if it's new.
+    ///
+    /// Materials other than `HQZLegacy` become a material which absorbs every
+    /// ray, so the object still casts its shadow.
+    fn material(&mut self, index: usize, material: &dyn Material) -> usize {
+        let key = match material.as_any().downcast_ref::<HQZLegacy>() {
+            Some(m) => {
+                if m.lambertian {
+                    self.unsupported(format!(
+                        "Object {} has lambertian diffuse reflection, exported as HQZ's diffuse",
+                        index
+                    ));
+                }
+                Some((m.d, m.r, m.t))
+            }
+            None => {
+                self.unsupported(format!("Object {} material is not `HQZLegacy`, exported as absorbing", index));
+                None
+            }
+        };
+        if let Some(i) = self.material_keys.iter().position(|k| *k == key) {
+            return i;
+        }
+        let outcomes: Vec<Value> = match key {
+            Some((d, r, t)) => [(d, "d"), (r, "r"), (t, "t")]
+                .iter()
+                .filter(|(p, _)| *p > 0.0)
+                .map(|(p, kind)| json!([p, kind]))
+                .collect(),
+            None => vec![],
+        };
+        self.materials.push(Value::Array(outcomes));
+        self.material_keys.push(key);
+        self.material_keys.len() - 1
+    }
+
+    /// HQZ notation for a sample: a number, `[min, max]` or `[temperature, "K"]`.
+    ///
+    /// Other distributions are written as their mean.
+    fn sample(&mut self, sample: &Sample, name: String) -> Value {
+        let kind = match sample {
+            Sample::Constant(c) => return json!(c),
+            Sample::Range(max, min) => return json!([min, max]),
+            Sample::Blackbody(k) => return json!([k, "K"]),
+            Sample::Normal(..) => "normal",
+            Sample::TruncatedNormal(..) => "truncated normal",
+            Sample::Discrete(..) => "discrete",
+            Sample::Piecewise(..) => "piecewise",
+            Sample::Exponential(..) => "exponential",
+            Sample::Keyframes(..) => "keyframed",
+        };
+        self.unsupported(format!("{} is a {} sample, exported as its mean", name, kind));
+        json!(sample.expected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use light::{Beam, Light};
+    use material::{HQZLegacy, Lambertian};
+    use object::{Curve, Line};
+    use sampler::Sample;
+    use scene::Scene;
+    use serde_json::{self, json, Value};
+
+    fn line(x0: f64, material: HQZLegacy) -> Line {
+        Line {
+            x0: Sample::Constant(x0),
+            y0: Sample::Range(20.0, 10.0),
+            dx: Sample::Constant(0.0),
+            dy: Sample::Constant(50.0),
+            material: Box::new(material),
+        }
+    }
+
+    #[test]
+    fn exports_hqz_scene() {
+        let scene = Scene::new(200, 100)
+            .with_light(Light {
+                power: Sample::Constant(1.0),
+                x: Sample::Constant(100.0),
+                y: Sample::Constant(50.0),
+                polar_angle: Sample::Constant(0.0),
+                polar_distance: Sample::Constant(0.0),
+                ray_angle: Sample::Range(360.0, 0.0),
+                wavelength: Sample::Blackbody(5800.0),
+            })
+            .with_object(line(10.0, HQZLegacy::new(0.1, 0.5, 0.0)))
+            .with_object(line(20.0, HQZLegacy::new(0.1, 0.5, 0.0)))
+            .with_object(Curve {
+                x0: Sample::Constant(30.0),
+                y0: Sample::Constant(0.0),
+                a0: Sample::Constant(90.0),
+                dx: Sample::Constant(10.0),
+                dy: Sample::Constant(0.0),
+                da: Sample::Constant(45.0),
+                material: Box::new(HQZLegacy::new(0.0, 0.0, 1.0)),
+            });
+        let export = scene.to_hqz(100000, 0.5);
+        assert!(export.unsupported.is_empty(), "{:?}", export.unsupported);
+
+        let hqz: Value = serde_json::from_str(&export.json).unwrap();
+        assert_eq!(hqz["resolution"], json!([200, 100]));
+        assert_eq!(hqz["viewport"], json!([0.0, 0.0, 200.0, 100.0]));
+        assert_eq!(hqz["rays"], json!(100000));
+        assert_eq!(
+            hqz["lights"],
+            json!([[1.0, 100.0, 50.0, 0.0, 0.0, [0.0, 360.0], [5800.0, "K"]]])
+        );
+        assert_eq!(
+            hqz["objects"],
+            json!([
+                [0, 10.0, [10.0, 20.0], 0.0, 50.0],
+                [0, 20.0, [10.0, 20.0], 0.0, 50.0],
+                [1, 30.0, 0.0, 90.0, 10.0, 0.0, 45.0]
+            ])
+        );
+        assert_eq!(hqz["materials"], json!([[[0.1, "d"], [0.5, "r"]], [[1.0, "t"]]]));
+    }
+
+    #[test]
+    fn reports_unsupported_features() {
+        let scene = Scene::new(100, 100)
+            .with_light(Beam::new(
+                Sample::Constant(0.0),
+                Sample::Constant(50.0),
+                Sample::Constant(0.0),
+                10.0,
+                Sample::Constant(500.0),
+            ))
+            .with_light(Light {
+                power: Sample::Constant(1.0),
+                x: Sample::Normal(50.0, 2.0),
+                y: Sample::Constant(50.0),
+                polar_angle: Sample::Constant(0.0),
+                polar_distance: Sample::Constant(0.0),
+                ray_angle: Sample::Range(360.0, 0.0),
+                wavelength: Sample::Constant(0.0),
+            })
+            .with_object(Line {
+                x0: Sample::Constant(90.0),
+                y0: Sample::Constant(0.0),
+                dx: Sample::Constant(0.0),
+                dy: Sample::Constant(100.0),
+                material: Box::new(Lambertian::new(0.5)),
+            });
+        let export = scene.to_hqz(1000, 0.5);
+        assert_eq!(
+            export.unsupported,
+            vec![
+                "Light 0 is not a `Light`, left out",
+                "Light 1 x is a normal sample, exported as its mean",
+                "Object 0 material is not `HQZLegacy`, exported as absorbing",
+            ]
+        );
+
+        let hqz: Value = serde_json::from_str(&export.json).unwrap();
+        assert_eq!(hqz["lights"][0][1], json!(50.0));
+        assert_eq!(hqz["materials"], json!([[]]));
+    }
+}
@@ -4,6 +4,28 @@ use pcg_rand::Pcg64Fast;
 use pcg_rand::seeds::PcgSeeder;
 use rand::prelude::*;
 
+/// Curve mapping the brightness of each pixel to the range the output can show.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ToneMap {
+    /// Brightness is kept as is, anything brighter than white is clipped.
+    Clip,
+    /// Reinhard's curve, `x / (1 + x)`, which compresses highlights
+    /// smoothly towards white so bright beams keep their colour.
+    Reinhard,
+    /// `1 - e^-x`, like film exposure, with a softer toe than `Reinhard`.
+    Exponential,
+}
+
+impl ToneMap {
+    fn map(self, x: f64) -> f64 {
+        match self {
+            ToneMap::Clip => x,
+            ToneMap::Reinhard => x / (1.0 + x),
+            ToneMap::Exponential => 1.0 - f64::exp(-x),
+        }
+    }
+}
+
 /// Represents an image while ray rendering is happening
 /// 
 /// This struct uses floats to represent each pixel of the image so normalisation
@@ -37,6 +59,11 @@ impl Image {
         }
     }
 
+    /// Total light power the image is normalised by.
+    pub(crate) fn light_power(&self) -> f64 {
+        self.lightpower
+    }
+
     #[inline]
     #[doc(hidden)]
     fn plot(&mut self, colour: (f64, f64, f64), pixel: usize, intensity: f64) {
@@ -53,9 +80,9 @@ impl Image {
         };
         let mut p = self.pixels[pixel];
 
-        p.0 = p.0 + (colour.0 * intensity);
-        p.1 = p.1 + (colour.1 * intensity);
-        p.2 = p.2 + (colour.2 * intensity);
+        p.0 += colour.0 * intensity;
+        p.1 += colour.1 * intensity;
+        p.2 += colour.2 * intensity;
 
         self.pixels[pixel] = p;
     }
@@ -65,6 +92,7 @@ impl Image {
     pub fn draw_line(
         &mut self,
         wavelength: f64,
+        intensity: f64,
         mut x0: f64,
         mut y0: f64,
         mut x1: f64,
@@ -80,7 +108,8 @@ impl Image {
          *   We scale the brightness of each pixel to compensate.
          */
 
-        let colour: (f64, f64, f64) = wavelength_to_colour(wavelength);
+        let (r, g, b) = wavelength_to_colour(wavelength);
+        let colour: (f64, f64, f64) = (r * intensity, g * intensity, b * intensity);
         //println!("draw_line [{},{},{}] ({},{}), ({},{})", colour.0, colour.1, colour.2, x0, y0, x1, y1);
         //let s: String = format!("{},{},{},{},{},{},{}\n", colour.0, colour.1, colour.2, x0, y0, x1, y1);
         //self.file.write_all(s.as_bytes()).unwrap();
@@ -169,6 +198,23 @@ impl Image {
         self.rays += 1;
     }
 
+    /// Adds another render of the same scene to this one, as if its rays had
+    /// been traced into this image, so a render can be split between threads.
+    ///
+    /// The renders should use different seeds or they will just repeat each other.
+    /// Panics if the images are not the same size.
+    pub fn merge(&mut self, other: &Image) {
+        if self.width != other.width || self.height != other.height {
+            panic!("Only images of the same size can be merged");
+        }
+        for (p, o) in self.pixels.iter_mut().zip(other.pixels.iter()) {
+            p.0 += o.0;
+            p.1 += o.1;
+            p.2 += o.2;
+        }
+        self.rays += other.rays;
+    }
+
     fn max(a: f64, b: f64) -> f64 {
         if a < b {
             b
@@ -188,15 +234,7 @@ impl Image {
     fn calculate_scale(&self, exposure: f64) -> f64 {
         let area_scale = f64::sqrt((self.width as f64 * self.height as f64) / (1024.0 * 576.0));
         let intensity_scale = self.lightpower / (255.0 * 8192.0);
-        let scale =
-            f64::exp(1.0 + 10.0 * exposure) * area_scale * intensity_scale / self.rays as f64;
-
-        println!(
-            "Image Statistics: raycount = {}, lightpower = {}, scale = {}",
-            self.rays, self.lightpower, scale
-        );
-
-        scale
+        f64::exp(1.0 + 10.0 * exposure) * area_scale * intensity_scale / self.rays as f64
     }
 
     /// Outputs the image. 
@@ -206,38 +244,59 @@ impl Image {
     /// This function also normalises the image applying exposure and gamma. 
     /// gamma is passed in the form of an exponent which is defined as `1.0 / gamma`
     pub fn to_rgb8(&self, exposure: f64, exponent: f64) -> Vec<u8> {
+        self.to_rgb8_tone_mapped(exposure, exponent, ToneMap::Clip)
+    }
+
+    /// Outputs the image as `to_rgb8()` does, passing each channel through
+    /// `tone_map` after exposure and before gamma.
+    pub fn to_rgb8_tone_mapped(&self, exposure: f64, exponent: f64, tone_map: ToneMap) -> Vec<u8> {
         let scale = self.calculate_scale(exposure);
         let mut rng = Pcg64Fast::from_seed(PcgSeeder::seed(0));
         let mut rgb: Vec<u8> = Vec::new();
         for i in self.pixels.iter() {
             // red
-            let u: f64 = Self::max(0.0, i.0.clone() as f64 * scale);
+            let u: f64 = tone_map.map(Self::max(0.0, i.0 * scale));
             let dither = rng.gen_range(0.0f64, 1.0f64);
             let v: f64 = 255.0 * u.powf(exponent) + dither;
             let r8 = Self::max(0.0, Self::min(255.9, v));
             rgb.push(r8 as u8);
 
             // green
-            let u: f64 = Self::max(0.0, i.1.clone() as f64 * scale);
+            let u: f64 = tone_map.map(Self::max(0.0, i.1 * scale));
             let dither = rng.gen_range(0.0f64, 1.0f64);
             let v: f64 = 255.0 * u.powf(exponent) + dither;
             let g8 = Self::max(0.0, Self::min(255.9, v));
             rgb.push(g8 as u8);
 
             // blue
-            let u: f64 = Self::max(0.0, i.2.clone() as f64 * scale);
+            let u: f64 = tone_map.map(Self::max(0.0, i.2 * scale));
             let dither = rng.gen_range(0.0f64, 1.0f64);
             let v: f64 = 255.0 * u.powf(exponent) + dither;
             let b8 = Self::max(0.0, Self::min(255.9, v));
             rgb.push(b8 as u8);
         }
-        return rgb;
+        rgb
+    }
+
+    /// Outputs the image as linear RGB floats, three per pixel, with exposure
+    /// applied but no tone mapping or gamma, suitible for HDR formats.
+    ///
+    /// 1.0 is the brightness `to_rgb8()` would show as white.
+    pub fn to_rgb_f32(&self, exposure: f64) -> Vec<f32> {
+        let scale = self.calculate_scale(exposure);
+        let mut rgb: Vec<f32> = Vec::with_capacity(self.pixels.len() * 3);
+        for i in self.pixels.iter() {
+            rgb.push(Self::max(0.0, i.0 * scale) as f32);
+            rgb.push(Self::max(0.0, i.1 * scale) as f32);
+            rgb.push(Self::max(0.0, i.2 * scale) as f32);
+        }
+        rgb
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Image;
+    use super::{Image, ToneMap};
 
     // For reading and opening files
     use std::fs::File;
@@ -249,9 +308,9 @@ mod tests {
     #[test]
     fn traced_ray_is_not_black() {
         let mut i = Image::new(100, 100, 1.0);
-        i.draw_line(620.0, 10.0, 10.0, 90.0, 90.0); //red
-        i.draw_line(520.0, 20.0, 10.0, 90.0, 80.0); //green
-        i.draw_line(470.0, 10.0, 20.0, 80.0, 90.0); //blue
+        i.draw_line(620.0, 1.0, 10.0, 10.0, 90.0, 90.0); //red
+        i.draw_line(520.0, 1.0, 20.0, 10.0, 90.0, 80.0); //green
+        i.draw_line(470.0, 1.0, 10.0, 20.0, 80.0, 90.0); //blue
         let mut count: f64 = 0.0;
         for p in i.pixels.iter() {
             count += p.0;
@@ -260,7 +319,7 @@ mod tests {
 
         let path = Path::new(r"image.ray_not_black.png");
         let file = File::create(path).unwrap();
-        let ref mut w = BufWriter::new(file);
+        let w = &mut BufWriter::new(file);
 
         let data = i.to_rgb8(0.3, 1.0);
 
@@ -285,4 +344,44 @@ mod tests {
         let v = i.to_rgb8(1.0, 1.0);
         assert_eq!(v.len(), 1920 * 1080 * 3);
     }
+
+    #[test]
+    fn merged_renders_add_rays() {
+        let mut a = Image::new(100, 100, 1.0);
+        a.draw_line(520.0, 1.0, 20.0, 10.0, 90.0, 80.0);
+        let mut b = Image::new(100, 100, 1.0);
+        b.draw_line(620.0, 1.0, 10.0, 10.0, 90.0, 90.0);
+        let mut both = Image::new(100, 100, 1.0);
+        both.draw_line(520.0, 1.0, 20.0, 10.0, 90.0, 80.0);
+        both.draw_line(620.0, 1.0, 10.0, 10.0, 90.0, 90.0);
+
+        a.merge(&b);
+        assert_eq!(a.rays, 2);
+        assert_eq!(a.to_rgb_f32(0.5), both.to_rgb_f32(0.5));
+    }
+
+    #[test]
+    fn tone_maps_keep_highlights() {
+        let mut i = Image::new(100, 100, 1.0);
+        i.draw_line(520.0, 0.0005, 20.0, 10.0, 90.0, 80.0);
+        let clipped = i.to_rgb8(1.0, 1.0);
+        assert_eq!(clipped, i.to_rgb8_tone_mapped(1.0, 1.0, ToneMap::Clip));
+        let saturated = |rgb: &[u8]| rgb.iter().filter(|c| **c == 255).count();
+        for map in &[ToneMap::Reinhard, ToneMap::Exponential] {
+            let mapped = i.to_rgb8_tone_mapped(1.0, 1.0, *map);
+            assert!(saturated(&mapped) < saturated(&clipped) / 2, "{:?} clipped", map);
+            assert!(mapped.iter().zip(clipped.iter()).all(|(m, c)| m <= c));
+        }
+    }
+
+    #[test]
+    fn intensity_scales_line() {
+        let mut full = Image::new(100, 100, 1.0);
+        full.draw_line(520.0, 1.0, 20.0, 10.0, 90.0, 80.0);
+        let mut half = Image::new(100, 100, 1.0);
+        half.draw_line(520.0, 0.5, 20.0, 10.0, 90.0, 80.0);
+        for (f, h) in full.pixels.iter().zip(half.pixels.iter()) {
+            assert_eq!(f.1 * 0.5, h.1);
+        }
+    }
 }
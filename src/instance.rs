@@ -0,0 +1,287 @@
+//! Shared geometry placed many times by lightweight instances.
+
+use bvh::Bvh;
+use geom::{Point, Rect, Transform, Vector};
+use material::Material;
+use object::{length_of, surface_of, Hit, Object};
+use pcg_rand::Pcg64Fast;
+use ray::SELF_HIT_DISTANCE;
+use std::cell::OnceCell;
+use std::rc::Rc;
+
+/// A collection of objects defined once, to be shared between `Instance`s.
+///
+/// The shape keeps its own bounding volume hierarchy, built the first time a
+/// ray is tested against it, so every instance of it reuses the same tree.
+#[derive(Default)]
+pub struct Shape {
+    pub(crate) objects: Vec<Box<dyn Object>>,
+    bvh: OnceCell<Bvh>,
+}
+
+impl Shape {
+    /// Creates an empty shape.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an object to the shape - Chainable varient
+    pub fn with_object<O: Object + 'static>(self, object: O) -> Self {
+        self.with_boxed_object(Box::new(object))
+    }
+
+    /// Adds an already boxed object to the shape - Chainable varient
+    pub(crate) fn with_boxed_object(mut self, object: Box<dyn Object>) -> Self {
+        self.objects.push(object);
+        self
+    }
+}
+
+impl Object for Shape {
+    fn get_hit(&self, origin: &Point, dir: &Vector, time: f64, rng: &mut Pcg64Fast) -> Option<Hit<'_>> {
+        self.get_hit_after(origin, dir, time, rng, SELF_HIT_DISTANCE / dir.magnitude())
+    }
+
+    fn get_hit_after(
+        &self,
+        origin: &Point,
+        dir: &Vector,
+        time: f64,
+        rng: &mut Pcg64Fast,
+        min_t: f64,
+    ) -> Option<Hit<'_>> {
+        let bvh = self.bvh.get_or_init(|| Bvh::new(&self.objects));
+        bvh.closest_hit(&self.objects, origin, dir, time, rng, min_t)
+            .map(|(_, hit)| hit)
+    }
+
+    fn bounds(&self) -> Rect {
+        let mut bounds: Option<Rect> = None;
+        for o in self.objects.iter() {
+            let b = o.bounds();
+            bounds = Some(bounds.map_or(b, |r| r.union_with(&b)));
+        }
+        bounds.unwrap_or_else(|| Rect::null_at(&Point { x: 0.0, y: 0.0 }))
+    }
+
+    fn surface_at(&self, alpha: f64, time: f64, rng: &mut Pcg64Fast) -> Option<Hit<'_>> {
+        surface_of(&self.objects, alpha, time, rng)
+    }
+
+    fn length(&self, time: f64) -> f64 {
+        length_of(&self.objects, time)
+    }
+}
+
+/// A shared object placed in the scene by a `Transform`.
+///
+/// Instances only hold a reference to their geometry, so a complex `Shape`
+/// can be repeated hundreds of times without being rebuilt. Each instance can
+/// override the material of everything in the shape.
+///
+/// # Example
+/// A row of identical crystals, every other one made a mirror:
+/// ```
+/// use std::rc::Rc;
+/// use rustic_zen::geom::Transform;
+/// use rustic_zen::prelude::*;
+///
+/// let side = |x0, y0, dx, dy| Line {
+///     x0: Sample::Constant(x0),
+///     y0: Sample::Constant(y0),
+///     dx: Sample::Constant(dx),
+///     dy: Sample::Constant(dy),
+///     material: Box::new(HQZLegacy::new(0.2, 0.0, 0.7)),
+/// };
+/// let crystal = Rc::new(
+///     Shape::new()
+///         .with_object(side(0.0, -20.0, 10.0, 20.0))
+///         .with_object(side(10.0, 0.0, -10.0, 20.0))
+///         .with_object(side(0.0, 20.0, -10.0, -20.0))
+///         .with_object(side(-10.0, 0.0, 10.0, -20.0)),
+/// );
+///
+/// let mut scene = Scene::new(1000, 200);
+/// for i in 0..20 {
+///     let mut instance = Instance::new(crystal.clone(), Transform::translation(25.0 + 50.0 * i as f64, 100.0));
+///     if i % 2 == 1 {
+///         instance = instance.with_material(Box::new(HQZLegacy::new(0.0, 1.0, 0.0)));
+///     }
+///     scene = scene.with_object(instance);
+/// }
+/// ```
+pub struct Instance {
+    pub(crate) shape: Rc<dyn Object>,
+    pub(crate) transform: Transform,
+    inverse: Transform,
+    pub(crate) material: Option<Box<dyn Material>>,
+}
+
+impl Instance {
+    /// Places `shape` in the scene with `transform`.
+    ///
+    /// Panics if the transform can't be inverted, such as a scale of zero.
+    pub fn new(shape: Rc<dyn Object>, transform: Transform) -> Self {
+        let inverse = transform
+            .inverse()
+            .expect("Instance transform must be invertible");
+        Self {
+            shape,
+            transform,
+            inverse,
+            material: None,
+        }
+    }
+
+    /// Uses `material` for this instance instead of the shape's own - Chainable varient
+    pub fn with_material(mut self, material: Box<dyn Material>) -> Self {
+        self.material = Some(material);
+        self
+    }
+}
+
+impl Object for Instance {
+    fn get_hit(&self, origin: &Point, dir: &Vector, time: f64, rng: &mut Pcg64Fast) -> Option<Hit<'_>> {
+        self.get_hit_after(origin, dir, time, rng, SELF_HIT_DISTANCE / dir.magnitude())
+    }
+
+    fn get_hit_after(
+        &self,
+        origin: &Point,
+        dir: &Vector,
+        time: f64,
+        rng: &mut Pcg64Fast,
+        min_t: f64,
+    ) -> Option<Hit<'_>> {
+        // The self-hit distance comes from the world ray, not the scaled local one.
+        let hit = self.shape.get_hit_after(
+            &self.inverse.apply_point(origin),
+            &self.inverse.apply_vector(dir),
+            time,
+            rng,
+            min_t,
+        )?;
+        Some(Hit {
+            point: self.transform.apply_point(&hit.point),
+            normal: self.transform.apply_normal(&hit.normal),
+            alpha: hit.alpha,
+            material: self.material.as_ref().map_or(hit.material, |m| m.as_ref()),
+        })
+    }
+
+    fn bounds(&self) -> Rect {
+        self.transform.apply_rect(&self.shape.bounds())
+    }
+
+    fn surface_at(&self, alpha: f64, time: f64, rng: &mut Pcg64Fast) -> Option<Hit<'_>> {
+        let hit = self.shape.surface_at(alpha, time, rng)?;
+        Some(Hit {
+            point: self.transform.apply_point(&hit.point),
+            normal: self.transform.apply_normal(&hit.normal),
+            alpha: hit.alpha,
+            material: self.material.as_ref().map_or(hit.material, |m| m.as_ref()),
+        })
+    }
+
+    fn length(&self, time: f64) -> f64 {
+        self.shape.length(time) * self.transform.length_scale()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Instance, Shape};
+    use geom::{Point, Transform, Vector};
+    use material::HQZLegacy;
+    use object::{Line, Object, Solid};
+    use pcg_rand::Pcg64Fast;
+    use rand::prelude::*;
+    use region::Region;
+    use sampler::Sample;
+    use std::rc::Rc;
+
+    fn square() -> Shape {
+        let side = |x0, y0, dx, dy| Line {
+            x0: Sample::Constant(x0),
+            y0: Sample::Constant(y0),
+            dx: Sample::Constant(dx),
+            dy: Sample::Constant(dy),
+            material: Box::new(HQZLegacy::new(0.0, 0.0, 1.0)),
+        };
+        Shape::new()
+            .with_object(side(-5.0, -5.0, 10.0, 0.0))
+            .with_object(side(5.0, -5.0, 0.0, 10.0))
+            .with_object(side(5.0, 5.0, -10.0, 0.0))
+            .with_object(side(-5.0, 5.0, 0.0, -10.0))
+    }
+
+    #[test]
+    fn instances_share_geometry() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let shape = Rc::new(square());
+        let a = Instance::new(shape.clone(), Transform::translation(100.0, 0.0));
+        let b = Instance::new(shape.clone(), Transform::scaling(2.0, 2.0).then(&Transform::translation(200.0, 0.0)))
+            .with_material(Box::new(HQZLegacy::new(0.0, 1.0, 0.0)));
+        assert_eq!(Rc::strong_count(&shape), 3);
+
+        let origin = Point { x: 0.0, y: 0.0 };
+        let dir = Vector { x: 1.0, y: 0.0 };
+        let hit = a.get_hit(&origin, &dir, 0.0, &mut rng).expect("Ray should hit the first square");
+        assert!(hit.point.close_to(&Point { x: 95.0, y: 0.0 }, 1e-9));
+        assert!(hit.normal.x.abs() > 0.0 && hit.normal.y.abs() < 1e-9);
+        // The shape's own material passes the ray through.
+        let out = hit.material.outcome(&dir, &hit.normal, 500.0, hit.alpha, &mut rng).unwrap();
+        assert_eq!(out.direction, dir);
+
+        let hit = b.get_hit(&origin, &dir, 0.0, &mut rng).expect("Ray should hit the second square");
+        assert!(hit.point.close_to(&Point { x: 190.0, y: 0.0 }, 1e-9));
+        // The override reflects it.
+        let out = hit.material.outcome(&dir, &hit.normal, 500.0, hit.alpha, &mut rng).unwrap();
+        assert!(out.direction.x < 0.0);
+
+        let bounds = b.bounds();
+        assert_eq!((bounds.left(), bounds.right(), bounds.top(), bounds.bottom()), (190.0, 210.0, -10.0, 10.0));
+    }
+
+    #[test]
+    fn leaving_a_shape_hits_the_far_side() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let shape = Rc::new(square());
+        let a = Instance::new(shape, Transform::rotation(45.0));
+
+        let dir = Vector { x: 1.0, y: 0.0 };
+        let first = a
+            .get_hit(&Point { x: -20.0, y: 0.0 }, &dir, 0.0, &mut rng)
+            .expect("Ray should enter the square");
+        let second = a
+            .get_hit(&first.point, &dir, 0.0, &mut rng)
+            .expect("Ray should leave the square");
+        let half_diagonal = 50.0f64.sqrt();
+        assert!(first.point.close_to(&Point { x: -half_diagonal, y: 0.0 }, 1e-9));
+        assert!(second.point.close_to(&Point { x: half_diagonal, y: 0.0 }, 1e-9));
+    }
+
+    #[test]
+    fn scaled_instances_skip_by_world_distance() {
+        let mut rng = Pcg64Fast::from_entropy();
+        // A unit circle blown up to radius 50, locally the self-hit distance would span the whole circle.
+        let circle = Solid {
+            region: Region::Circle {
+                center: Point { x: 0.0, y: 0.0 },
+                radius: 1.0,
+            },
+            material: Box::new(HQZLegacy::new(0.0, 0.0, 1.0)),
+        };
+        let a = Instance::new(Rc::new(circle), Transform::scaling(50.0, 50.0));
+
+        let dir = Vector { x: 1.0, y: 0.0 };
+        let first = a
+            .get_hit(&Point { x: -100.0, y: 0.0 }, &dir, 0.0, &mut rng)
+            .expect("Ray should enter the circle");
+        assert!(first.point.close_to(&Point { x: -50.0, y: 0.0 }, 1e-9));
+        let second = a
+            .get_hit(&first.point, &dir, 0.0, &mut rng)
+            .expect("Ray should leave the circle");
+        assert!(second.point.close_to(&Point { x: 50.0, y: 0.0 }, 1e-9));
+    }
+}
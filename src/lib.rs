@@ -24,7 +24,7 @@
 //!     let m = Box::new(HQZLegacy::new(0.3, 0.3, 0.3));
 //!
 //!     // Build a basic Object
-//!     let o = Object::Line {
+//!     let o = Line {
 //!         x0: Sample::Constant(0.0),
 //!         y0: Sample::Constant(height*0.75),
 //!         dx: Sample::Constant(width),
@@ -63,29 +63,83 @@
 extern crate png;
 extern crate pcg_rand;
 extern crate rand;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde")]
+extern crate serde_json;
 
 pub mod geom;
-
+pub mod optics;
+
+mod animation;
+mod bvh;
+mod downcast;
+mod group;
+#[cfg(feature = "serde")]
+mod hqz;
+mod instance;
+mod light;
 mod material;
+mod medium;
 mod object;
+mod polyline;
+mod region;
 mod sampler;
 mod scene;
+#[cfg(feature = "serde")]
+mod scene_file;
+mod sequence;
 
 /// This prelude contains everything to quickstart using Rustic Zen.
 pub mod prelude {
+    pub use animation::{Interpolation, Keyframe};
     pub use geom::{Point};
-    pub use material::{HQZLegacy, Material};
-    pub use object::Object;
+    pub use group::Group;
+    pub use instance::{Instance, Shape};
+    pub use light::{AreaLight, Beam, Emission, Emitter, Light, ProfileLight, Spotlight};
+    pub use material::{
+        AlphaSelect, Blend, Dichroic, Dielectric, DirectionalMaterial, Filter, Glossy, Grating, HQZLegacy,
+        Lambertian, Layered, Material, Measured, Mix, Outcome, Passband, Texture, ThinFilm, TwoSided,
+    };
+    pub use medium::{Medium, Phase};
+    pub use region::Region;
+    pub use object::{Line, Object, Solid};
     pub use sampler::Sample;
-    pub use scene::{Light, Scene};
+    pub use scene::Scene;
+    pub use sequence::Sampling;
 }
 
 // Rexport everything for documentation use.
-pub use material::{HQZLegacy, Material};
-pub use object::Object;
-pub use sampler::Sample;
-pub use scene::{Light, Scene};
-pub use image::Image;
+pub use animation::{Interpolation, Keyframe, Timeline};
+pub use group::Group;
+pub use instance::{Instance, Shape};
+pub use light::{AreaLight, Beam, Emission, Emitter, Light, ProfileLight, Spotlight};
+pub use material::{
+    AlphaSelect, Blend, Dichroic, Dielectric, DirectionalMaterial, Filter, Glossy, Grating, HQZLegacy,
+    Lambertian, Layered, Material, Measured, Mix, Outcome, Passband, Texture, ThinFilm, TwoSided,
+};
+pub use polyline::Polyline;
+pub use medium::{Medium, Phase};
+pub use region::Region;
+pub use object::{Curve, Hit, Line, Object, Solid};
+pub use sampler::{Sample, Table};
+pub use scene::Scene;
+#[cfg(feature = "serde")]
+pub use hqz::HqzExport;
+#[cfg(feature = "serde")]
+pub use scene_file::{
+    LightDesc, MaterialDesc, MaterialRegistry, MediumDesc, ObjectDesc, PassbandDesc, SceneFile, SceneFileError,
+    TextureDesc,
+};
+pub use sequence::Sampling;
+pub use image::{Image, ToneMap};
+
+/// Indices of the uniform numbers handed to `Emitter::emit()`.
+pub mod dimension {
+    pub use sequence::{
+        DIMENSIONS, LIGHT, POLAR_ANGLE, POLAR_DISTANCE, RAY_ANGLE, TIME, WAVELENGTH, X, Y,
+    };
+}
 
 mod image;
 mod ray;
@@ -106,9 +160,9 @@ mod tests {
     use png::HasParameters;
 
     //Scene Parameters
-    use object::Object;
+    use object::Line;
     use sampler::Sample;
-    use scene::Light;
+    use light::Light;
     use scene::Scene;
 
     use material::HQZLegacy;
@@ -136,7 +190,7 @@ mod tests {
 
         let path = Path::new(r"lib.png_test.png");
         let file = File::create(path).unwrap();
-        let ref mut w = BufWriter::new(file);
+        let w = &mut BufWriter::new(file);
 
         let mut encoder = png::Encoder::new(w, 1024, 1024);
         encoder.set(png::ColorType::RGB).set(png::BitDepth::Eight);
@@ -152,7 +206,7 @@ mod tests {
 
         let m = Box::new(HQZLegacy::default());
 
-        let o = Object::Line {
+        let o = Line {
             x0: Sample::Constant(0.0),
             y0: Sample::Constant(height * 0.75),
             dx: Sample::Constant(width),
@@ -180,7 +234,7 @@ mod tests {
 
         let path = Path::new(r"lib.png_test_2.png");
         let file = File::create(path).unwrap();
-        let ref mut w = BufWriter::new(file);
+        let w = &mut BufWriter::new(file);
 
         let mut encoder = png::Encoder::new(w, 1024, 1024);
         encoder.set(png::ColorType::RGB).set(png::BitDepth::Eight);
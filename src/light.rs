@@ -0,0 +1,879 @@
+//! Sources of photons.
+//!
+//! Every light is an `Emitter`, which tells the scene how bright it is and
+//! samples where each photon starts, which way it goes and what colour it is.
+
+use downcast::AsAny;
+use geom::{Point, Vector};
+use object::Object;
+use pcg_rand::seeds::PcgSeeder;
+use pcg_rand::Pcg64Fast;
+use rand::SeedableRng;
+use sampler::Sample;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use sequence::{DIMENSIONS, POLAR_ANGLE, POLAR_DISTANCE, RAY_ANGLE, WAVELENGTH, X, Y};
+use std::f64::consts::PI;
+
+/// Light source Trait
+///
+/// Just as `Material` defines what happens when a photon hits an object, an
+/// `Emitter` defines how photons are born: how bright the light is and where
+/// each photon starts, which way it goes and what wavelength it has.
+///
+/// The trait is exposed to allow library users to define their own lights,
+/// `Light` is the default implementation and is what most scenes use.
+///
+/// # Example
+/// Photons spawned along a spiral, heading outwards:
+/// ```
+/// extern crate pcg_rand;
+/// extern crate rustic_zen;
+///
+/// use pcg_rand::Pcg64Fast;
+/// use rustic_zen::dimension::{DIMENSIONS, WAVELENGTH, X};
+/// use rustic_zen::geom::Vector;
+/// use rustic_zen::prelude::*;
+///
+/// struct Spiral;
+///
+/// impl Emitter for Spiral {
+///     fn power(&self, _time: f64) -> f64 {
+///         1.0
+///     }
+///
+///     fn emit(&self, _time: f64, u: &[f64; DIMENSIONS], _rng: &mut Pcg64Fast) -> Emission {
+///         let angle = u[X] * 6.0 * std::f64::consts::PI;
+///         let radius = 20.0 * angle;
+///         let out = Vector { x: angle.cos(), y: angle.sin() };
+///         let origin = Point { x: 500.0 + radius * out.x, y: 500.0 + radius * out.y };
+///         Emission::new(origin, out, 400.0 + 300.0 * u[WAVELENGTH])
+///     }
+/// }
+///
+/// # fn main() {
+/// let s = Scene::new(1000, 1000).with_light(Spiral);
+/// # }
+/// ```
+pub trait Emitter: AsAny {
+    /// Expected power of the emitter at `time`, relative to other lights in the scene.
+    ///
+    /// Lights are chosen in proportion to this, so a light with twice the
+    /// power spawns twice as many photons.
+    fn power(&self, time: f64) -> f64;
+
+    /**
+     * Samples a photon emitted at `time`.
+     *
+     * # Parameters:
+     *  - __time__: Time the photon is emitted at, for animated lights.
+     *  - __u__: Uniform numbers within `[0, 1)`, from a low discrepancy sequence if the
+     *    scene uses one. Use the entries named in the `dimension` module for the purpose
+     *    they are named after, the scene has already used `LIGHT` and `TIME`.
+     *  - __rng__: random number generator for anything else, (don't spawn your own, way to slow.)
+     */
+    fn emit(&self, time: f64, u: &[f64; DIMENSIONS], rng: &mut Pcg64Fast) -> Emission;
+}
+
+/// A photon leaving an `Emitter`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Emission {
+    /// Where the photon starts.
+    pub origin: Point,
+    /// Direction the photon travels in.
+    pub direction: Vector,
+    /// Wavelength of the photon in nm.
+    pub wavelength: f64,
+    /// Intensity the photon's path is drawn with, 1.0 is normal.
+    pub energy: f64,
+}
+
+impl Emission {
+    /// Creates an emission with an energy of 1.0.
+    pub fn new(origin: Point, direction: Vector, wavelength: f64) -> Self {
+        Emission {
+            origin,
+            direction,
+            wavelength,
+            energy: 1.0,
+        }
+    }
+}
+
+/// Data only struct which defines a Light Source
+///
+/// # Examples
+/// Make a warm white small round light:
+/// ```
+/// use rustic_zen::prelude::{Light, Sample};
+///
+/// Light {
+///     power: Sample::Constant(1.0),
+///     x: Sample::Constant(512.0),
+///     y: Sample::Constant(512.0),
+///     polar_angle: Sample::Constant(0.0),
+///     polar_distance: Sample::Constant(0.0),
+///     ray_angle: Sample::Range(360.0, 0.0),
+///     wavelength: Sample::Blackbody(5800.0),
+/// };
+/// ```
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Light {
+    /// Brightness of this light relative to other lights in the scene
+    pub power: Sample,
+    /// x coordinate of the light.
+    pub x: Sample,
+    /// y coordinate of the light.
+    pub y: Sample,
+    /// distance from x,y that photon will spawn
+    pub polar_distance: Sample,
+    /// angle that polar_distance will be at
+    pub polar_angle: Sample,
+    /// Angle which spawned ray will be at
+    pub ray_angle: Sample,
+    /// Wavelength of spawned ray
+    pub wavelength: Sample,
+}
+
+impl Emitter for Light {
+    fn power(&self, time: f64) -> f64 {
+        self.power.expected_at(time)
+    }
+
+    fn emit(&self, time: f64, u: &[f64; DIMENSIONS], _rng: &mut Pcg64Fast) -> Emission {
+        let cart_x = self.x.val_from(time, u[X]);
+        let cart_y = self.y.val_from(time, u[Y]);
+        let polar_angle = self.polar_angle.val_from(time, u[POLAR_ANGLE]) * (PI / 180.0);
+        let polar_dist = self.polar_distance.val_from(time, u[POLAR_DISTANCE]);
+        let origin = Point {
+            x: cart_x + f64::cos(polar_angle) * polar_dist,
+            y: cart_y + f64::sin(polar_angle) * polar_dist,
+        };
+        let ray_angle = self.ray_angle.val_from(time, u[RAY_ANGLE]) * (PI / 180.0);
+        let direction = Vector {
+            x: f64::cos(ray_angle),
+            y: f64::sin(ray_angle),
+        };
+        let wavelength = self.wavelength.val_from(time, u[WAVELENGTH]);
+        Emission::new(origin, direction, wavelength)
+    }
+}
+
+/// An object which glows, such as a neon tube or a lit window.
+///
+/// Photons spawn along the object and leave both sides of it, by default
+/// spread evenly along its length and cosine weighted about its normal, as
+/// from a diffuse glowing surface. The light's power is per unit of length,
+/// so longer objects are brighter.
+///
+/// The object only emits light, add a separate object to the scene if the
+/// glowing surface should also block or reflect light.
+///
+/// # Example
+/// A red neon tube:
+/// ```
+/// use rustic_zen::prelude::{AreaLight, HQZLegacy, Line, Sample, Scene};
+///
+/// let tube = Line {
+///     x0: Sample::Constant(100.0),
+///     y0: Sample::Constant(500.0),
+///     dx: Sample::Constant(400.0),
+///     dy: Sample::Constant(0.0),
+///     material: Box::new(HQZLegacy::default()),
+/// };
+/// let neon = AreaLight::new(tube, Sample::Constant(1.0), Sample::Normal(640.0, 5.0));
+/// let s = Scene::new(1920, 1080).with_light(neon);
+/// ```
+pub struct AreaLight {
+    pub(crate) object: Box<dyn Object>,
+    pub(crate) power: Sample,
+    pub(crate) wavelength: Sample,
+    pub(crate) profile: Sample,
+    pub(crate) ray_angle: Option<Sample>,
+    pub(crate) one_sided: bool,
+}
+
+impl AreaLight {
+    /// Creates new instance with provided parameters.
+    ///
+    /// # Parameters:
+    ///  - object: the object whose shape emits light, its material is not used.
+    ///  - power: brightness per unit of length, relative to other lights in the scene.
+    ///  - wavelength: wavelength of spawned rays.
+    ///
+    /// Panics if the object doesn't implement `Object::surface_at()`, such as a
+    /// custom object which keeps the default.
+    pub fn new<O: Object + 'static>(object: O, power: Sample, wavelength: Sample) -> Self {
+        Self::from_boxed(Box::new(object), power, wavelength)
+    }
+
+    /// `new()` for an already boxed object.
+    pub(crate) fn from_boxed(object: Box<dyn Object>, power: Sample, wavelength: Sample) -> Self {
+        if !Self::can_emit(object.as_ref()) {
+            panic!("AreaLight object must provide surface points to emit from");
+        }
+        Self {
+            object,
+            power,
+            wavelength,
+            profile: Sample::Range(1.0, 0.0),
+            ray_angle: None,
+            one_sided: false,
+        }
+    }
+
+    /// Tests if `object` provides surface points to emit from.
+    pub(crate) fn can_emit(object: &dyn Object) -> bool {
+        let mut rng = Pcg64Fast::from_seed(PcgSeeder::seed(1));
+        object.surface_at(0.5, 0.0, &mut rng).is_some()
+    }
+
+    /// Sets where along the object photons spawn - Chainable varient
+    ///
+    /// `profile` gives how far along the object (0.0 to 1.0) each photon
+    /// starts, by default they are spread evenly.
+    ///
+    /// # Example
+    /// A tube which is brightest in the middle:
+    /// ```
+    /// use rustic_zen::prelude::{AreaLight, HQZLegacy, Line, Sample};
+    ///
+    /// # let tube = Line {
+    /// #     x0: Sample::Constant(100.0),
+    /// #     y0: Sample::Constant(500.0),
+    /// #     dx: Sample::Constant(400.0),
+    /// #     dy: Sample::Constant(0.0),
+    /// #     material: Box::new(HQZLegacy::default()),
+    /// # };
+    /// let light = AreaLight::new(tube, Sample::Constant(1.0), Sample::Blackbody(3000.0))
+    ///     .with_profile(Sample::piecewise(&[(0.0, 0.0), (0.5, 1.0), (1.0, 0.0)]));
+    /// ```
+    pub fn with_profile(mut self, profile: Sample) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Sets the angle in degrees photons leave at, relative to the normal - Chainable varient
+    ///
+    /// By default photons are cosine weighted about the normal, use
+    /// `Sample::Range(90.0, -90.0)` to spread them evenly or
+    /// `Sample::Constant(0.0)` for them all to leave straight out.
+    pub fn with_ray_angle(mut self, ray_angle: Sample) -> Self {
+        self.ray_angle = Some(ray_angle);
+        self
+    }
+
+    /// Only emit from the side of the object its normal points out of - Chainable varient
+    ///
+    /// For a line from `(x0, y0)` along `(dx, dy)` that is the side of `(-dy, dx)`.
+    pub fn one_sided(mut self) -> Self {
+        self.one_sided = true;
+        self
+    }
+}
+
+impl Emitter for AreaLight {
+    fn power(&self, time: f64) -> f64 {
+        self.power.expected_at(time) * self.object.length(time)
+    }
+
+    fn emit(&self, time: f64, u: &[f64; DIMENSIONS], rng: &mut Pcg64Fast) -> Emission {
+        let alpha = self.profile.val_from(time, u[X]);
+        let surface = self
+            .object
+            .surface_at(alpha, time, rng)
+            .expect("AreaLight object stopped providing surface points");
+        let origin = surface.point;
+        let mut normal = surface.normal.normalized();
+        if !self.one_sided && u[POLAR_ANGLE] >= 0.5 {
+            normal = -normal;
+        }
+        let angle = match &self.ray_angle {
+            Some(s) => s.val_from(time, u[RAY_ANGLE]) * (PI / 180.0),
+            // In 2D the inverse CDF of cos(theta) / 2 is asin(2u - 1)
+            None => f64::asin(2.0 * u[RAY_ANGLE] - 1.0),
+        };
+        let wavelength = self.wavelength.val_from(time, u[WAVELENGTH]);
+        Emission::new(origin, normal.rotate(angle), wavelength)
+    }
+}
+
+/// Position and direction of a light, shared by the light shapes with one
+/// position and a direction they are aimed in.
+fn aimed(
+    x: &Sample,
+    y: &Sample,
+    direction: &Sample,
+    time: f64,
+    u: &[f64; DIMENSIONS],
+) -> (Point, f64) {
+    let origin = Point {
+        x: x.val_from(time, u[X]),
+        y: y.val_from(time, u[Y]),
+    };
+    (origin, direction.val_from(time, u[POLAR_ANGLE]))
+}
+
+/// Unit vector at `degrees` from the x axis.
+fn heading(degrees: f64) -> Vector {
+    let rad = degrees * (PI / 180.0);
+    Vector {
+        x: f64::cos(rad),
+        y: f64::sin(rad),
+    }
+}
+
+/// A wide parallel beam, such as sunlight through a window.
+///
+/// Photons spawn evenly across a line of the given width, centred on `(x, y)`
+/// and square to the direction, and all travel in the same direction.
+/// The beam's power is per unit of width, so wider beams are brighter.
+///
+/// # Example
+/// ```
+/// use rustic_zen::prelude::{Beam, Sample, Scene};
+///
+/// let sunlight = Beam::new(
+///     Sample::Constant(200.0),
+///     Sample::Constant(100.0),
+///     Sample::Constant(30.0),
+///     80.0,
+///     Sample::Blackbody(5800.0),
+/// );
+/// let s = Scene::new(1920, 1080).with_light(sunlight);
+/// ```
+#[derive(Clone)]
+pub struct Beam {
+    pub(crate) power: Sample,
+    pub(crate) x: Sample,
+    pub(crate) y: Sample,
+    pub(crate) direction: Sample,
+    pub(crate) width: f64,
+    pub(crate) wavelength: Sample,
+}
+
+impl Beam {
+    /// Creates new instance with provided parameters.
+    ///
+    /// # Parameters:
+    ///  - x, y: centre of the beam where photons spawn.
+    ///  - direction: angle in degrees the beam travels in.
+    ///  - width: width of the beam.
+    ///  - wavelength: wavelength of spawned rays.
+    pub fn new(x: Sample, y: Sample, direction: Sample, width: f64, wavelength: Sample) -> Self {
+        if width < 0.0 {
+            panic!("Beam width must not be negative");
+        }
+        Self {
+            power: Sample::Constant(1.0),
+            x,
+            y,
+            direction,
+            width,
+            wavelength,
+        }
+    }
+
+    /// Sets the brightness per unit of width, relative to other lights in the scene - Chainable varient
+    pub fn with_power(mut self, power: Sample) -> Self {
+        self.power = power;
+        self
+    }
+}
+
+impl Emitter for Beam {
+    fn power(&self, time: f64) -> f64 {
+        self.power.expected_at(time) * self.width
+    }
+
+    fn emit(&self, time: f64, u: &[f64; DIMENSIONS], _rng: &mut Pcg64Fast) -> Emission {
+        let (centre, angle) = aimed(&self.x, &self.y, &self.direction, time, u);
+        let direction = heading(angle);
+        let offset = (u[POLAR_DISTANCE] - 0.5) * self.width;
+        let origin = Point {
+            x: centre.x - direction.y * offset,
+            y: centre.y + direction.x * offset,
+        };
+        let wavelength = self.wavelength.val_from(time, u[WAVELENGTH]);
+        Emission::new(origin, direction, wavelength)
+    }
+}
+
+/// A spotlight, emitting a cone of light with a soft edge.
+///
+/// Intensity is even out to the inner angle, then falls smoothly to nothing
+/// at the outer angle. Both angles are measured from the direction the
+/// spotlight is aimed in, so the cone is twice as wide as the outer angle.
+///
+/// # Example
+/// ```
+/// use rustic_zen::prelude::{Sample, Scene, Spotlight};
+///
+/// let spot = Spotlight::new(
+///     Sample::Constant(960.0),
+///     Sample::Constant(0.0),
+///     Sample::Constant(90.0),
+///     10.0,
+///     25.0,
+///     Sample::Blackbody(3200.0),
+/// );
+/// let s = Scene::new(1920, 1080).with_light(spot);
+/// ```
+#[derive(Clone)]
+pub struct Spotlight {
+    pub(crate) power: Sample,
+    pub(crate) x: Sample,
+    pub(crate) y: Sample,
+    pub(crate) direction: Sample,
+    spread: Sample,
+    pub(crate) wavelength: Sample,
+    angles: (f64, f64),
+}
+
+/// Number of segments the spotlight falloff is approximated with.
+const FALLOFF_STEPS: usize = 16;
+
+impl Spotlight {
+    /// Creates new instance with provided parameters.
+    ///
+    /// # Parameters:
+    ///  - x, y: position of the spotlight.
+    ///  - direction: angle in degrees the spotlight is aimed in.
+    ///  - inner: angle in degrees from the aim out to which intensity is even.
+    ///  - outer: angle in degrees from the aim where intensity reaches zero.
+    ///  - wavelength: wavelength of spawned rays.
+    pub fn new(
+        x: Sample,
+        y: Sample,
+        direction: Sample,
+        inner: f64,
+        outer: f64,
+        wavelength: Sample,
+    ) -> Self {
+        if inner < 0.0 || outer < inner || outer > 180.0 {
+            panic!("Spotlight angles must satisfy 0.0 <= inner <= outer <= 180.0");
+        }
+        // Intensity against angle, falling off as a smoothstep between inner and outer.
+        let mut points = vec![];
+        if inner > 0.0 {
+            points.push((0.0, 1.0));
+        }
+        if outer > inner {
+            for i in 0..=FALLOFF_STEPS {
+                let t = i as f64 / FALLOFF_STEPS as f64;
+                let angle = inner + t * (outer - inner);
+                points.push((angle, 1.0 - t * t * (3.0 - 2.0 * t)));
+            }
+        } else {
+            points.push((outer, 1.0));
+        }
+        Self {
+            power: Sample::Constant(1.0),
+            x,
+            y,
+            direction,
+            spread: mirrored(&points).expect("Spotlight falloff always has some intensity"),
+            wavelength,
+            angles: (inner, outer),
+        }
+    }
+
+    /// Inner and outer angle in degrees the spotlight was created with.
+    pub fn angles(&self) -> (f64, f64) {
+        self.angles
+    }
+
+    /// Sets the brightness relative to other lights in the scene - Chainable varient
+    pub fn with_power(mut self, power: Sample) -> Self {
+        self.power = power;
+        self
+    }
+}
+
+impl Emitter for Spotlight {
+    fn power(&self, time: f64) -> f64 {
+        self.power.expected_at(time)
+    }
+
+    fn emit(&self, time: f64, u: &[f64; DIMENSIONS], _rng: &mut Pcg64Fast) -> Emission {
+        let (origin, angle) = aimed(&self.x, &self.y, &self.direction, time, u);
+        let direction = heading(angle + self.spread.val_from(time, u[RAY_ANGLE]));
+        let wavelength = self.wavelength.val_from(time, u[WAVELENGTH]);
+        Emission::new(origin, direction, wavelength)
+    }
+}
+
+/// A light with measured intensity against angle, as given by a lamp's goniometric data.
+///
+/// The profile gives intensity at angles from the direction the light is
+/// aimed in, from 0.0 out to at most 180.0 degrees, and is mirrored either
+/// side of the aim. Intensity is interpolated linearly between angles, and
+/// nothing is emitted beyond the last.
+///
+/// # Example
+/// A lamp which is brightest at 30 degrees either side of straight down:
+/// ```
+/// use rustic_zen::prelude::{ProfileLight, Sample, Scene};
+///
+/// let lamp = ProfileLight::new(
+///     Sample::Constant(960.0),
+///     Sample::Constant(100.0),
+///     Sample::Constant(90.0),
+///     &[(0.0, 0.6), (30.0, 1.0), (70.0, 0.2), (120.0, 0.0)],
+///     Sample::Blackbody(2700.0),
+/// );
+/// let s = Scene::new(1920, 1080).with_light(lamp);
+/// ```
+#[derive(Clone)]
+pub struct ProfileLight {
+    pub(crate) power: Sample,
+    pub(crate) x: Sample,
+    pub(crate) y: Sample,
+    pub(crate) direction: Sample,
+    profile: Sample,
+    pub(crate) wavelength: Sample,
+    points: Vec<(f64, f64)>,
+}
+
+impl ProfileLight {
+    /// Creates new instance with provided parameters.
+    ///
+    /// # Parameters:
+    ///  - x, y: position of the light.
+    ///  - direction: angle in degrees the light is aimed in.
+    ///  - profile: `(angle, intensity)` pairs, angles in degrees from the aim, sorted.
+    ///  - wavelength: wavelength of spawned rays.
+    ///
+    /// Panics if the profile is empty, an angle is outside 0.0 to 180.0, the
+    /// angles aren't sorted, an intensity is negative or there's no intensity
+    /// over any range of angles.
+    pub fn new(
+        x: Sample,
+        y: Sample,
+        direction: Sample,
+        profile: &[(f64, f64)],
+        wavelength: Sample,
+    ) -> Self {
+        Self::try_new(x, y, direction, profile, wavelength).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// `new()`, returning why the profile is invalid instead of panicking.
+    pub(crate) fn try_new(
+        x: Sample,
+        y: Sample,
+        direction: Sample,
+        profile: &[(f64, f64)],
+        wavelength: Sample,
+    ) -> Result<Self, &'static str> {
+        if profile.is_empty() {
+            return Err("ProfileLight needs at least one point");
+        }
+        if profile.iter().any(|p| !(0.0..=180.0).contains(&p.0)) {
+            return Err("ProfileLight angles must be between 0.0 and 180.0");
+        }
+        if profile.windows(2).any(|w| w[1].0 < w[0].0) {
+            return Err("ProfileLight angles must be sorted");
+        }
+        if profile.iter().any(|p| p.1 < 0.0 || p.1.is_nan()) {
+            return Err("ProfileLight intensities must not be negative");
+        }
+        let spread = mirrored(profile).map_err(|_| "ProfileLight needs some intensity over a range of angles")?;
+        Ok(Self {
+            power: Sample::Constant(1.0),
+            x,
+            y,
+            direction,
+            profile: spread,
+            wavelength,
+            points: profile.to_vec(),
+        })
+    }
+
+    /// `(angle, intensity)` pairs the light was created with.
+    pub fn profile(&self) -> &[(f64, f64)] {
+        &self.points
+    }
+
+    /// Sets the brightness relative to other lights in the scene - Chainable varient
+    pub fn with_power(mut self, power: Sample) -> Self {
+        self.power = power;
+        self
+    }
+}
+
+impl Emitter for ProfileLight {
+    fn power(&self, time: f64) -> f64 {
+        self.power.expected_at(time)
+    }
+
+    fn emit(&self, time: f64, u: &[f64; DIMENSIONS], _rng: &mut Pcg64Fast) -> Emission {
+        let (origin, angle) = aimed(&self.x, &self.y, &self.direction, time, u);
+        let direction = heading(angle + self.profile.val_from(time, u[RAY_ANGLE]));
+        let wavelength = self.wavelength.val_from(time, u[WAVELENGTH]);
+        Emission::new(origin, direction, wavelength)
+    }
+}
+
+/// Builds a density over angle from one side of a symmetric intensity profile.
+///
+/// A single point is a uniform spread out to that angle.
+fn mirrored(points: &[(f64, f64)]) -> Result<Sample, &'static str> {
+    if points.len() == 1 {
+        let a = points[0].0;
+        return Ok(if a > 0.0 {
+            Sample::Range(a, -a)
+        } else {
+            Sample::Constant(0.0)
+        });
+    }
+    let mut full: Vec<(f64, f64)> = points
+        .iter()
+        .rev()
+        .filter(|p| p.0 > 0.0)
+        .map(|&(a, i)| (-a, i))
+        .collect();
+    if points[0].0 > 0.0 {
+        // Nothing is emitted inside the first angle.
+        full.push((-points[0].0, 0.0));
+        full.push((points[0].0, 0.0));
+    }
+    full.extend_from_slice(points);
+    Sample::try_piecewise(&full)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AreaLight, Beam, Emitter, ProfileLight, Spotlight};
+    use geom::{Point, Rect, Transform, Vector};
+    use group::Group;
+    use instance::{Instance, Shape};
+    use material::HQZLegacy;
+    use object::{Hit, Line, Object, Solid};
+    use region::Region;
+    use pcg_rand::Pcg64Fast;
+    use rand::prelude::*;
+    use sampler::Sample;
+    use sequence::DIMENSIONS;
+    use std::rc::Rc;
+
+    fn tube() -> Line {
+        Line {
+            x0: Sample::Constant(10.0),
+            y0: Sample::Constant(20.0),
+            dx: Sample::Constant(30.0),
+            dy: Sample::Constant(40.0),
+            material: Box::new(HQZLegacy::default()),
+        }
+    }
+
+    #[test]
+    fn power_scales_with_length() {
+        let l = AreaLight::new(tube(), Sample::Constant(2.0), Sample::Constant(500.0));
+        assert_eq!(l.power(0.0), 100.0);
+    }
+
+    #[test]
+    fn solids_emit_from_their_edge() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let disc = Solid {
+            region: Region::Circle {
+                center: Point { x: 0.0, y: 0.0 },
+                radius: 10.0,
+            },
+            material: Box::new(HQZLegacy::default()),
+        };
+        let l = AreaLight::new(disc, Sample::Constant(1.0), Sample::Constant(500.0)).one_sided();
+        assert!((l.power(0.0) - 20.0 * std::f64::consts::PI).abs() < 1e-9);
+        for _ in 0..1000 {
+            let e = l.emit(0.0, &uniform(&mut rng), &mut rng);
+            let (origin, dir) = (e.origin, e.direction);
+            assert!((origin.x.hypot(origin.y) - 10.0).abs() < 1e-9);
+            // Leaving the disc, not entering it.
+            assert!(origin.x * dir.x + origin.y * dir.y >= 0.0);
+        }
+    }
+
+    #[test]
+    fn transformed_containers_emit() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let shape = Rc::new(Shape::new().with_object(tube()));
+        let instance = Instance::new(shape, Transform::scaling(2.0, 2.0));
+        let l = AreaLight::new(instance, Sample::Constant(1.0), Sample::Constant(500.0));
+        assert!((l.power(0.0) - 100.0).abs() < 1e-9);
+
+        let group = Group::new(Transform::translation(100.0, 0.0)).with_object(tube());
+        let l = AreaLight::new(group, Sample::Constant(1.0), Sample::Constant(500.0));
+        assert!((l.power(0.0) - 50.0).abs() < 1e-9);
+        for _ in 0..100 {
+            let origin = l.emit(0.0, &uniform(&mut rng), &mut rng).origin;
+            let t = (origin.x - 110.0) / 30.0;
+            assert!((0.0..=1.0).contains(&t));
+            assert!((origin.y - (20.0 + 40.0 * t)).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn objects_without_a_surface_cant_emit() {
+        struct Invisible;
+        impl Object for Invisible {
+            fn get_hit(&self, _: &Point, _: &Vector, _: f64, _: &mut Pcg64Fast) -> Option<Hit<'_>> {
+                None
+            }
+
+            fn bounds(&self) -> Rect {
+                Rect::null_at(&Point { x: 0.0, y: 0.0 })
+            }
+        }
+        AreaLight::new(Invisible, Sample::Constant(1.0), Sample::Constant(500.0));
+    }
+
+    #[test]
+    fn emits_along_object() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let l = AreaLight::new(tube(), Sample::Constant(1.0), Sample::Constant(500.0)).one_sided();
+        let normal = (-0.8, 0.6);
+        let mut sum_cos = 0.0;
+        let n = 10_000;
+        for _ in 0..n {
+            let mut u = [0.0; DIMENSIONS];
+            for x in u.iter_mut() {
+                *x = rng.gen_range(0.0, 1.0);
+            }
+            let e = l.emit(0.0, &u, &mut rng);
+            let (origin, dir) = (e.origin, e.direction);
+            // On the line from (10, 20) to (40, 60)
+            let t = (origin.x - 10.0) / 30.0;
+            assert!((0.0..=1.0).contains(&t));
+            assert!((origin.y - (20.0 + 40.0 * t)).abs() < 1e-9);
+            assert_eq!(e.wavelength, 500.0);
+
+            let cos = dir.x * normal.0 + dir.y * normal.1;
+            assert!(cos >= 0.0);
+            sum_cos += cos;
+        }
+        // Mean cosine of a cosine weighted direction in 2D is pi / 4
+        assert!((sum_cos / n as f64 - std::f64::consts::FRAC_PI_4).abs() < 0.01);
+    }
+
+    #[test]
+    fn two_sided_emits_both_ways() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let l = AreaLight::new(tube(), Sample::Constant(1.0), Sample::Constant(500.0))
+            .with_ray_angle(Sample::Constant(0.0));
+        let mut u = [0.25; DIMENSIONS];
+        let front = l.emit(0.0, &u, &mut rng).direction;
+        u[super::POLAR_ANGLE] = 0.75;
+        let back = l.emit(0.0, &u, &mut rng).direction;
+        assert!((front.x + 0.8).abs() < 1e-9 && (front.y - 0.6).abs() < 1e-9);
+        assert!((back.x - 0.8).abs() < 1e-9 && (back.y + 0.6).abs() < 1e-9);
+    }
+
+    fn uniform(rng: &mut Pcg64Fast) -> [f64; DIMENSIONS] {
+        let mut u = [0.0; DIMENSIONS];
+        for x in u.iter_mut() {
+            *x = rng.gen_range(0.0, 1.0);
+        }
+        u
+    }
+
+    /// Angles in degrees of many photons, relative to the x axis.
+    fn angles(l: &dyn Emitter) -> Vec<f64> {
+        let mut rng = Pcg64Fast::from_entropy();
+        (0..20_000)
+            .map(|_| {
+                let dir = l.emit(0.0, &uniform(&mut rng), &mut rng).direction;
+                dir.y.atan2(dir.x).to_degrees()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn beam_is_parallel() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let l = Beam::new(
+            Sample::Constant(50.0),
+            Sample::Constant(50.0),
+            Sample::Constant(90.0),
+            20.0,
+            Sample::Constant(500.0),
+        )
+        .with_power(Sample::Constant(0.5));
+        assert_eq!(l.power(0.0), 10.0);
+        for _ in 0..1000 {
+            let e = l.emit(0.0, &uniform(&mut rng), &mut rng);
+            let (origin, dir) = (e.origin, e.direction);
+            assert!(dir.x.abs() < 1e-9 && (dir.y - 1.0).abs() < 1e-9);
+            assert!((origin.y - 50.0).abs() < 1e-9);
+            assert!((origin.x - 50.0).abs() <= 10.0);
+        }
+    }
+
+    #[test]
+    fn spotlight_cone() {
+        let l = Spotlight::new(
+            Sample::Constant(0.0),
+            Sample::Constant(0.0),
+            Sample::Constant(90.0),
+            10.0,
+            30.0,
+            Sample::Constant(500.0),
+        );
+        let a = angles(&l);
+        assert!(a.iter().all(|x| (60.0..=120.0).contains(x)));
+        // The smoothstep falloff has the same area as a hard edge half way out.
+        let inner = a.iter().filter(|x| (x.abs() - 90.0).abs() <= 10.0).count();
+        assert!((inner as f64 / a.len() as f64 - 0.5).abs() < 0.02);
+    }
+
+    #[test]
+    fn profile_is_mirrored() {
+        let l = ProfileLight::new(
+            Sample::Constant(0.0),
+            Sample::Constant(0.0),
+            Sample::Constant(0.0),
+            &[(20.0, 1.0), (60.0, 0.0)],
+            Sample::Constant(500.0),
+        );
+        let a = angles(&l);
+        assert!(a.iter().all(|x| (20.0..=60.0).contains(&x.abs())));
+        let left = a.iter().filter(|x| **x < 0.0).count();
+        assert!((left as f64 / a.len() as f64 - 0.5).abs() < 0.02);
+    }
+
+    #[test]
+    fn invalid_profiles_are_errors() {
+        let profile = |points: &[(f64, f64)]| {
+            ProfileLight::try_new(
+                Sample::Constant(0.0),
+                Sample::Constant(0.0),
+                Sample::Constant(0.0),
+                points,
+                Sample::Constant(500.0),
+            )
+            .err()
+        };
+        assert_eq!(profile(&[]), Some("ProfileLight needs at least one point"));
+        assert_eq!(profile(&[(0.0, 1.0), (200.0, 0.0)]), Some("ProfileLight angles must be between 0.0 and 180.0"));
+        assert_eq!(profile(&[(60.0, 1.0), (20.0, 0.0)]), Some("ProfileLight angles must be sorted"));
+        assert_eq!(profile(&[(0.0, 1.0), (90.0, -1.0)]), Some("ProfileLight intensities must not be negative"));
+        assert_eq!(
+            profile(&[(0.0, 0.0), (90.0, 0.0)]),
+            Some("ProfileLight needs some intensity over a range of angles")
+        );
+        assert!(profile(&[(0.0, 1.0), (90.0, 0.0)]).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "ProfileLight angles must be sorted")]
+    fn unsorted_profile_panics() {
+        ProfileLight::new(
+            Sample::Constant(0.0),
+            Sample::Constant(0.0),
+            Sample::Constant(0.0),
+            &[(60.0, 1.0), (20.0, 0.0)],
+            Sample::Constant(500.0),
+        );
+    }
+}
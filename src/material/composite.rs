@@ -0,0 +1,248 @@
+use geom::Vector;
+use material::{Material, Outcome};
+use pcg_rand::Pcg64Fast;
+use rand::prelude::*;
+
+/// Probabilistic mix of several materials.
+///
+/// Each hit is handed to one of the materials, chosen by weight, so a
+/// mix of a mirror and clear glass gives half-silvered glass.
+pub struct Mix {
+    pub(crate) materials: Vec<(f64, Box<dyn Material>)>,
+}
+
+impl Mix {
+    /// Creates new instance from `(weight, material)` pairs.
+    ///
+    /// Weights need not sum to one, but must not be negative, and at least one
+    /// must be positive.
+    ///
+    /// # Example
+    /// ```
+    /// use rustic_zen::prelude::{HQZLegacy, Material, Mix};
+    ///
+    /// let half_silvered = Mix::new(vec![
+    ///     (1.0, Box::new(HQZLegacy::new(0.0, 1.0, 0.0)) as Box<dyn Material>),
+    ///     (1.0, Box::new(HQZLegacy::new(0.0, 0.0, 1.0))),
+    /// ]);
+    /// ```
+    pub fn new(materials: Vec<(f64, Box<dyn Material>)>) -> Self {
+        if materials.iter().any(|m| m.0 < 0.0) {
+            panic!("Mix weights must not be negative");
+        }
+        let total: f64 = materials.iter().map(|m| m.0).sum();
+        if total <= 0.0 {
+            panic!("Mix needs at least one material with a positive weight");
+        }
+        let mut sum = 0.0;
+        let materials = materials
+            .into_iter()
+            .map(|(w, m)| {
+                sum += w;
+                (sum / total, m)
+            })
+            .collect();
+        Self { materials }
+    }
+}
+
+impl Material for Mix {
+    fn outcome(
+        &self,
+        direction: &Vector,
+        normal: &Vector,
+        wavelength: f64,
+        alpha: f64,
+        rng: &mut Pcg64Fast,
+    ) -> Option<Outcome> {
+        let u: f64 = rng.gen_range(0.0, 1.0);
+        let i = self
+            .materials
+            .partition_point(|m| m.0 <= u)
+            .min(self.materials.len() - 1);
+        self.materials[i]
+            .1
+            .outcome(direction, normal, wavelength, alpha, rng)
+    }
+}
+
+/// Uses a different material for each side of a surface.
+///
+/// The front is the side the object's normal points out of, for a line
+/// from `(x0, y0)` along `(dx, dy)` that is the side of `(-dy, dx)`.
+pub struct TwoSided {
+    pub(crate) front: Box<dyn Material>,
+    pub(crate) back: Box<dyn Material>,
+}
+
+impl TwoSided {
+    /// Creates new instance with provided materials.
+    ///
+    /// # Example
+    /// A mirror which is black on the back:
+    /// ```
+    /// use rustic_zen::prelude::{HQZLegacy, TwoSided};
+    ///
+    /// let mirror = TwoSided::new(
+    ///     Box::new(HQZLegacy::new(0.0, 1.0, 0.0)),
+    ///     Box::new(HQZLegacy::new(0.0, 0.0, 0.0)),
+    /// );
+    /// ```
+    pub fn new(front: Box<dyn Material>, back: Box<dyn Material>) -> Self {
+        Self { front, back }
+    }
+}
+
+impl Material for TwoSided {
+    fn outcome(
+        &self,
+        direction: &Vector,
+        normal: &Vector,
+        wavelength: f64,
+        alpha: f64,
+        rng: &mut Pcg64Fast,
+    ) -> Option<Outcome> {
+        let m = if direction.dot(normal) < 0.0 {
+            &self.front
+        } else {
+            &self.back
+        };
+        m.outcome(direction, normal, wavelength, alpha, rng)
+    }
+}
+
+/// Maximum number of passes between the layers before a ray is absorbed.
+const MAX_LAYER_BOUNCES: usize = 16;
+
+/// A coat on top of a base material, such as varnish over wood.
+///
+/// Rays hit the coat first, anything the coat transmits goes on to hit
+/// the base, and anything the base sends back has to pass through the coat
+/// again, bouncing between the two until the ray leaves one side or the other.
+/// The coat is on the side the ray arrives from, whichever that is.
+pub struct Layered {
+    pub(crate) coat: Box<dyn Material>,
+    pub(crate) base: Box<dyn Material>,
+}
+
+impl Layered {
+    /// Creates new instance with provided materials.
+    ///
+    /// # Example
+    /// Glossy lacquer over a matte surface:
+    /// ```
+    /// use rustic_zen::prelude::{HQZLegacy, Lambertian, Layered};
+    ///
+    /// let lacquered = Layered::new(Box::new(HQZLegacy::new(0.0, 0.1, 0.9)), Box::new(Lambertian::new(0.6)));
+    /// ```
+    pub fn new(coat: Box<dyn Material>, base: Box<dyn Material>) -> Self {
+        Self { coat, base }
+    }
+}
+
+impl Material for Layered {
+    fn outcome(
+        &self,
+        direction: &Vector,
+        normal: &Vector,
+        wavelength: f64,
+        alpha: f64,
+        rng: &mut Pcg64Fast,
+    ) -> Option<Outcome> {
+        // Positive when heading into the surface from the side the ray arrived on.
+        let inward = direction.dot(normal).signum();
+        let mut outcome = Outcome::new(*direction, wavelength);
+        let mut at_coat = true;
+
+        for _ in 0..MAX_LAYER_BOUNCES {
+            let layer = if at_coat { &self.coat } else { &self.base };
+            let o = layer.outcome(&outcome.direction, normal, outcome.wavelength, alpha, rng)?;
+            outcome = Outcome {
+                energy: outcome.energy * o.energy,
+                ..o
+            };
+
+            let going_in = outcome.direction.dot(normal) * inward > 0.0;
+            // Leaving out of the top of the coat or the bottom of the base.
+            if at_coat != going_in {
+                return Some(outcome);
+            }
+            at_coat = !at_coat;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Layered, Mix, TwoSided};
+    use geom::Vector;
+    use material::{HQZLegacy, Material};
+    use pcg_rand::Pcg64Fast;
+    use rand::prelude::*;
+
+    fn absorber() -> Box<dyn Material> {
+        Box::new(HQZLegacy::new(0.0, 0.0, 0.0))
+    }
+
+    fn mirror() -> Box<dyn Material> {
+        Box::new(HQZLegacy::new(0.0, 1.0, 0.0))
+    }
+
+    fn clear() -> Box<dyn Material> {
+        Box::new(HQZLegacy::new(0.0, 0.0, 1.0))
+    }
+
+    /// Counts rays (absorbed, reflected, transmitted) over many hits.
+    fn tally(m: &dyn Material, dir: Vector) -> (usize, usize, usize) {
+        let mut rng = Pcg64Fast::from_entropy();
+        let normal = Vector { x: 0.0, y: 1.0 };
+        let mut counts = (0, 0, 0);
+        for _ in 0..10_000 {
+            match m.outcome(&dir, &normal, 500.0, 0.5, &mut rng) {
+                None => counts.0 += 1,
+                Some(o) if o.direction.y * dir.y < 0.0 => counts.1 += 1,
+                Some(_) => counts.2 += 1,
+            }
+        }
+        counts
+    }
+
+    #[test]
+    fn mix_weights() {
+        let m = Mix::new(vec![(1.0, mirror()), (3.0, clear()), (0.0, absorber())]);
+        let (absorbed, reflected, transmitted) = tally(&m, Vector { x: 0.0, y: -1.0 });
+        assert_eq!(absorbed, 0);
+        assert!((reflected as f64 / 10_000.0 - 0.25).abs() < 0.03);
+        assert!((transmitted as f64 / 10_000.0 - 0.75).abs() < 0.03);
+    }
+
+    #[test]
+    fn two_sided_mirror() {
+        let m = TwoSided::new(mirror(), absorber());
+        // Normal is +y, so rays heading down hit the front.
+        assert_eq!(tally(&m, Vector { x: 0.3, y: -1.0 }), (0, 10_000, 0));
+        assert_eq!(tally(&m, Vector { x: 0.3, y: 1.0 }), (10_000, 0, 0));
+    }
+
+    #[test]
+    fn layered_coat_and_base() {
+        // A clear coat shows the base.
+        let m = Layered::new(clear(), mirror());
+        assert_eq!(tally(&m, Vector { x: 0.3, y: -1.0 }), (0, 10_000, 0));
+        // An opaque coat hides it.
+        let m = Layered::new(absorber(), mirror());
+        assert_eq!(tally(&m, Vector { x: 0.3, y: 1.0 }), (10_000, 0, 0));
+        // Coat reflects half, the rest bounces off the base and must come back out,
+        // only rays still trapped after eight passes of the coat (1 in 256) are lost.
+        let coat = Box::new(HQZLegacy::new(0.0, 0.5, 0.5));
+        let m = Layered::new(coat, mirror());
+        let (absorbed, reflected, transmitted) = tally(&m, Vector { x: 0.3, y: -1.0 });
+        assert!(absorbed < 100);
+        assert_eq!(reflected + absorbed, 10_000);
+        assert_eq!(transmitted, 0);
+        // Clear over clear lets everything through.
+        let m = Layered::new(clear(), clear());
+        assert_eq!(tally(&m, Vector { x: 0.3, y: -1.0 }), (0, 0, 10_000));
+    }
+}
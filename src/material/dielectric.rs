@@ -0,0 +1,165 @@
+use geom::Vector;
+use material::DirectionalMaterial;
+use pcg_rand::Pcg64Fast;
+use rand::prelude::*;
+
+/// Transparent material which refracts rays, such as glass or water.
+///
+/// Rays are bent by Snell's law as they cross the surface, or reflected
+/// with the probability given by the Fresnel equations, including total
+/// internal reflection. The refractive index can vary with wavelength, which
+/// splits white light into a spectrum.
+///
+/// Normals must point out of the material, as they do on a `Solid`, so the
+/// material knows if a ray is entering or leaving.
+#[derive(Copy, Clone, Debug)]
+pub struct Dielectric {
+    pub(crate) index: f64,
+    pub(crate) dispersion: f64,
+}
+
+impl Dielectric {
+    /// Creates new instance with provided parameters.
+    ///
+    /// # Parameters:
+    ///  - index: refractive index of the material, relative to its surroundings.
+    pub fn new(index: f64) -> Self {
+        if index <= 0.0 {
+            panic!("Dielectric index must be positive");
+        }
+        Self {
+            index,
+            dispersion: 0.0,
+        }
+    }
+
+    /// Crown glass, an index of 1.5 with the dispersion of BK7.
+    pub fn glass() -> Self {
+        Self::new(1.5046).with_dispersion(0.00420)
+    }
+
+    /// Makes the index depend on wavelength - Chainable varient
+    ///
+    /// The index used is Cauchy's equation, `index + dispersion / wavelength^2`
+    /// with the wavelength in micrometres, so blue light bends more than red.
+    ///
+    /// # Example
+    /// Flint glass, with strong dispersion:
+    /// ```
+    /// use rustic_zen::prelude::Dielectric;
+    ///
+    /// let flint = Dielectric::new(1.5220).with_dispersion(0.00459);
+    /// ```
+    pub fn with_dispersion(mut self, dispersion: f64) -> Self {
+        self.dispersion = dispersion;
+        self
+    }
+
+    /// Refractive index at `wavelength` in nanometres.
+    pub fn index_at(&self, wavelength: f64) -> f64 {
+        // Monochromatic white (0.0) has no wavelength to disperse.
+        if wavelength <= 0.0 {
+            return self.index;
+        }
+        let micrometres = wavelength / 1000.0;
+        self.index + self.dispersion / (micrometres * micrometres)
+    }
+
+    /// Fresnel reflectance for unpolarised light, from the cosines of the angles either side.
+    fn reflectance(eta: f64, cos_in: f64, cos_out: f64) -> f64 {
+        let s = (eta * cos_in - cos_out) / (eta * cos_in + cos_out);
+        let p = (eta * cos_out - cos_in) / (eta * cos_out + cos_in);
+        0.5 * (s * s + p * p)
+    }
+}
+
+impl DirectionalMaterial for Dielectric {
+    fn direction(
+        &self,
+        direction: &Vector,
+        normal: &Vector,
+        wavelength: f64,
+        _alpha: f64,
+        rng: &mut Pcg64Fast,
+    ) -> Option<Vector> {
+        let d = direction.normalized();
+        let mut n = normal.normalized();
+        let mut cos_in = -d.dot(&n);
+        let index = self.index_at(wavelength);
+        // Ratio of indices, from the side the ray is on to the side it's going.
+        let eta = if cos_in > 0.0 {
+            1.0 / index
+        } else {
+            n = Vector { x: -n.x, y: -n.y };
+            cos_in = -cos_in;
+            index
+        };
+
+        let k = 1.0 - eta * eta * (1.0 - cos_in * cos_in);
+        if k < 0.0 {
+            // Total internal reflection
+            return Some(d.reflect(&n));
+        }
+        let cos_out = k.sqrt();
+        if rng.gen_range(0.0, 1.0) < Self::reflectance(eta, cos_in, cos_out) {
+            return Some(d.reflect(&n));
+        }
+        Some(Vector {
+            x: eta * d.x + (eta * cos_in - cos_out) * n.x,
+            y: eta * d.y + (eta * cos_in - cos_out) * n.y,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Dielectric;
+    use geom::Vector;
+    use material::DirectionalMaterial;
+    use pcg_rand::Pcg64Fast;
+    use rand::prelude::*;
+
+    #[test]
+    fn snells_law() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let glass = Dielectric::new(1.5);
+        // Outward normal facing up, rays coming down into the glass at 30 degrees.
+        let normal = Vector { x: 0.0, y: 1.0 };
+        let theta = 30.0f64.to_radians();
+        let down = Vector {
+            x: theta.sin(),
+            y: -theta.cos(),
+        };
+        let mut refracted = 0;
+        for _ in 0..1000 {
+            let out = glass.direction(&down, &normal, 500.0, 0.0, &mut rng).unwrap();
+            if out.y < 0.0 {
+                refracted += 1;
+                assert!((out.x - theta.sin() / 1.5).abs() < 1e-12);
+                assert!((out.magnitude() - 1.0).abs() < 1e-12);
+            } else {
+                assert!((out.x - down.x).abs() < 1e-12 && (out.y + down.y).abs() < 1e-12);
+            }
+        }
+        // About 4% reflected near normal incidence.
+        assert!(refracted > 930 && refracted < 990, "{} refracted", refracted);
+
+        // Leaving the glass past the critical angle always reflects.
+        let steep = Vector {
+            x: 50.0f64.to_radians().sin(),
+            y: 50.0f64.to_radians().cos(),
+        };
+        for _ in 0..100 {
+            let out = glass.direction(&steep, &normal, 500.0, 0.0, &mut rng).unwrap();
+            assert!(out.y < 0.0);
+        }
+    }
+
+    #[test]
+    fn blue_bends_more() {
+        let glass = Dielectric::glass();
+        assert!(glass.index_at(450.0) > glass.index_at(650.0));
+        assert!((glass.index_at(587.6) - 1.5168).abs() < 1e-3);
+        assert_eq!(glass.index_at(0.0), 1.5046);
+    }
+}
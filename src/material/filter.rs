@@ -0,0 +1,326 @@
+use geom::Vector;
+use material::DirectionalMaterial;
+use pcg_rand::Pcg64Fast;
+use polyline::Polyline;
+use rand::prelude::*;
+
+/// Transmission curve of a `Filter` or `Dichroic`, as a function of wavelength in nm.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Passband {
+    /// Transmits wavelengths between the lower (first) and upper (second) cutoff.
+    BandPass(f64, f64),
+    /// Transmits wavelengths longer than the cutoff, such as a red filter.
+    LongPass(f64),
+    /// Transmits wavelengths shorter than the cutoff, such as a blue filter.
+    ShortPass(f64),
+    /// Transmission interpolated from measured data, build this with `Passband::tabulated()`.
+    Tabulated(Polyline),
+}
+
+impl Passband {
+    /// Creates a `Passband` from `(wavelength, transmittance)` pairs.
+    ///
+    /// Transmittance is interpolated linearly between points and held
+    /// constant beyond the first and last. Points may be given in any order,
+    /// transmittances must be within 0.0 to 1.0.
+    ///
+    /// Only the transmitted fraction is given here, use `Measured` for data
+    /// which separates reflectance from absorption.
+    ///
+    /// # Example
+    /// A green filter which leaks a little red:
+    /// ```
+    /// use rustic_zen::prelude::{Filter, Passband};
+    ///
+    /// let green = Filter::new(Passband::tabulated(&[
+    ///     (450.0, 0.0),
+    ///     (530.0, 0.9),
+    ///     (600.0, 0.0),
+    ///     (650.0, 0.1),
+    /// ]));
+    /// ```
+    pub fn tabulated(points: &[(f64, f64)]) -> Self {
+        if points.iter().any(|p| !(0.0..=1.0).contains(&p.1)) {
+            panic!("Tabulated passband transmittance must be between 0.0 and 1.0");
+        }
+        Passband::Tabulated(Polyline::new(points))
+    }
+
+    /// Fraction of light at `wavelength` which is passed, with cutoffs
+    /// softened over roughly `edge` nm.
+    fn transmittance(&self, wavelength: f64, edge: f64) -> f64 {
+        match self {
+            Passband::BandPass(lower, upper) => {
+                step(wavelength - lower, edge) * step(upper - wavelength, edge)
+            }
+            Passband::LongPass(cutoff) => step(wavelength - cutoff, edge),
+            Passband::ShortPass(cutoff) => step(cutoff - wavelength, edge),
+            Passband::Tabulated(c) => c.value_at(wavelength),
+        }
+    }
+}
+
+/// Logistic step from 0.0 to 1.0 about `x = 0`, a hard step if `edge` is zero.
+fn step(x: f64, edge: f64) -> f64 {
+    if edge <= 0.0 {
+        return if x >= 0.0 { 1.0 } else { 0.0 };
+    }
+    // Scaled so the 10% to 90% rise takes `edge` nm.
+    1.0 / (1.0 + f64::exp(-x * 4.394 / edge))
+}
+
+/// Coloured glass, transmits some wavelengths and absorbs the rest.
+///
+/// Transmitted rays carry straight on through the surface.
+#[derive(Clone, Debug)]
+pub struct Filter {
+    pub(crate) passband: Passband,
+    pub(crate) edge: f64,
+}
+
+impl Filter {
+    /// Creates a filter which transmits the given `Passband`, with hard cutoffs.
+    pub fn new(passband: Passband) -> Self {
+        Self { passband, edge: 0.0 }
+    }
+
+    /// Softens the cutoffs so transmission rises from 10% to 90% over
+    /// `width` nm, as real filters do - Chainable varient
+    ///
+    /// Has no effect on `Passband::Tabulated`.
+    ///
+    /// # Example
+    /// ```
+    /// use rustic_zen::prelude::{Filter, Passband};
+    ///
+    /// let red = Filter::new(Passband::LongPass(600.0)).with_edge_width(20.0);
+    /// ```
+    pub fn with_edge_width(mut self, width: f64) -> Self {
+        self.edge = width;
+        self
+    }
+}
+
+impl DirectionalMaterial for Filter {
+    fn direction(
+        &self,
+        direction: &Vector,
+        _normal: &Vector,
+        wavelength: f64,
+        _alpha: f64,
+        rng: &mut Pcg64Fast,
+    ) -> Option<Vector> {
+        if rng.gen_range(0.0, 1.0) < self.passband.transmittance(wavelength, self.edge) {
+            Some(*direction)
+        } else {
+            None
+        }
+    }
+}
+
+/// Dichroic mirror, transmits some wavelengths and reflects the rest.
+///
+/// Nothing is absorbed, so a `Dichroic` splits a white beam into two
+/// complementary colours.
+#[derive(Clone, Debug)]
+pub struct Dichroic {
+    pub(crate) passband: Passband,
+    pub(crate) edge: f64,
+}
+
+impl Dichroic {
+    /// Creates a dichroic mirror which transmits the given `Passband`, with hard cutoffs.
+    pub fn new(passband: Passband) -> Self {
+        Self { passband, edge: 0.0 }
+    }
+
+    /// Softens the cutoffs, as for `Filter::with_edge_width()` - Chainable varient
+    pub fn with_edge_width(mut self, width: f64) -> Self {
+        self.edge = width;
+        self
+    }
+}
+
+impl DirectionalMaterial for Dichroic {
+    fn direction(
+        &self,
+        direction: &Vector,
+        normal: &Vector,
+        wavelength: f64,
+        _alpha: f64,
+        rng: &mut Pcg64Fast,
+    ) -> Option<Vector> {
+        if rng.gen_range(0.0, 1.0) < self.passband.transmittance(wavelength, self.edge) {
+            Some(*direction)
+        } else {
+            Some(direction.reflect(normal))
+        }
+    }
+}
+
+/// Surface with measured transmittance and reflectance curves, as functions of wavelength in nm.
+///
+/// Whatever is neither transmitted nor reflected is absorbed, so measured
+/// data for all three can be reproduced from two curves. Transmitted rays
+/// carry straight on, reflected rays are mirrored.
+#[derive(Clone, Debug)]
+pub struct Measured {
+    pub(crate) transmittance: Polyline,
+    pub(crate) reflectance: Polyline,
+}
+
+impl Measured {
+    /// Creates a surface from `(wavelength, transmittance)` and `(wavelength, reflectance)` pairs.
+    ///
+    /// Both curves are interpolated linearly between points and held constant
+    /// beyond the first and last. Panics if either curve is empty, if any value
+    /// is outside 0.0 to 1.0, or if transmittance and reflectance add up to
+    /// more than 1.0 anywhere.
+    ///
+    /// # Example
+    /// A hot mirror, reflecting infrared and absorbing a little of everything:
+    /// ```
+    /// use rustic_zen::prelude::Measured;
+    ///
+    /// let hot_mirror = Measured::new(
+    ///     &[(650.0, 0.9), (700.0, 0.05)],
+    ///     &[(650.0, 0.05), (700.0, 0.9)],
+    /// );
+    /// ```
+    pub fn new(transmittance: &[(f64, f64)], reflectance: &[(f64, f64)]) -> Self {
+        if transmittance.is_empty() || reflectance.is_empty() {
+            panic!("Measured transmittance and reflectance need at least one point each");
+        }
+        let mut fractions = transmittance.iter().chain(reflectance.iter());
+        if fractions.clone().any(|p| !(0.0..=1.0).contains(&p.1)) {
+            panic!("Measured transmittance and reflectance must be between 0.0 and 1.0");
+        }
+        let m = Self {
+            transmittance: Polyline::new(transmittance),
+            reflectance: Polyline::new(reflectance),
+        };
+        // Both curves are linear between their points, so checking every point checks everywhere.
+        if fractions.any(|p| m.transmittance.value_at(p.0) + m.reflectance.value_at(p.0) > 1.0) {
+            panic!("Measured transmittance and reflectance add up to more than 1.0");
+        }
+        m
+    }
+}
+
+impl DirectionalMaterial for Measured {
+    fn direction(
+        &self,
+        direction: &Vector,
+        normal: &Vector,
+        wavelength: f64,
+        _alpha: f64,
+        rng: &mut Pcg64Fast,
+    ) -> Option<Vector> {
+        let u = rng.gen_range(0.0, 1.0);
+        let t = self.transmittance.value_at(wavelength);
+        if u < t {
+            Some(*direction)
+        } else if u < t + self.reflectance.value_at(wavelength) {
+            Some(direction.reflect(normal))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Dichroic, Filter, Measured, Passband};
+    use geom::Vector;
+    use material::Material;
+    use pcg_rand::Pcg64Fast;
+    use rand::prelude::*;
+
+    /// Fraction of rays at `wavelength` which carry straight on.
+    fn transmitted(m: &dyn Material, wavelength: f64) -> f64 {
+        let mut rng = Pcg64Fast::from_entropy();
+        let normal = Vector { x: 0.0, y: 1.0 };
+        let dir = Vector { x: 0.6, y: -0.8 };
+        let n = 10_000;
+        let mut count = 0;
+        for _ in 0..n {
+            if let Some(o) = m.outcome(&dir, &normal, wavelength, 0.5, &mut rng) {
+                if o.direction == dir {
+                    count += 1;
+                }
+            }
+        }
+        count as f64 / n as f64
+    }
+
+    #[test]
+    fn hard_filters() {
+        let band = Filter::new(Passband::BandPass(500.0, 600.0));
+        assert_eq!(transmitted(&band, 450.0), 0.0);
+        assert_eq!(transmitted(&band, 550.0), 1.0);
+        assert_eq!(transmitted(&band, 650.0), 0.0);
+
+        let long = Filter::new(Passband::LongPass(600.0));
+        assert_eq!(transmitted(&long, 550.0), 0.0);
+        assert_eq!(transmitted(&long, 650.0), 1.0);
+
+        let short = Filter::new(Passband::ShortPass(600.0));
+        assert_eq!(transmitted(&short, 550.0), 1.0);
+        assert_eq!(transmitted(&short, 650.0), 0.0);
+    }
+
+    #[test]
+    fn soft_edges() {
+        let f = Filter::new(Passband::LongPass(600.0)).with_edge_width(20.0);
+        assert!((transmitted(&f, 600.0) - 0.5).abs() < 0.03);
+        assert!((transmitted(&f, 610.0) - 0.9).abs() < 0.03);
+        assert!((transmitted(&f, 590.0) - 0.1).abs() < 0.03);
+    }
+
+    #[test]
+    fn tabulated_interpolates() {
+        let f = Filter::new(Passband::tabulated(&[(600.0, 1.0), (500.0, 0.0)]));
+        assert_eq!(transmitted(&f, 400.0), 0.0);
+        assert!((transmitted(&f, 525.0) - 0.25).abs() < 0.03);
+        assert_eq!(transmitted(&f, 700.0), 1.0);
+    }
+
+    #[test]
+    fn dichroic_reflects_the_rest() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let m = Dichroic::new(Passband::LongPass(600.0));
+        let normal = Vector { x: 0.0, y: 1.0 };
+        let dir = Vector { x: 0.6, y: -0.8 };
+
+        let o = m.outcome(&dir, &normal, 500.0, 0.5, &mut rng).expect("Dichroic absorbed a ray");
+        assert_eq!(o.direction, dir.reflect(&normal));
+        let o = m.outcome(&dir, &normal, 700.0, 0.5, &mut rng).expect("Dichroic absorbed a ray");
+        assert_eq!(o.direction, dir);
+    }
+
+    #[test]
+    fn measured_transmits_reflects_and_absorbs() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let m = Measured::new(&[(500.0, 0.6), (600.0, 0.2)], &[(500.0, 0.1), (600.0, 0.5)]);
+        let normal = Vector { x: 0.0, y: 1.0 };
+        let dir = Vector { x: 0.6, y: -0.8 };
+        let (mut t, mut r, mut a) = (0, 0, 0);
+        for _ in 0..10_000 {
+            match m.outcome(&dir, &normal, 550.0, 0.5, &mut rng) {
+                Some(o) if o.direction == dir => t += 1,
+                Some(_) => r += 1,
+                None => a += 1,
+            }
+        }
+        // 0.4 transmitted, 0.3 reflected and 0.3 absorbed half way between the points.
+        assert!((t as f64 / 10_000.0 - 0.4).abs() < 0.03);
+        assert!((r as f64 / 10_000.0 - 0.3).abs() < 0.03);
+        assert!((a as f64 / 10_000.0 - 0.3).abs() < 0.03);
+    }
+
+    #[test]
+    #[should_panic]
+    fn measured_cant_make_light() {
+        Measured::new(&[(500.0, 0.6), (600.0, 0.6)], &[(400.0, 0.2), (550.0, 0.5)]);
+    }
+}
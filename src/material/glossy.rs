@@ -0,0 +1,175 @@
+use geom::Vector;
+use material::DirectionalMaterial;
+use pcg_rand::Pcg64Fast;
+use rand::prelude::*;
+use sampler::Sample;
+use std::f64::consts::FRAC_PI_2;
+
+/// Rough specular material, somewhere between a mirror and a matte surface.
+///
+/// Reflected rays are spread about the mirror direction by a Phong lobe,
+/// `cos(theta)^exponent`, so a high exponent gives polished metal and a low
+/// one frosted or brushed surfaces. Rays never leave through the surface.
+#[derive(Copy, Clone, Debug)]
+pub struct Glossy {
+    pub(crate) reflectance: f64,
+    pub(crate) exponent: f64,
+    pub(crate) dispersion: Option<(f64, f64)>,
+}
+
+impl Glossy {
+    /// Creates new instance with provided parameters.
+    ///
+    /// # Parameters:
+    ///  - reflectance: probability ray will be reflected, the rest are absorbed.
+    ///  - exponent: sharpness of the lobe around the mirror direction, 0.0 is
+    ///    as rough as it gets and it approaches a mirror as it grows.
+    pub fn new(reflectance: f64, exponent: f64) -> Self {
+        if !(0.0..=1.0).contains(&reflectance) {
+            panic!("Glossy reflectance must be between 0.0 and 1.0");
+        }
+        if exponent < 0.0 {
+            panic!("Glossy exponent must not be negative");
+        }
+        Self {
+            reflectance,
+            exponent,
+            dispersion: None,
+        }
+    }
+
+    /// Makes the roughness depend on wavelength - Chainable varient
+    ///
+    /// The exponent used is `exponent * (wavelength / reference) ^ power`,
+    /// so with a positive power red light reflects more sharply than blue,
+    /// as it would off a surface with scratches close to the wavelength of light.
+    ///
+    /// # Example
+    /// ```
+    /// use rustic_zen::prelude::Glossy;
+    ///
+    /// let brushed = Glossy::new(0.9, 50.0).with_wavelength_dependence(550.0, 2.0);
+    /// ```
+    pub fn with_wavelength_dependence(mut self, reference: f64, power: f64) -> Self {
+        self.dispersion = Some((reference, power));
+        self
+    }
+
+    fn exponent_at(&self, wavelength: f64) -> f64 {
+        match self.dispersion {
+            // Monochromatic white (0.0) has no wavelength to depend on.
+            Some((reference, power)) if wavelength > 0.0 => {
+                self.exponent * (wavelength / reference).powf(power)
+            }
+            _ => self.exponent,
+        }
+    }
+
+    /// Samples an angle from the lobe `cos(theta)^n` on `(-pi/2, pi/2)`.
+    fn lobe_angle(n: f64, rng: &mut Pcg64Fast) -> f64 {
+        loop {
+            if n < 1.0 {
+                // Wide lobes, propose uniformly.
+                let theta = rng.gen_range(-FRAC_PI_2, FRAC_PI_2);
+                if rng.gen_range(0.0, 1.0) < theta.cos().powf(n) {
+                    return theta;
+                }
+            } else {
+                // Narrow lobes, cos(theta)^n <= exp(-n theta^2 / 2) so a
+                // gaussian proposal accepts most of the time.
+                let theta = Sample::Normal(0.0, 1.0 / n.sqrt()).val(rng);
+                if theta.abs() >= FRAC_PI_2 {
+                    continue;
+                }
+                let ratio = theta.cos().powf(n) / f64::exp(-0.5 * n * theta * theta);
+                if rng.gen_range(0.0, 1.0) < ratio {
+                    return theta;
+                }
+            }
+        }
+    }
+}
+
+impl DirectionalMaterial for Glossy {
+    fn direction(
+        &self,
+        direction: &Vector,
+        normal: &Vector,
+        wavelength: f64,
+        _alpha: f64,
+        rng: &mut Pcg64Fast,
+    ) -> Option<Vector> {
+        if rng.gen_range(0.0, 1.0) >= self.reflectance {
+            return None;
+        }
+
+        let mirror = direction.reflect(normal).normalized();
+        let side = mirror.dot(normal);
+        let n = self.exponent_at(wavelength);
+        // Retry lobe samples which would pass through the surface,
+        // grazing mirror directions can reject a lot so give up eventually.
+        for _ in 0..16 {
+            let out = mirror.rotate(Self::lobe_angle(n, rng));
+            if out.dot(normal) * side > 0.0 {
+                return Some(out);
+            }
+        }
+        Some(mirror)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Glossy;
+    use geom::Vector;
+    use material::Material;
+    use pcg_rand::Pcg64Fast;
+    use rand::prelude::*;
+
+    /// Mean absolute angle between outbound rays and the mirror direction.
+    fn spread(m: &Glossy, wavelength: f64) -> f64 {
+        let mut rng = Pcg64Fast::from_entropy();
+        let normal = Vector { x: 0.0, y: 1.0 };
+        let dir = Vector { x: 0.0, y: -1.0 };
+
+        let n = 20_000;
+        let mut sum = 0.0;
+        for _ in 0..n {
+            let o = m.outcome(&dir, &normal, wavelength, 0.5, &mut rng).expect("Reflectance 1.0 absorbed a ray");
+            assert!(o.direction.y > 0.0);
+            sum += o.direction.x.atan2(o.direction.y).abs();
+        }
+        sum / n as f64
+    }
+
+    #[test]
+    fn exponent_controls_roughness() {
+        let rough = spread(&Glossy::new(1.0, 1.0), 500.0);
+        let smooth = spread(&Glossy::new(1.0, 100.0), 500.0);
+        let mirror = spread(&Glossy::new(1.0, 1e6), 500.0);
+        assert!(rough > smooth);
+        assert!(smooth > mirror);
+        assert!(mirror < 0.01);
+        // The mean |theta| of cos(theta) on (-pi/2, pi/2) is pi/2 - 1
+        assert!((rough - (std::f64::consts::FRAC_PI_2 - 1.0)).abs() < 0.02);
+    }
+
+    #[test]
+    fn wavelength_dependence() {
+        let m = Glossy::new(1.0, 50.0).with_wavelength_dependence(550.0, 2.0);
+        assert!(spread(&m, 700.0) < spread(&m, 400.0));
+    }
+
+    #[test]
+    fn grazing_rays_stay_outside() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let m = Glossy::new(1.0, 0.0);
+        let normal = Vector { x: 0.0, y: 1.0 };
+        let dir = Vector { x: 1.0, y: -0.01 };
+
+        for _ in 0..10_000 {
+            let o = m.outcome(&dir, &normal, 500.0, 0.5, &mut rng).expect("Reflectance 1.0 absorbed a ray");
+            assert!(o.direction.y > 0.0);
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use geom::Vector;
+use material::DirectionalMaterial;
+use pcg_rand::Pcg64Fast;
+use rand::prelude::*;
+
+/// Diffraction grating, splitting light into orders fanned out by wavelength.
+///
+/// Each ray is sent into one diffraction order, chosen by the order weights,
+/// at the angle given by the grating equation `sin(out) = sin(in) + m * wavelength / spacing`.
+/// When the chosen order doesn't exist for the ray's wavelength and angle the
+/// ray falls back to the zero order, or is absorbed if the zero order has no weight.
+#[derive(Clone, Debug)]
+pub struct Grating {
+    pub(crate) spacing: f64,
+    pub(crate) transmissive: bool,
+    pub(crate) orders: Vec<(i32, f64)>,
+    pub(crate) zero_order: bool,
+}
+
+impl Grating {
+    /// Creates a reflective grating with the given line spacing in nm.
+    ///
+    /// Rays are split evenly between the -1, 0 and +1 orders.
+    ///
+    /// # Example
+    /// A 600 lines per mm grating:
+    /// ```
+    /// use rustic_zen::prelude::Grating;
+    ///
+    /// let grating = Grating::new(1e6 / 600.0);
+    /// ```
+    pub fn new(spacing: f64) -> Self {
+        if spacing <= 0.0 {
+            panic!("Grating line spacing must be positive");
+        }
+        Self {
+            spacing,
+            transmissive: false,
+            orders: vec![(-1, 1.0 / 3.0), (0, 2.0 / 3.0), (1, 1.0)],
+            zero_order: true,
+        }
+    }
+
+    /// Makes the grating transmit diffracted light instead of reflecting it - Chainable varient
+    pub fn transmissive(mut self) -> Self {
+        self.transmissive = true;
+        self
+    }
+
+    /// Sets which diffraction orders are used and how often - Chainable varient
+    ///
+    /// Takes pairs of `(order, weight)`, weights need not sum to one, but must
+    /// not be negative, and at least one must be positive.
+    ///
+    /// # Example
+    /// A blazed grating sending most light into the first order:
+    /// ```
+    /// use rustic_zen::prelude::Grating;
+    ///
+    /// let blazed = Grating::new(1000.0).with_orders(&[(0, 0.2), (1, 0.7), (2, 0.1)]);
+    /// ```
+    pub fn with_orders(mut self, orders: &[(i32, f64)]) -> Self {
+        if orders.iter().any(|o| o.1 < 0.0) {
+            panic!("Grating order weights must not be negative");
+        }
+        let total: f64 = orders.iter().map(|o| o.1).sum();
+        if total <= 0.0 {
+            panic!("Grating needs at least one order with a positive weight");
+        }
+        let mut sum = 0.0;
+        self.orders = orders
+            .iter()
+            .map(|&(m, w)| {
+                sum += w;
+                (m, sum / total)
+            })
+            .collect();
+        self.zero_order = orders.iter().any(|&(m, w)| m == 0 && w > 0.0);
+        self
+    }
+}
+
+impl DirectionalMaterial for Grating {
+    fn direction(
+        &self,
+        direction: &Vector,
+        normal: &Vector,
+        wavelength: f64,
+        _alpha: f64,
+        rng: &mut Pcg64Fast,
+    ) -> Option<Vector> {
+        let u: f64 = rng.gen_range(0.0, 1.0);
+        let i = self.orders.partition_point(|o| o.1 <= u).min(self.orders.len() - 1);
+        let order = self.orders[i].0;
+
+        let d = direction.normalized();
+        let n = normal.normalized();
+        let t = Vector { x: -n.y, y: n.x };
+        let cos_in = d.dot(&n);
+
+        let mut sin_out = d.dot(&t) + order as f64 * wavelength / self.spacing;
+        if sin_out.abs() > 1.0 {
+            // Evanescent order, nothing propagates at this angle.
+            if !self.zero_order {
+                return None;
+            }
+            sin_out = d.dot(&t);
+        }
+        let mut cos_out = (1.0 - sin_out * sin_out).sqrt().copysign(cos_in);
+        if !self.transmissive {
+            cos_out = -cos_out;
+        }
+        Some(Vector {
+            x: t.x * sin_out + n.x * cos_out,
+            y: t.y * sin_out + n.y * cos_out,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Grating;
+    use geom::Vector;
+    use material::Material;
+    use pcg_rand::Pcg64Fast;
+    use rand::prelude::*;
+
+    fn out(m: &Grating, dir: Vector, wavelength: f64) -> Option<Vector> {
+        let mut rng = Pcg64Fast::from_entropy();
+        let normal = Vector { x: 0.0, y: 1.0 };
+        m.outcome(&dir, &normal, wavelength, 0.5, &mut rng).map(|o| o.direction)
+    }
+
+    #[test]
+    fn first_order_angle() {
+        let m = Grating::new(1000.0).with_orders(&[(1, 1.0)]);
+        let d = out(&m, Vector { x: 0.0, y: -1.0 }, 500.0).expect("First order absorbed");
+        // sin(30 degrees) = 500 / 1000
+        assert!((d.x.abs() - 0.5).abs() < 1e-9);
+        assert!(d.y > 0.0);
+        assert!((d.magnitude() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn zero_order_is_mirror_or_straight() {
+        let dir = Vector { x: 0.6, y: -0.8 };
+        let normal = Vector { x: 0.0, y: 1.0 };
+        let reflect = Grating::new(1000.0).with_orders(&[(0, 1.0)]);
+        let d = out(&reflect, dir, 500.0).expect("Zero order absorbed");
+        let r = dir.reflect(&normal);
+        assert!((d.x - r.x).abs() < 1e-9 && (d.y - r.y).abs() < 1e-9);
+
+        let transmit = reflect.transmissive();
+        let d = out(&transmit, dir, 500.0).expect("Zero order absorbed");
+        assert!((d.x - dir.x).abs() < 1e-9 && (d.y - dir.y).abs() < 1e-9);
+    }
+
+    #[test]
+    fn missing_orders() {
+        let dir = Vector { x: 0.0, y: -1.0 };
+        // Third order needs sin(out) = 1.5
+        let m = Grating::new(1000.0).with_orders(&[(3, 1.0), (0, 0.0)]);
+        assert!(out(&m, dir, 500.0).is_none());
+
+        let m = Grating::new(1000.0).with_orders(&[(3, 1.0), (0, 1e-12)]);
+        let d = out(&m, dir, 500.0).expect("Zero order fallback absorbed");
+        assert!(d.x.abs() < 1e-9 && (d.y - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn orders_spread_by_wavelength() {
+        let m = Grating::new(2000.0).with_orders(&[(1, 1.0)]);
+        let dir = Vector { x: 0.0, y: -1.0 };
+        let blue = out(&m, dir, 450.0).expect("First order absorbed");
+        let red = out(&m, dir, 650.0).expect("First order absorbed");
+        assert!(red.x.abs() > blue.x.abs());
+    }
+}
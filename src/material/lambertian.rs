@@ -0,0 +1,108 @@
+use geom::Vector;
+use material::DirectionalMaterial;
+use pcg_rand::Pcg64Fast;
+use rand::prelude::*;
+
+/// Matte material with Lambertian (cosine weighted) diffuse reflection.
+///
+/// Reflected rays leave on the same side of the surface the ray arrived from,
+/// with directions weighted by the cosine of their angle to the normal, so
+/// most light leaves close to the normal and almost none grazes the surface.
+#[derive(Copy, Clone, Debug)]
+pub struct Lambertian {
+    pub(crate) albedo: f64,
+}
+
+impl Lambertian {
+    /// Creates new instance with provided parameters.
+    ///
+    /// # Parameters:
+    ///  - albedo: probability ray will be reflected, the rest are absorbed.
+    pub fn new(albedo: f64) -> Self {
+        if !(0.0..=1.0).contains(&albedo) {
+            panic!("Lambertian albedo must be between 0.0 and 1.0");
+        }
+        Self { albedo }
+    }
+}
+
+impl DirectionalMaterial for Lambertian {
+    fn direction(
+        &self,
+        direction: &Vector,
+        normal: &Vector,
+        _wavelength: f64,
+        _alpha: f64,
+        rng: &mut Pcg64Fast,
+    ) -> Option<Vector> {
+        if rng.gen_range(0.0, 1.0) >= self.albedo {
+            return None;
+        }
+        Some(cosine_direction(direction, normal, rng))
+    }
+}
+
+/// Samples a direction on the incident side of a surface with a cosine
+/// distribution about the normal.
+///
+/// Does *not* require `normal` to already be normalized, or to face the ray.
+pub fn cosine_direction(direction: &Vector, normal: &Vector, rng: &mut Pcg64Fast) -> Vector {
+    let mut n = normal.normalized();
+    if direction.dot(&n) > 0.0 {
+        n = -n;
+    }
+    // In 2D the inverse CDF of cos(theta) / 2 is asin(2u - 1)
+    let theta = f64::asin(rng.gen_range(-1.0, 1.0));
+    n.rotate(theta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Lambertian;
+    use geom::Vector;
+    use material::Material;
+    use pcg_rand::Pcg64Fast;
+    use rand::prelude::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn reflects_cosine_weighted_on_incident_side() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let m = Lambertian::new(1.0);
+        let normal = Vector { x: 0.0, y: 3.0 };
+        let dir = Vector { x: 1.0, y: 1.0 };
+
+        let n = 100_000;
+        let mut sum = 0.0;
+        for _ in 0..n {
+            let o = m.outcome(&dir, &normal, 500.0, 0.5, &mut rng).expect("Albedo 1.0 absorbed a ray");
+            assert!((o.direction.magnitude() - 1.0).abs() < 1e-9);
+            // The ray came down through the top, it must leave below.
+            assert!(o.direction.y <= 0.0);
+            sum += -o.direction.y;
+        }
+        // Mean cosine to the normal of a 2D Lambertian lobe is pi / 4
+        let mean = sum / n as f64;
+        assert!((mean - PI / 4.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn albedo_absorbs() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let m = Lambertian::new(0.25);
+        let normal = Vector { x: 1.0, y: 0.0 };
+        let dir = Vector { x: -1.0, y: 0.0 };
+
+        let n = 100_000;
+        let hits = (0..n)
+            .filter(|_| m.outcome(&dir, &normal, 500.0, 0.5, &mut rng).is_some())
+            .count();
+        assert!((hits as f64 / n as f64 - 0.25).abs() < 0.01);
+    }
+
+    #[test]
+    #[should_panic]
+    fn albedo_too_high() {
+        Lambertian::new(1.5);
+    }
+}
@@ -0,0 +1,244 @@
+use downcast::AsAny;
+use geom::Vector;
+use pcg_rand::Pcg64Fast;
+use std::f64::consts::PI;
+use rand::prelude::*;
+
+mod composite;
+mod dielectric;
+mod filter;
+mod glossy;
+mod grating;
+mod lambertian;
+mod texture;
+mod thin_film;
+
+pub use self::composite::{Layered, Mix, TwoSided};
+pub use self::dielectric::Dielectric;
+pub use self::filter::{Dichroic, Filter, Measured, Passband};
+pub use self::glossy::Glossy;
+pub use self::grating::Grating;
+pub use self::lambertian::Lambertian;
+pub use self::texture::{AlphaSelect, Blend, Texture};
+pub use self::thin_film::ThinFilm;
+use self::lambertian::cosine_direction;
+
+/// Shader Trait
+///
+/// This trait exposes a single `outcome` method, this is used by Rustic Zen
+/// to calulate the result of a ray interacting with an object.
+///
+/// The trait is exposed to allow library users to define their own shaders.
+/// A lot of information is provided to the outcome method, to give the user
+/// options on how they want their shader to work.
+///
+/// Wavelength is provided instead of colour to encourage the design of physically based
+/// shaders. Colour can be calculated from the wavelength using Rustic's spectrum module.
+///
+/// Shaders which only ever change the direction of a ray can implement
+/// `DirectionalMaterial` instead, which provides this trait for them.
+pub trait Material: AsAny {
+    /**
+     * This function computes the outcome of a ray to object interaction.
+     *
+     * It returns a wrapped `Outcome` holding the direction, wavelength and
+     * energy of the bounced ray. If the ray is absorbed then it returns `None`.
+     *
+     * # Parameters:
+     *  - __direction__: Vector of the direction of the inbound ray.
+     *  - __normal__: Computed normal to the hit surface. This can be used in `direction.reflect(normal)`, to get a mirror reflection.
+     *  - __wavelength__: Wavelength of inbound ray.
+     *  - __alpha__: how far along the object the inbound ray hit. clamped 0.0 to 1.0
+     *  - __rng__: random number generator for use during the function, (don't spawn your own, way to slow.)
+     */
+    fn outcome(
+        &self,
+        direction: &Vector,
+        normal: &Vector,
+        wavelength: f64,
+        alpha: f64,
+        rng: &mut Pcg64Fast,
+    ) -> Option<Outcome>;
+}
+
+/// Result of a ray interacting with a `Material`, describing the outbound ray.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Outcome {
+    /// Direction of the outbound ray.
+    pub direction: Vector,
+    /// Wavelength of the outbound ray, changing this allows fluorescence.
+    pub wavelength: f64,
+    /// Multiplier applied to the energy of the ray, 1.0 leaves it unchanged.
+    ///
+    /// The energy is used as the intensity the rest of the ray's path is drawn with.
+    pub energy: f64,
+}
+
+impl Outcome {
+    /// Creates an outcome which keeps the ray's wavelength and energy.
+    pub fn new(direction: Vector, wavelength: f64) -> Self {
+        Outcome {
+            direction,
+            wavelength,
+            energy: 1.0,
+        }
+    }
+}
+
+/// Shader Trait for shaders which only change the direction of a ray.
+///
+/// This is the simpler form of `Material`, every `DirectionalMaterial` is also
+/// a `Material` which keeps the wavelength and energy of the ray.
+pub trait DirectionalMaterial: AsAny {
+    /**
+     * This function computes the new direction of a ray after hitting an object.
+     *
+     * It returns a wrapped vector of the direction of the bounced ray.
+     * If the ray is absorbed then it returns `None`.
+     *
+     * Parameters are the same as for `Material::outcome()`.
+     */
+    fn direction(
+        &self,
+        direction: &Vector,
+        normal: &Vector,
+        wavelength: f64,
+        alpha: f64,
+        rng: &mut Pcg64Fast,
+    ) -> Option<Vector>;
+}
+
+impl<T: DirectionalMaterial> Material for T {
+    fn outcome(
+        &self,
+        direction: &Vector,
+        normal: &Vector,
+        wavelength: f64,
+        alpha: f64,
+        rng: &mut Pcg64Fast,
+    ) -> Option<Outcome> {
+        self.direction(direction, normal, wavelength, alpha, rng)
+            .map(|d| Outcome::new(d, wavelength))
+    }
+}
+
+/// Reference / Legacy implementation of Material trait.
+///
+/// This implementation models the behavour of shaders from the original HQZ.
+/// HQZ's diffuse reflection sends rays off in any direction, including back
+/// through the surface, `with_lambertian_diffuse()` swaps it for a matte
+/// cosine weighted reflection.
+#[derive(Copy, Clone)]
+pub struct HQZLegacy {
+    pub(crate) d: f64,
+    pub(crate) r: f64,
+    pub(crate) t: f64,
+    pub(crate) lambertian: bool,
+}
+
+impl HQZLegacy {
+    /// Creates new instance with provided parameters.
+    ///
+    /// # Parameters:
+    ///  - d: probability ray will be diffuse reflected
+    ///  - r: probability ray will be specularly reflected
+    ///  - t: probability ray will be transmitted through surface
+    ///
+    /// Probablitity ray will be absorbed is defined from `1.0 - d - r - t`
+    pub fn new(d: f64, r: f64, t: f64) -> Self {
+        if d + r + t > 1.0 {
+            panic!("HQZ Legacy shader cooefficents > 1.0");
+        }
+        Self {
+            d,
+            r,
+            t,
+            lambertian: false,
+        }
+    }
+
+    /// Makes diffuse reflections cosine weighted about the normal, on the
+    /// side of the surface the ray arrived from - Chainable varient
+    ///
+    /// # Example
+    /// ```
+    /// use rustic_zen::prelude::HQZLegacy;
+    ///
+    /// let matte_wall = HQZLegacy::new(0.8, 0.0, 0.0).with_lambertian_diffuse();
+    /// ```
+    pub fn with_lambertian_diffuse(mut self) -> Self {
+        self.lambertian = true;
+        self
+    }
+}
+
+impl Default for HQZLegacy {
+    /// Creates a new instance with default arguments,
+    /// This is used in many of the the tests and examples.
+    /// d: 0.1, r: 0.4, t: 0.4, a: 0.1
+    fn default() -> Self {
+        Self {
+            d: 0.1,
+            r: 0.4,
+            t: 0.4,
+            lambertian: false,
+        }
+    }
+}
+
+impl DirectionalMaterial for HQZLegacy {
+    fn direction(
+        &self,
+        direction: &Vector,
+        normal: &Vector,
+        _wavelength: f64,
+        _alpha: f64,
+        rng: &mut Pcg64Fast,
+    ) -> Option<Vector> {
+        let f: f64 = rng.gen_range(0.0,1.0);
+
+        if f <= self.d {
+            if self.lambertian {
+                return Some(cosine_direction(direction, normal, rng));
+            }
+            let angle = rng.gen_range(0.0, 2.0 * PI);
+            return Some(Vector {
+                x: f64::cos(angle),
+                y: f64::sin(angle),
+            });
+        }
+
+        if f <= self.d + self.r {
+            let angle = direction.reflect(normal);
+            return Some(angle);
+        }
+
+        if f <= self.d + self.r + self.t {
+            let angle = *direction;
+            return Some(angle);
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HQZLegacy, Material};
+    use geom::Vector;
+    use pcg_rand::Pcg64Fast;
+    use rand::prelude::*;
+
+    #[test]
+    fn hqz_lambertian_stays_on_incident_side() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let m = HQZLegacy::new(1.0, 0.0, 0.0).with_lambertian_diffuse();
+        let normal = Vector { x: 0.0, y: 1.0 };
+        let dir = Vector { x: 0.3, y: -1.0 };
+
+        for _ in 0..10_000 {
+            let o = m.outcome(&dir, &normal, 500.0, 0.5, &mut rng).expect("d of 1.0 absorbed a ray");
+            assert!(o.direction.y >= 0.0);
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use geom::Vector;
+use material::{Material, Outcome};
+use polyline::Polyline;
+use pcg_rand::Pcg64Fast;
+use rand::prelude::*;
+
+/// A 1D texture, giving a value between 0.0 and 1.0 for each point along an object.
+///
+/// Textures are looked up by the `alpha` passed to `Material::outcome`,
+/// which runs from 0.0 at the start of an object to 1.0 at the end.
+#[derive(Clone, Debug)]
+pub enum Texture {
+    /// The same value all along the object.
+    Constant(f64),
+    /// Piecewise constant stripes, build this with `Texture::stripes()`.
+    Stripes(Polyline),
+    /// Piecewise linear gradient, build this with `Texture::gradient()`.
+    Gradient(Polyline),
+    /// Repeats a texture every `period` of alpha, build this with `Texture::periodic()`.
+    Periodic(Box<Texture>, f64),
+}
+
+impl Texture {
+    /// Creates stripes from `(start, value)` pairs, each value holds from
+    /// its start until the next stripe begins.
+    ///
+    /// # Example
+    /// A barcode, used with `Blend` between an absorber and a mirror:
+    /// ```
+    /// use rustic_zen::prelude::Texture;
+    ///
+    /// let barcode = Texture::stripes(&[(0.0, 1.0), (0.1, 0.0), (0.15, 1.0), (0.4, 0.0), (0.6, 1.0)]);
+    /// ```
+    pub fn stripes(stripes: &[(f64, f64)]) -> Self {
+        Texture::Stripes(Polyline::new(&checked(stripes)))
+    }
+
+    /// Creates a gradient from `(alpha, value)` pairs, interpolated
+    /// linearly between points and held beyond the first and last.
+    pub fn gradient(points: &[(f64, f64)]) -> Self {
+        Texture::Gradient(Polyline::new(&checked(points)))
+    }
+
+    /// Repeats `texture` every `period` along the object, `texture` is
+    /// stretched over a single period so should be defined from 0.0 to 1.0.
+    ///
+    /// # Example
+    /// Twenty slits, each a fifth of their pitch wide:
+    /// ```
+    /// use rustic_zen::prelude::Texture;
+    ///
+    /// let slits = Texture::periodic(Texture::stripes(&[(0.0, 1.0), (0.2, 0.0)]), 1.0 / 20.0);
+    /// ```
+    pub fn periodic(texture: Texture, period: f64) -> Self {
+        if period <= 0.0 {
+            panic!("Texture period must be positive");
+        }
+        Texture::Periodic(Box::new(texture), period)
+    }
+
+    /// Value of the texture at `alpha`.
+    pub fn value_at(&self, alpha: f64) -> f64 {
+        match self {
+            Texture::Constant(v) => *v,
+            Texture::Stripes(c) => c.step_at(alpha),
+            Texture::Gradient(c) => c.value_at(alpha),
+            Texture::Periodic(t, period) => {
+                let x = alpha / period;
+                t.value_at(x - x.floor())
+            }
+        }
+    }
+}
+
+fn checked(points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    if points.iter().any(|p| !(0.0..=1.0).contains(&p.1)) {
+        panic!("Texture values must be between 0.0 and 1.0");
+    }
+    points.to_vec()
+}
+
+/// Chooses between two materials by a `Texture`.
+///
+/// Where the texture is 0.0 the first material is always used, where it is
+/// 1.0 the second is, and in between they are mixed in proportion. Stripes
+/// make patterned mirrors, gradients fade from one material to another.
+pub struct Blend {
+    pub(crate) a: Box<dyn Material>,
+    pub(crate) b: Box<dyn Material>,
+    pub(crate) texture: Texture,
+}
+
+impl Blend {
+    /// Creates new instance, using `b` with probability `texture.value_at(alpha)` and `a` otherwise.
+    ///
+    /// # Example
+    /// A mirror which fades to black:
+    /// ```
+    /// use rustic_zen::prelude::{Blend, HQZLegacy, Texture};
+    ///
+    /// let fade = Blend::new(
+    ///     Box::new(HQZLegacy::new(0.0, 1.0, 0.0)),
+    ///     Box::new(HQZLegacy::new(0.0, 0.0, 0.0)),
+    ///     Texture::gradient(&[(0.0, 0.0), (1.0, 1.0)]),
+    /// );
+    /// ```
+    pub fn new(a: Box<dyn Material>, b: Box<dyn Material>, texture: Texture) -> Self {
+        Self { a, b, texture }
+    }
+}
+
+impl Material for Blend {
+    fn outcome(
+        &self,
+        direction: &Vector,
+        normal: &Vector,
+        wavelength: f64,
+        alpha: f64,
+        rng: &mut Pcg64Fast,
+    ) -> Option<Outcome> {
+        let m = if rng.gen_range(0.0, 1.0) < self.texture.value_at(alpha) {
+            &self.b
+        } else {
+            &self.a
+        };
+        m.outcome(direction, normal, wavelength, alpha, rng)
+    }
+}
+
+/// Selects a material by which range of alpha the ray hit.
+///
+/// Ranges are checked in the order they were added and the first containing
+/// alpha is used, anywhere outside every range uses the default material.
+/// Ranges include their start but not their end, except a range ending at 1.0
+/// which includes the end of the object.
+/// This makes slits and apertures from a single line.
+pub struct AlphaSelect {
+    pub(crate) default: Box<dyn Material>,
+    pub(crate) ranges: Vec<(f64, f64, Box<dyn Material>)>,
+}
+
+impl AlphaSelect {
+    /// Creates new instance using `default` all along the object.
+    pub fn new(default: Box<dyn Material>) -> Self {
+        Self {
+            default,
+            ranges: Vec::new(),
+        }
+    }
+
+    /// Uses `material` for alpha from `start` up to `end` - Chainable varient
+    ///
+    /// # Example
+    /// An absorbing wall with a transparent slit in the middle:
+    /// ```
+    /// use rustic_zen::prelude::{AlphaSelect, HQZLegacy};
+    ///
+    /// let slit = AlphaSelect::new(Box::new(HQZLegacy::new(0.0, 0.0, 0.0)))
+    ///     .with_range(0.45, 0.55, Box::new(HQZLegacy::new(0.0, 0.0, 1.0)));
+    /// ```
+    pub fn with_range(mut self, start: f64, end: f64, material: Box<dyn Material>) -> Self {
+        if end < start {
+            panic!("AlphaSelect range must not end before it starts");
+        }
+        self.ranges.push((start, end, material));
+        self
+    }
+}
+
+impl Material for AlphaSelect {
+    fn outcome(
+        &self,
+        direction: &Vector,
+        normal: &Vector,
+        wavelength: f64,
+        alpha: f64,
+        rng: &mut Pcg64Fast,
+    ) -> Option<Outcome> {
+        let m = self
+            .ranges
+            .iter()
+            .find(|r| alpha >= r.0 && (alpha < r.1 || (alpha >= 1.0 && r.1 >= 1.0)))
+            .map_or(&self.default, |r| &r.2);
+        m.outcome(direction, normal, wavelength, alpha, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AlphaSelect, Blend, Texture};
+    use geom::Vector;
+    use material::{HQZLegacy, Material};
+    use pcg_rand::Pcg64Fast;
+    use rand::prelude::*;
+
+    fn absorber() -> Box<dyn Material> {
+        Box::new(HQZLegacy::new(0.0, 0.0, 0.0))
+    }
+
+    fn mirror() -> Box<dyn Material> {
+        Box::new(HQZLegacy::new(0.0, 1.0, 0.0))
+    }
+
+    /// Fraction of rays surviving a hit at `alpha`.
+    fn survival(m: &dyn Material, alpha: f64) -> f64 {
+        let mut rng = Pcg64Fast::from_entropy();
+        let normal = Vector { x: 0.0, y: 1.0 };
+        let dir = Vector { x: 0.0, y: -1.0 };
+        let n = 10_000;
+        let count = (0..n)
+            .filter(|_| m.outcome(&dir, &normal, 500.0, alpha, &mut rng).is_some())
+            .count();
+        count as f64 / n as f64
+    }
+
+    #[test]
+    fn stripes_and_gradients() {
+        let t = Texture::stripes(&[(0.0, 1.0), (0.25, 0.0), (0.5, 0.5)]);
+        assert_eq!(t.value_at(-1.0), 1.0);
+        assert_eq!(t.value_at(0.1), 1.0);
+        assert_eq!(t.value_at(0.25), 0.0);
+        assert_eq!(t.value_at(0.9), 0.5);
+
+        let t = Texture::gradient(&[(0.0, 0.0), (1.0, 1.0)]);
+        assert_eq!(t.value_at(0.25), 0.25);
+    }
+
+    #[test]
+    fn periodic_repeats() {
+        let t = Texture::periodic(Texture::stripes(&[(0.0, 1.0), (0.5, 0.0)]), 0.1);
+        assert_eq!(t.value_at(0.02), 1.0);
+        assert_eq!(t.value_at(0.07), 0.0);
+        assert_eq!(t.value_at(0.92), 1.0);
+        assert_eq!(t.value_at(0.97), 0.0);
+    }
+
+    #[test]
+    fn blend_follows_texture() {
+        let m = Blend::new(absorber(), mirror(), Texture::gradient(&[(0.0, 0.0), (1.0, 1.0)]));
+        assert_eq!(survival(&m, 0.0), 0.0);
+        assert!((survival(&m, 0.3) - 0.3).abs() < 0.03);
+        assert_eq!(survival(&m, 1.0), 1.0);
+    }
+
+    #[test]
+    fn select_slit() {
+        let m = AlphaSelect::new(absorber()).with_range(0.4, 0.6, mirror());
+        assert_eq!(survival(&m, 0.2), 0.0);
+        assert_eq!(survival(&m, 0.5), 1.0);
+        assert_eq!(survival(&m, 0.6), 0.0);
+
+        // The last range reaches the very end of the object.
+        let m = AlphaSelect::new(absorber()).with_range(0.5, 1.0, mirror());
+        assert_eq!(survival(&m, 1.0), 1.0);
+        assert_eq!(survival(&m, 0.0), 0.0);
+    }
+}
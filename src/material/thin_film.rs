@@ -0,0 +1,161 @@
+use geom::Vector;
+use material::DirectionalMaterial;
+use polyline::Polyline;
+use pcg_rand::Pcg64Fast;
+use rand::prelude::*;
+use std::f64::consts::PI;
+
+/// Thin film interference, as seen in soap bubbles and oil slicks.
+///
+/// Light reflecting off the front and back of the film interferes, so the
+/// chance a ray is reflected rather than transmitted depends on its wavelength,
+/// the angle it hits at and the thickness of the film. Nothing is absorbed,
+/// and transmitted rays carry straight on as the film is surrounded by the
+/// same medium on both sides.
+#[derive(Clone, Debug)]
+pub struct ThinFilm {
+    pub(crate) thickness: Polyline,
+    pub(crate) film_index: f64,
+    pub(crate) surrounding_index: f64,
+}
+
+impl ThinFilm {
+    /// Creates new instance with provided parameters.
+    ///
+    /// # Parameters:
+    ///  - thickness: thickness of the film in nm.
+    ///  - film_index: refractive index of the film, about 1.33 for soapy water.
+    ///
+    /// The film is surrounded by air (refractive index 1.0).
+    pub fn new(thickness: f64, film_index: f64) -> Self {
+        if thickness < 0.0 {
+            panic!("ThinFilm thickness must not be negative");
+        }
+        Self {
+            thickness: Polyline::new(&[(0.0, thickness)]),
+            film_index,
+            surrounding_index: 1.0,
+        }
+    }
+
+    /// Sets the refractive index of the medium either side of the film - Chainable varient
+    pub fn with_surrounding_index(mut self, index: f64) -> Self {
+        self.surrounding_index = index;
+        self
+    }
+
+    /// Varies the thickness along the object - Chainable varient
+    ///
+    /// Takes pairs of `(alpha, thickness)`, where alpha is how far along the
+    /// object (0.0 to 1.0) the thickness applies. Thickness is interpolated
+    /// linearly between points and held beyond the first and last.
+    ///
+    /// # Example
+    /// A soap film draining so it is thicker at the bottom:
+    /// ```
+    /// use rustic_zen::prelude::ThinFilm;
+    ///
+    /// let soap = ThinFilm::new(0.0, 1.33).with_thickness_profile(&[(0.0, 100.0), (1.0, 900.0)]);
+    /// ```
+    pub fn with_thickness_profile(mut self, profile: &[(f64, f64)]) -> Self {
+        if profile.iter().any(|p| p.1 < 0.0) {
+            panic!("ThinFilm thickness must not be negative");
+        }
+        self.thickness = Polyline::new(profile);
+        self
+    }
+
+    /// Probability of reflection for unpolarised light, from the Airy formula.
+    fn reflectance(&self, cos_in: f64, wavelength: f64, thickness: f64) -> f64 {
+        let (n1, n2) = (self.surrounding_index, self.film_index);
+        let sin_film = n1 / n2 * (1.0 - cos_in * cos_in).sqrt();
+        if sin_film >= 1.0 {
+            // Total internal reflection at the front face.
+            return 1.0;
+        }
+        let cos_film = (1.0 - sin_film * sin_film).sqrt();
+
+        // Front face fresnel coefficients, the back face is the same with the sign flipped.
+        let rs = (n1 * cos_in - n2 * cos_film) / (n1 * cos_in + n2 * cos_film);
+        let rp = (n2 * cos_in - n1 * cos_film) / (n2 * cos_in + n1 * cos_film);
+        let cos_delta = f64::cos(4.0 * PI * n2 * thickness * cos_film / wavelength);
+
+        let airy = |r: f64| {
+            let r2 = r * r;
+            2.0 * r2 * (1.0 - cos_delta) / (1.0 + r2 * r2 - 2.0 * r2 * cos_delta)
+        };
+        (airy(rs) + airy(rp)) / 2.0
+    }
+}
+
+impl DirectionalMaterial for ThinFilm {
+    fn direction(
+        &self,
+        direction: &Vector,
+        normal: &Vector,
+        wavelength: f64,
+        alpha: f64,
+        rng: &mut Pcg64Fast,
+    ) -> Option<Vector> {
+        let cos_in = direction.normalized().dot(&normal.normalized()).abs();
+        let thickness = self.thickness.value_at(alpha);
+        if rng.gen_range(0.0, 1.0) < self.reflectance(cos_in, wavelength, thickness) {
+            Some(direction.reflect(normal))
+        } else {
+            Some(*direction)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ThinFilm;
+    use geom::Vector;
+    use material::Material;
+    use pcg_rand::Pcg64Fast;
+    use rand::prelude::*;
+
+    #[test]
+    fn quarter_and_half_wave() {
+        let n = 1.5;
+        let m = ThinFilm::new(0.0, n);
+        let wavelength = 600.0;
+        // Normal incidence on a single surface reflects ((n - 1) / (n + 1))^2
+        let r = (n - 1.0) / (n + 1.0);
+        let peak = 4.0 * r * r / ((1.0 + r * r) * (1.0 + r * r));
+
+        let quarter = wavelength / (4.0 * n);
+        assert!((m.reflectance(1.0, wavelength, quarter) - peak).abs() < 1e-12);
+        assert!(m.reflectance(1.0, wavelength, 2.0 * quarter).abs() < 1e-12);
+        assert!(m.reflectance(1.0, wavelength, 0.0).abs() < 1e-12);
+        // Thicker films pick out colours, five quarter waves of 600nm is three half waves of 500nm
+        let thick = 5.0 * quarter;
+        assert!((m.reflectance(1.0, wavelength, thick) - peak).abs() < 1e-12);
+        assert!(m.reflectance(1.0, 500.0, thick).abs() < 1e-12);
+    }
+
+    #[test]
+    fn thickness_profile() {
+        let mut rng = Pcg64Fast::from_entropy();
+        // Half wave at the start, nothing reflects.
+        let m = ThinFilm::new(0.0, 1.5).with_thickness_profile(&[(0.0, 200.0), (1.0, 100.0)]);
+        let normal = Vector { x: 0.0, y: 1.0 };
+        let dir = Vector { x: 0.0, y: -1.0 };
+
+        let mut start = 0;
+        let mut end = 0;
+        for _ in 0..10_000 {
+            let o = m.outcome(&dir, &normal, 600.0, 0.0, &mut rng).expect("ThinFilm absorbed a ray");
+            if o.direction.y > 0.0 {
+                start += 1;
+            }
+            let o = m.outcome(&dir, &normal, 600.0, 1.0, &mut rng).expect("ThinFilm absorbed a ray");
+            if o.direction.y > 0.0 {
+                end += 1;
+            }
+        }
+        assert_eq!(start, 0);
+        // Quarter wave at the end, about 14.8% reflects.
+        assert!((end as f64 / 10_000.0 - 0.148).abs() < 0.02);
+    }
+}
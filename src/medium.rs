@@ -0,0 +1,203 @@
+//! Participating media, such as fog, smoke and haze.
+//!
+//! A `Medium` fills a closed `Region` with particles which scatter rays
+//! part way along their path instead of at a surface. This is what makes
+//! beams of light visible as they cross a scene.
+
+use geom::{Point, Vector};
+use pcg_rand::Pcg64Fast;
+use rand::prelude::*;
+use region::Region;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/// Distribution of the angle a ray is turned through when it scatters.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Phase {
+    /// Scatter in any direction with equal probability.
+    Isotropic,
+    /// Henyey-Greenstein scattering with asymmetry `g` between -1.0 and 1.0,
+    /// positive values scatter forwards, as in haze, negative values backwards.
+    ///
+    /// In 2D this is the wrapped Cauchy distribution, which keeps the meaning
+    /// of `g` as the mean cosine of the scattering angle.
+    HenyeyGreenstein(f64),
+}
+
+impl Phase {
+    /// Samples the angle, in radians, a ray is turned through.
+    fn angle(&self, rng: &mut Pcg64Fast) -> f64 {
+        let u: f64 = rng.gen_range(0.0, 1.0);
+        match self {
+            Phase::Isotropic => 2.0 * PI * (u - 0.5),
+            Phase::HenyeyGreenstein(g) => {
+                2.0 * f64::atan((1.0 - g) / (1.0 + g) * f64::tan(PI * (u - 0.5)))
+            }
+        }
+    }
+}
+
+/// A scattering medium filling a `Region`.
+///
+/// # Example
+/// A bank of haze, scattering on average once every 200 pixels:
+/// ```
+/// use rustic_zen::prelude::{Medium, Phase, Point, Region};
+///
+/// let haze = Medium::new(
+///     Region::Circle {
+///         center: Point { x: 960.0, y: 540.0 },
+///         radius: 400.0,
+///     },
+///     1.0 / 200.0,
+/// )
+/// .with_phase(Phase::HenyeyGreenstein(0.7));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Medium {
+    pub(crate) region: Region,
+    pub(crate) density: f64,
+    pub(crate) albedo: f64,
+    pub(crate) phase: Phase,
+}
+
+impl Medium {
+    /// Creates new instance with provided parameters.
+    ///
+    /// # Parameters:
+    ///  - region: area filled by the medium.
+    ///  - density: chance of scattering per pixel travelled, the mean distance
+    ///    between scattering events is `1.0 / density`.
+    ///
+    /// Nothing is absorbed and rays scatter isotropically until changed with
+    /// `with_albedo()` and `with_phase()`.
+    pub fn new(region: Region, density: f64) -> Self {
+        if density < 0.0 {
+            panic!("Medium density must not be negative");
+        }
+        Self {
+            region,
+            density,
+            albedo: 1.0,
+            phase: Phase::Isotropic,
+        }
+    }
+
+    /// Sets the probability a ray survives a scattering event, the rest are absorbed - Chainable varient
+    pub fn with_albedo(mut self, albedo: f64) -> Self {
+        if !(0.0..=1.0).contains(&albedo) {
+            panic!("Medium albedo must be between 0.0 and 1.0");
+        }
+        self.albedo = albedo;
+        self
+    }
+
+    /// Sets the phase function used when rays scatter - Chainable varient
+    pub fn with_phase(mut self, phase: Phase) -> Self {
+        self.phase = phase;
+        self
+    }
+
+    /// Samples the distance along the ray to the next scattering event,
+    /// or `None` if the ray travels `max` without scattering.
+    pub(crate) fn free_flight(
+        &self,
+        origin: &Point,
+        direction: &Vector,
+        max: f64,
+        rng: &mut Pcg64Fast,
+    ) -> Option<f64> {
+        if self.density <= 0.0 {
+            return None;
+        }
+        let spans = self.region.spans(origin, direction, max);
+        if spans.is_empty() {
+            return None;
+        }
+        // Optical depth to travel before scattering.
+        let mut depth = -f64::ln(1.0 - rng.gen_range(0.0, 1.0)) / self.density;
+        for (start, end) in spans {
+            if depth < end - start {
+                return Some(start + depth);
+            }
+            depth -= end - start;
+        }
+        None
+    }
+
+    /// New direction of a ray scattered by the medium, or `None` if absorbed.
+    pub(crate) fn scatter(&self, direction: &Vector, rng: &mut Pcg64Fast) -> Option<Vector> {
+        if rng.gen_range(0.0, 1.0) >= self.albedo {
+            return None;
+        }
+        Some(direction.rotate(self.phase.angle(rng)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Medium, Phase};
+    use geom::{Point, Vector};
+    use pcg_rand::Pcg64Fast;
+    use rand::prelude::*;
+    use region::Region;
+
+    fn square() -> Region {
+        Region::Polygon(vec![
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 10.0, y: 0.0 },
+            Point { x: 10.0, y: 10.0 },
+            Point { x: 0.0, y: 10.0 },
+        ])
+    }
+
+    #[test]
+    fn free_flight_mean() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let m = Medium::new(square(), 0.5);
+        let origin = Point { x: 0.0, y: 5.0 };
+        let dir = Vector { x: 1.0, y: 0.0 };
+
+        let n = 20_000;
+        let mut sum = 0.0;
+        let mut escaped = 0;
+        for _ in 0..n {
+            match m.free_flight(&origin, &dir, 100.0, &mut rng) {
+                Some(t) => {
+                    assert!(t < 10.0);
+                    sum += t;
+                }
+                None => escaped += 1,
+            }
+        }
+        // exp(-0.5 * 10) of rays cross the whole square.
+        let expect = f64::exp(-5.0);
+        assert!((escaped as f64 / n as f64 - expect).abs() < 0.005);
+        // Mean of an exponential truncated at 10.
+        let mean = 2.0 - 10.0 * expect / (1.0 - expect);
+        assert!((sum / (n - escaped) as f64 - mean).abs() < 0.05);
+    }
+
+    #[test]
+    fn henyey_greenstein_mean_cosine() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let dir = Vector { x: 1.0, y: 0.0 };
+        for &g in &[-0.5, 0.0, 0.8] {
+            let m = Medium::new(square(), 1.0).with_phase(Phase::HenyeyGreenstein(g));
+            let n = 20_000;
+            let sum: f64 = (0..n)
+                .map(|_| m.scatter(&dir, &mut rng).expect("Albedo 1.0 absorbed a ray").x)
+                .sum();
+            assert!((sum / n as f64 - g).abs() < 0.02);
+        }
+    }
+
+    #[test]
+    fn albedo_absorbs() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let m = Medium::new(square(), 1.0).with_albedo(0.0);
+        assert!(m.scatter(&Vector { x: 1.0, y: 0.0 }, &mut rng).is_none());
+    }
+}
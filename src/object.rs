@@ -1,204 +1,428 @@
+use downcast::AsAny;
 use geom::{Point, Rect, Vector, Matrix};
 use pcg_rand::Pcg64Fast;
 use sampler::Sample;
 use std::f64::consts::PI;
 
 use material::Material;
+use ray::SELF_HIT_DISTANCE;
+use region::Region;
 
-/// Holds a definition of an object
+/// Geometry Trait
 ///
-/// Interally contains the associated logic.
-
-pub enum Object {
-    /// Straight line varient
-    Line {
-        /// Material used
-        material: Box<Material>,
-        /// Starting x Position
-        x0: Sample,
-        /// Starting y Position
-        y0: Sample,
-        /// Length in x Axis
-        dx: Sample,
-        /// Length in y Axis
-        dy: Sample,
-    },
-    /// Curve Varient, completely untested and in places inplemented
-    /// as a straight line.
-    ///
-    /// **Do not Use!.**
-    Curve {
-        /// Material used
-        material: Box<Material>,
-        /// Starting x Position
-        x0: Sample,
-        /// Starting y Position
-        y0: Sample,
-        /// Starting angle
-        a0: Sample,
-        /// Length in x Axis
-        dx: Sample,
-        /// Length in y Axis
-        dy: Sample,
-        /// Starting ending angle? maybe?
-        da: Sample,
-    },
-}
+/// Every object in a scene implements this trait, which tests rays against
+/// the object's shape and gives the `Material` used where they hit.
+///
+/// The trait is exposed to allow library users to define their own shapes,
+/// `Line` and `Curve` are the built in implementations.
+pub trait Object: AsAny {
+    /**
+     * Tests if the inbound ray actually hit the object,
+     * if so it returns the coords of the hit, the normal to the hit surface,
+     * how far along the object the hit was and the material there.
+     *
+     * The object's samples should be evaluated at `time`.
+     *
+     * If miss it returns None
+     *
+     * # Parameters:
+     *  - __origin__: Starting point of the ray.
+     *  - __dir__: Direction of the ray, not necessarily normalised.
+     *  - __time__: Time the ray is being traced at, for animated objects.
+     *  - __rng__: random number generator for sampling the object, (don't spawn your own, way to slow.)
+     */
+    fn get_hit(&self, origin: &Point, dir: &Vector, time: f64, rng: &mut Pcg64Fast) -> Option<Hit<'_>>;
 
-impl Object {
-    /// Returns a rectangle enclosing The Object.
-    /// Currently does not support curved lines, cos math is hard.
-    #[inline(always)]
-    pub fn bounds(&self) -> Rect {
-        match self {
-            Object::Curve { .. } => {
-                // TODO: implement this,
-                // its the same algorithm as for the line,
-                // the curve will always fit in the box.
-                unimplemented!();
-            }
-            Object::Line { x0, y0, dx, dy, .. } => {
-                let mut x_s = [
-                    x0.bounds().0,
-                    x0.bounds().1,
-                    x0.bounds().0 + dx.bounds().0,
-                    x0.bounds().0 + dx.bounds().1,
-                    x0.bounds().1 + dx.bounds().0,
-                    x0.bounds().1 + dx.bounds().1,
-                ];
-                x_s.sort_by(|a, b| a.partial_cmp(b).unwrap());
-
-                let mut y_s = [
-                    y0.bounds().0,
-                    y0.bounds().1,
-                    y0.bounds().0 + dy.bounds().0,
-                    y0.bounds().0 + dy.bounds().1,
-                    y0.bounds().1 + dy.bounds().0,
-                    y0.bounds().1 + dy.bounds().1,
-                ];
-                y_s.sort_by(|a, b| a.partial_cmp(b).unwrap());
-
-                let p0 = Point {
-                    x: x_s[0],
-                    y: y_s[0],
-                };
-
-                let p1 = Point {
-                    x: x_s[x_s.len()],
-                    y: y_s[y_s.len()],
-                };
-
-                return Rect::from_points(&p0, &p1);
-            }
-        }
+    /**
+     * Like `get_hit`, but ignores hits closer than `min_t` multiples of `dir`.
+     *
+     * The scene uses this to skip the surface a ray is leaving. Distances in
+     * multiples of `dir` don't change when the ray is transformed, so groups and
+     * instances pass `min_t` straight on to their contents. Objects made of
+     * several surfaces should override this to look past the near ones, the
+     * default only discards a near hit.
+     */
+    fn get_hit_after(
+        &self,
+        origin: &Point,
+        dir: &Vector,
+        time: f64,
+        rng: &mut Pcg64Fast,
+        min_t: f64,
+    ) -> Option<Hit<'_>> {
+        self.get_hit(origin, dir, time, rng)
+            .filter(|hit| (hit.point - *origin).dot(dir) / dir.dot(dir) >= min_t)
     }
 
+    /// Returns a rectangle enclosing every possible position of the object.
+    fn bounds(&self) -> Rect;
+
     /**
-     * Returns a reference to the material used in this object
+     * Returns the point `alpha` of the way along the object at `time`, with the
+     * normal to the surface there, so the object can be used in an `AreaLight`.
+     *
+     * Objects which can't emit light return `None`, which is the default.
      */
-    pub fn get_material(&self) -> &Box<Material> {
-        match self {
-            Object::Curve { material, .. } => material,
-            Object::Line { material, .. } => material,
-        }
+    fn surface_at(&self, _alpha: f64, _time: f64, _rng: &mut Pcg64Fast) -> Option<Hit<'_>> {
+        None
     }
 
     /**
-     * Tests if the inbound ray actually hit the object,
-     * if so it returns the coords of the hit, followed by the normal
-     * to the hit surface.
-     *
-     * If miss it returns None
+     * Expected length of the object at `time`, used to weight `AreaLight`s.
      *
-     * This test assumes you have done a box test to
-     * check the bounds of the line first
+     * Defaults to 0.0, for objects which can't emit light.
      */
+    fn length(&self, _time: f64) -> f64 {
+        0.0
+    }
+}
+
+/// Where a ray hit an `Object`.
+#[derive(Copy, Clone)]
+pub struct Hit<'a> {
+    /// Point the ray hit.
+    pub point: Point,
+    /// Normal to the surface at the hit, does not need to be normalised.
+    pub normal: Vector,
+    /// How far along the object the hit was, from 0.0 to 1.0.
+    pub alpha: f64,
+    /// Material of the object where it was hit.
+    pub material: &'a dyn Material,
+}
+
+/// Straight line object
+pub struct Line {
+    /// Material used
+    pub material: Box<dyn Material>,
+    /// Starting x Position
+    pub x0: Sample,
+    /// Starting y Position
+    pub y0: Sample,
+    /// Length in x Axis
+    pub dx: Sample,
+    /// Length in y Axis
+    pub dy: Sample,
+}
+
+/// Curve object, a straight line with a normal which turns along its length.
+///
+/// The normal at the start is at angle `a0` and turns through `da` degrees
+/// by the end, so the line shades as if it were curved.
+pub struct Curve {
+    /// Material used
+    pub material: Box<dyn Material>,
+    /// Starting x Position
+    pub x0: Sample,
+    /// Starting y Position
+    pub y0: Sample,
+    /// Starting angle of the normal, in degrees
+    pub a0: Sample,
+    /// Length in x Axis
+    pub dx: Sample,
+    /// Length in y Axis
+    pub dy: Sample,
+    /// Angle the normal turns through by the end, in degrees
+    pub da: Sample,
+}
+
+/// Closed shape object, the edge of a `Region`.
+///
+/// Regions combined with `union_with()`, `intersect_with()` and `subtract()`
+/// are only hit where a ray crosses the edge of the combined shape. Normals
+/// always point out of the region, so refractive materials can tell if a ray
+/// is entering or leaving. `alpha` is the direction the normal faces, from
+/// 0.0 pointing along -x, turning towards -y, round to 1.0.
+///
+/// In an `AreaLight` the whole edge of the combined shape glows.
+///
+/// # Example
+/// A biconvex lens, the overlap of two circles:
+/// ```
+/// use rustic_zen::prelude::{HQZLegacy, Point, Region, Solid};
+///
+/// let lens = Solid {
+///     region: Region::Circle { center: Point { x: 480.0, y: 500.0 }, radius: 100.0 }
+///         .intersect_with(Region::Circle { center: Point { x: 520.0, y: 500.0 }, radius: 100.0 }),
+///     material: Box::new(HQZLegacy::new(0.0, 0.1, 0.9)),
+/// };
+/// ```
+pub struct Solid {
+    /// Material used
+    pub material: Box<dyn Material>,
+    /// Area filled by the object
+    pub region: Region,
+}
+
+/// Returns a rectangle enclosing every sampled position of a line segment.
+fn segment_bounds(x0: &Sample, y0: &Sample, dx: &Sample, dy: &Sample) -> Rect {
+    let mut x_s = [
+        x0.bounds().0,
+        x0.bounds().1,
+        x0.bounds().0 + dx.bounds().0,
+        x0.bounds().0 + dx.bounds().1,
+        x0.bounds().1 + dx.bounds().0,
+        x0.bounds().1 + dx.bounds().1,
+    ];
+    x_s.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let mut y_s = [
+        y0.bounds().0,
+        y0.bounds().1,
+        y0.bounds().0 + dy.bounds().0,
+        y0.bounds().0 + dy.bounds().1,
+        y0.bounds().1 + dy.bounds().0,
+        y0.bounds().1 + dy.bounds().1,
+    ];
+    y_s.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let p0 = Point {
+        x: x_s[0],
+        y: y_s[0],
+    };
+
+    let p1 = Point {
+        x: x_s[x_s.len() - 1],
+        y: y_s[y_s.len() - 1],
+    };
+
+    Rect::from_points(&p0, &p1)
+}
+
+/// Samples a line segment at `time`, returning its start and its length in each axis.
+#[inline(always)]
+fn sample_segment(
+    x0: &Sample,
+    y0: &Sample,
+    dx: &Sample,
+    dy: &Sample,
+    time: f64,
+    rng: &mut Pcg64Fast,
+) -> (Point, Point) {
+    (
+        Point {
+            x: x0.val_at(time, rng),
+            y: y0.val_at(time, rng),
+        },
+        Point {
+            x: dx.val_at(time, rng),
+            y: dy.val_at(time, rng),
+        },
+    )
+}
+
+/// Intersects a ray with the segment from `s1` along `sd`, returning
+/// the hit point and how far along the segment it is.
+#[inline(always)]
+fn segment_hit(origin: &Point, dir: &Vector, s1: Point, sd: Point) -> Option<(Point, f64)> {
+    let mat_a = Matrix {
+        a1: sd.x, b1: -dir.x,
+        a2: sd.y, b2: -dir.y,
+    };
+
+    let omega = *origin - s1;
+
+    let result = match mat_a.inverse() {
+        Some(m) => m * omega,
+        None => {
+            return None; // Probably cos rays are parallel
+        }
+    };
+    if (result.x >= 0.0) && (result.x <= 1.0) && (result.y > 0.0) {
+    } else {
+        return None;
+    };
+
+    let alpha = result.x;
+    let distance = result.y;
+
+    let hit = Point {
+        x: origin.x + distance * dir.x,
+        y: origin.y + distance * dir.y,
+    };
+    Some((hit, alpha))
+}
+
+impl Object for Line {
     #[inline(always)]
-    pub fn get_hit(
+    fn get_hit(&self, origin: &Point, dir: &Vector, time: f64, rng: &mut Pcg64Fast) -> Option<Hit<'_>> {
+        let (s1, sd) = sample_segment(&self.x0, &self.y0, &self.dx, &self.dy, time, rng);
+        let (point, alpha) = segment_hit(origin, dir, s1, sd)?;
+        Some(Hit {
+            point,
+            normal: Vector { x: -sd.y, y: sd.x },
+            alpha,
+            material: self.material.as_ref(),
+        })
+    }
+
+    fn bounds(&self) -> Rect {
+        segment_bounds(&self.x0, &self.y0, &self.dx, &self.dy)
+    }
+
+    fn surface_at(&self, alpha: f64, time: f64, rng: &mut Pcg64Fast) -> Option<Hit<'_>> {
+        let (s1, sd) = sample_segment(&self.x0, &self.y0, &self.dx, &self.dy, time, rng);
+        Some(Hit {
+            point: Point {
+                x: s1.x + alpha * sd.x,
+                y: s1.y + alpha * sd.y,
+            },
+            normal: Vector { x: -sd.y, y: sd.x },
+            alpha,
+            material: self.material.as_ref(),
+        })
+    }
+
+    fn length(&self, time: f64) -> f64 {
+        f64::hypot(self.dx.expected_at(time), self.dy.expected_at(time))
+    }
+}
+
+impl Curve {
+    /// Normal `alpha` of the way along the curve.
+    fn normal_at(&self, alpha: f64, time: f64, rng: &mut Pcg64Fast) -> Vector {
+        let deg = self.a0.val_at(time, rng) + alpha * self.da.val_at(time, rng);
+        let rad = deg * (PI / 180.0);
+        Vector {
+            x: f64::cos(rad),
+            y: f64::sin(rad),
+        }
+    }
+}
+
+impl Object for Curve {
+    #[inline(always)]
+    fn get_hit(&self, origin: &Point, dir: &Vector, time: f64, rng: &mut Pcg64Fast) -> Option<Hit<'_>> {
+        let (s1, sd) = sample_segment(&self.x0, &self.y0, &self.dx, &self.dy, time, rng);
+        let (point, alpha) = segment_hit(origin, dir, s1, sd)?;
+        Some(Hit {
+            point,
+            normal: self.normal_at(alpha, time, rng),
+            alpha,
+            material: self.material.as_ref(),
+        })
+    }
+
+    fn bounds(&self) -> Rect {
+        segment_bounds(&self.x0, &self.y0, &self.dx, &self.dy)
+    }
+
+    fn surface_at(&self, alpha: f64, time: f64, rng: &mut Pcg64Fast) -> Option<Hit<'_>> {
+        let (s1, sd) = sample_segment(&self.x0, &self.y0, &self.dx, &self.dy, time, rng);
+        Some(Hit {
+            point: Point {
+                x: s1.x + alpha * sd.x,
+                y: s1.y + alpha * sd.y,
+            },
+            normal: self.normal_at(alpha, time, rng),
+            alpha,
+            material: self.material.as_ref(),
+        })
+    }
+
+    fn length(&self, time: f64) -> f64 {
+        f64::hypot(self.dx.expected_at(time), self.dy.expected_at(time))
+    }
+}
+
+impl Object for Solid {
+    fn get_hit(&self, origin: &Point, dir: &Vector, time: f64, rng: &mut Pcg64Fast) -> Option<Hit<'_>> {
+        self.get_hit_after(origin, dir, time, rng, SELF_HIT_DISTANCE / dir.magnitude())
+    }
+
+    fn get_hit_after(
         &self,
         origin: &Point,
         dir: &Vector,
-        rng: &mut Pcg64Fast,
-    ) -> Option<(Point, Vector, f64)> {
-        // Get s1 and sD from samples
-        let (s1, sd) = match self {
-            Object::Curve { x0, y0, dx, dy, .. } => (
-                Point {
-                    x: x0.val(rng),
-                    y: y0.val(rng),
-                },
-                Point {
-                    x: dx.val(rng),
-                    y: dy.val(rng),
-                },
-            ),
-
-            Object::Line { x0, y0, dx, dy, .. } => (
-                Point {
-                    x: x0.val(rng),
-                    y: y0.val(rng),
-                },
-                Point {
-                    x: dx.val(rng),
-                    y: dy.val(rng),
+        _time: f64,
+        _rng: &mut Pcg64Fast,
+        min_t: f64,
+    ) -> Option<Hit<'_>> {
+        let (mut inside, crossings) = self.region.crossings(origin, dir);
+        // Skip the edge a ray is leaving, so it can reach the far side.
+        for (t, normal) in crossings {
+            if t < min_t {
+                inside = !inside;
+                continue;
+            }
+            // Rays leaving the region travel with the outward normal, entering against it.
+            let normal = if (normal.dot(dir) > 0.0) == inside {
+                normal
+            } else {
+                Vector {
+                    x: -normal.x,
+                    y: -normal.y,
+                }
+            };
+            return Some(Hit {
+                point: Point {
+                    x: origin.x + t * dir.x,
+                    y: origin.y + t * dir.y,
                 },
-            ),
-        };
-
-        let mat_a = Matrix {
-            a1: sd.x, b1: -dir.x,
-            a2: sd.y, b2: -dir.y,
-        };
+                alpha: normal.y.atan2(normal.x) / (2.0 * PI) + 0.5,
+                normal,
+                material: self.material.as_ref(),
+            });
+        }
+        None
+    }
 
-        let omega = origin.clone() - s1;
-        
-        let result = match mat_a.inverse() {
-            Some(m) => m * omega,
-            None => {
-                return None; // Probably cos rays are parallel
-            }
-        };
-        if (result.x >= 0.0) && (result.x <= 1.0) && (result.y > 0.0) {
-        } else {
-            return None;
-        };
+    fn bounds(&self) -> Rect {
+        self.region.bounds()
+    }
 
-        let alpha = result.x;
-        let distance = result.y;
+    fn surface_at(&self, alpha: f64, _time: f64, _rng: &mut Pcg64Fast) -> Option<Hit<'_>> {
+        let (point, normal) = self.region.edge_at(alpha)?;
+        Some(Hit {
+            point,
+            alpha: normal.y.atan2(normal.x) / (2.0 * PI) + 0.5,
+            normal,
+            material: self.material.as_ref(),
+        })
+    }
 
-        let (hit, norm) = match self {
-            Object::Line { .. } => (
-                Point {
-                    x: origin.x + distance * dir.x,
-                    y: origin.y + distance * dir.y,
-                },
-                Vector { x: -sd.y, y: sd.x },
-            ),
-            Object::Curve { a0, da, .. } => {
-                let deg = a0.val(rng) + alpha as f64 * da.val(rng);
-                let rad = deg * (PI / 180.0);
-                (
-                    Point {
-                        x: origin.x + distance * dir.x,
-                        y: origin.y + distance * dir.y,
-                    },
-                    Vector {
-                        x: f64::cos(rad),
-                        y: f64::sin(rad),
-                    },
-                )
-            }
-        };
+    fn length(&self, _time: f64) -> f64 {
+        self.region.edge_length()
+    }
+}
 
-        return Some((hit, norm, alpha));
+/// The point `alpha` of the way along every object's surface in turn, for
+/// containers of objects. Each object gets a share of `alpha` in proportion
+/// to its length.
+pub(crate) fn surface_of<'a>(
+    objects: &'a [Box<dyn Object>],
+    alpha: f64,
+    time: f64,
+    rng: &mut Pcg64Fast,
+) -> Option<Hit<'a>> {
+    let lengths: Vec<f64> = objects.iter().map(|o| o.length(time)).collect();
+    let total: f64 = lengths.iter().sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let mut along = alpha.clamp(0.0, 1.0) * total;
+    for (o, length) in objects.iter().zip(lengths) {
+        if length <= 0.0 {
+            continue;
+        }
+        if along <= length {
+            return o.surface_at(along / length, time, rng);
+        }
+        along -= length;
     }
+    None
+}
+
+/// Total length of the objects in a container.
+pub(crate) fn length_of(objects: &[Box<dyn Object>], time: f64) -> f64 {
+    objects.iter().map(|o| o.length(time)).sum()
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Object;
-    use geom::{Point, Vector};
+    use super::{Curve, Hit, Line, Object, Solid};
+    use geom::{Point, Rect, Vector};
     use material::HQZLegacy;
+    use region::Region;
     use sampler::Sample;
     use rand::prelude::*;
     use pcg_rand::Pcg64Fast;
@@ -211,7 +435,7 @@ mod tests {
 
         let m = Box::new(HQZLegacy::new(0.3, 0.3, 0.3));
 
-        let obj = Object::Line {
+        let obj = Line {
             x0: Sample::Constant(0.0),
             y0: Sample::Constant(0.0),
             dx: Sample::Constant(10.0),
@@ -222,9 +446,10 @@ mod tests {
         let origin = Point { x: 10.0, y: 0.0 };
         let dir = Vector { x: -1.0, y: 1.0 };
 
-        let a = obj.get_hit(&origin, &dir, &mut rng);
+        let a = obj.get_hit(&origin, &dir, 0.0, &mut rng);
 
-        let (a, b, _) = a.expect("A was not meant to be `None`");
+        let hit = a.expect("A was not meant to be `None`");
+        let (a, b) = (hit.point, hit.normal);
 
         //assert hit is at (5,5)
         assert_eq!(a.x, 5.0);
@@ -245,7 +470,7 @@ mod tests {
 
         let m = Box::new(HQZLegacy::new(0.3, 0.3, 0.3));
 
-        let obj = Object::Line {
+        let obj = Line {
             x0: Sample::Constant(0.0),
             y0: Sample::Constant(0.0),
             dx: Sample::Constant(10.0),
@@ -256,7 +481,7 @@ mod tests {
         let origin = Point { x: 30.0, y: 0.0 };
         let dir = Vector { x: -1.0, y: 1.0 };
 
-        let a = obj.get_hit(&origin, &dir, &mut rng);
+        let a = obj.get_hit(&origin, &dir, 0.0, &mut rng);
 
         assert!(a.is_none());
     }
@@ -270,7 +495,7 @@ mod tests {
 
         let m = Box::new(HQZLegacy::new(0.3, 0.3, 0.3));
 
-        let obj = Object::Line {
+        let obj = Line {
             x0: Sample::Constant(0.0),
             y0: Sample::Constant(0.0),
             dx: Sample::Constant(10.0),
@@ -281,8 +506,149 @@ mod tests {
         let origin = Point { x: 10.0, y: 0.0 };
         let dir = Vector { x: 1.0, y: 1.0 };
 
-        let a = obj.get_hit(&origin, &dir, &mut rng);
+        let a = obj.get_hit(&origin, &dir, 0.0, &mut rng);
 
         assert!(a.is_none());
     }
+
+    #[test]
+    /// Bounds should enclose every sampled position of the line.
+    fn line_bounds() {
+        let m = Box::new(HQZLegacy::new(0.3, 0.3, 0.3));
+
+        let obj = Line {
+            x0: Sample::Range(10.0, 0.0),
+            y0: Sample::TruncatedNormal(5.0, 1.0, 6.0, 4.0),
+            dx: Sample::discrete(&[(-5.0, 1.0), (5.0, 1.0)]),
+            dy: Sample::Constant(10.0),
+            material: m,
+        };
+
+        let b = obj.bounds();
+        assert_eq!(b.left(), -5.0);
+        assert_eq!(b.right(), 15.0);
+        assert_eq!(b.top(), 4.0);
+        assert_eq!(b.bottom(), 16.0);
+    }
+
+    #[test]
+    /// Curves are straight, with the normal swept between two angles.
+    fn curve_normal() {
+        let mut rng = Pcg64Fast::from_entropy();
+
+        let obj = Curve {
+            x0: Sample::Constant(0.0),
+            y0: Sample::Constant(0.0),
+            a0: Sample::Constant(0.0),
+            dx: Sample::Constant(0.0),
+            dy: Sample::Constant(10.0),
+            da: Sample::Constant(90.0),
+            material: Box::new(HQZLegacy::default()),
+        };
+
+        let origin = Point { x: 10.0, y: 5.0 };
+        let dir = Vector { x: -1.0, y: 0.0 };
+        let hit = obj.get_hit(&origin, &dir, 0.0, &mut rng).expect("Ray should hit the curve");
+        assert_eq!(hit.point, Point { x: 0.0, y: 5.0 });
+        assert_eq!(hit.alpha, 0.5);
+        let angle = hit.normal.y.atan2(hit.normal.x).to_degrees();
+        assert!((angle - 45.0).abs() < 1e-9);
+
+        assert_eq!(obj.bounds(), Rect::from_points(&Point { x: 0.0, y: 0.0 }, &Point { x: 0.0, y: 10.0 }));
+    }
+
+    #[test]
+    /// User defined geometry works wherever the built in objects do.
+    fn custom_object() {
+        struct Wall(HQZLegacy);
+
+        impl Object for Wall {
+            fn get_hit(&self, origin: &Point, dir: &Vector, _time: f64, _rng: &mut Pcg64Fast) -> Option<Hit<'_>> {
+                // The vertical line x = 0
+                let distance = -origin.x / dir.x;
+                if distance <= 0.0 {
+                    return None;
+                }
+                Some(Hit {
+                    point: Point { x: 0.0, y: origin.y + distance * dir.y },
+                    normal: Vector { x: 1.0, y: 0.0 },
+                    alpha: 0.5,
+                    material: &self.0,
+                })
+            }
+
+            fn bounds(&self) -> Rect {
+                Rect::from_points(&Point { x: 0.0, y: -1e9 }, &Point { x: 0.0, y: 1e9 })
+            }
+        }
+
+        let mut rng = Pcg64Fast::from_entropy();
+        let wall: Box<dyn Object> = Box::new(Wall(HQZLegacy::default()));
+        let hit = wall.get_hit(&Point { x: 10.0, y: 0.0 }, &Vector { x: -1.0, y: 1.0 }, 0.0, &mut rng);
+        assert_eq!(hit.expect("Ray should hit the wall").point, Point { x: 0.0, y: 10.0 });
+        assert!(wall.surface_at(0.5, 0.0, &mut rng).is_none());
+        assert_eq!(wall.length(0.0), 0.0);
+    }
+
+    #[test]
+    /// Rays see both edges of a combined shape, with normals pointing out of it.
+    fn solid_lens_normals() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let lens = Solid {
+            region: Region::Circle {
+                center: Point { x: -20.0, y: 0.0 },
+                radius: 30.0,
+            }
+            .intersect_with(Region::Circle {
+                center: Point { x: 20.0, y: 0.0 },
+                radius: 30.0,
+            }),
+            material: Box::new(HQZLegacy::default()),
+        };
+        let dir = Vector { x: 1.0, y: 0.0 };
+
+        let enter = lens
+            .get_hit(&Point { x: -60.0, y: 0.0 }, &dir, 0.0, &mut rng)
+            .expect("Ray should enter the lens");
+        assert!(enter.point.close_to(&Point { x: -10.0, y: 0.0 }, 1e-9));
+        assert!(enter.normal.x < 0.0 && enter.normal.y.abs() < 1e-9);
+
+        let leave = lens
+            .get_hit(&enter.point, &dir, 0.0, &mut rng)
+            .expect("Ray should leave the lens");
+        assert!(leave.point.close_to(&Point { x: 10.0, y: 0.0 }, 1e-9));
+        assert!(leave.normal.x > 0.0 && leave.normal.y.abs() < 1e-9);
+        assert!((leave.alpha - 0.5).abs() < 1e-9);
+
+        assert!(lens.get_hit(&leave.point, &dir, 0.0, &mut rng).is_none());
+        let b = lens.bounds();
+        assert_eq!((b.left(), b.right(), b.top(), b.bottom()), (-10.0, 10.0, -30.0, 30.0));
+    }
+
+    #[test]
+    /// The edge of a hole points into the hole, out of the solid.
+    fn solid_difference_normals() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let frame = Solid {
+            region: Region::Polygon(vec![
+                Point { x: -10.0, y: -10.0 },
+                Point { x: 10.0, y: -10.0 },
+                Point { x: 10.0, y: 10.0 },
+                Point { x: -10.0, y: 10.0 },
+            ])
+            .subtract(Region::Circle {
+                center: Point { x: 0.0, y: 0.0 },
+                radius: 5.0,
+            }),
+            material: Box::new(HQZLegacy::default()),
+        };
+        let dir = Vector { x: 1.0, y: 0.0 };
+        let mut origin = Point { x: -20.0, y: 0.0 };
+        let mut hits = vec![];
+        while let Some(hit) = frame.get_hit(&origin, &dir, 0.0, &mut rng) {
+            hits.push((hit.point.x, hit.normal.x.signum()));
+            origin = hit.point;
+        }
+        assert_eq!(hits, vec![(-10.0, -1.0), (-5.0, 1.0), (5.0, -1.0), (10.0, 1.0)]);
+    }
 }
@@ -0,0 +1,439 @@
+//! Constructors for common optical components.
+//!
+//! Each component is placed by the `center` it's built around and an `angle`,
+//! in degrees, turning it from its default orientation. Components come with
+//! sensible default materials: lenses and prisms are crown glass, mirrors are
+//! perfect reflectors and barriers absorb everything. Swap the `material` of
+//! a `Solid`, or place a `Shape` with an `Instance` to override its material.
+//!
+//! # Example
+//! A lens focusing a beam onto a parabolic mirror:
+//! ```
+//! use rustic_zen::optics;
+//! use rustic_zen::prelude::*;
+//!
+//! let lens = optics::biconvex_lens(Point { x: 300.0, y: 500.0 }, 0.0, 400.0, 40.0);
+//! let mirror = optics::parabolic_mirror(Point { x: 700.0, y: 500.0 }, 180.0, 150.0, 300.0);
+//! let scene = Scene::new(1000, 1000).with_object(lens).with_object(mirror);
+//! ```
+
+use geom::{Point, Transform, Vector};
+use instance::Shape;
+use material::{Dielectric, HQZLegacy};
+use object::{Curve, Line, Solid};
+use region::Region;
+use sampler::Sample;
+
+/// Number of curved segments each mirror is made of.
+const MIRROR_SEGMENTS: usize = 32;
+
+/// Moves local coordinates, where components are built, into the scene.
+fn placement(center: &Point, angle: f64) -> Transform {
+    Transform::rotation(angle).then(&Transform::translation(center.x, center.y))
+}
+
+/// Circle centred `x` along the local x axis.
+fn circle(place: &Transform, x: f64, radius: f64) -> Region {
+    Region::Circle {
+        center: place.apply_point(&Point { x, y: 0.0 }),
+        radius,
+    }
+}
+
+/// Polygon from corners in local coordinates.
+fn polygon(place: &Transform, corners: &[(f64, f64)]) -> Region {
+    Region::Polygon(corners.iter().map(|&(x, y)| place.apply_point(&Point { x, y })).collect())
+}
+
+/// Rectangle spanning `left` to `right` along the local x axis, `height` tall.
+fn slab(place: &Transform, left: f64, right: f64, height: f64) -> Region {
+    let h = height / 2.0;
+    polygon(place, &[(left, -h), (right, -h), (right, h), (left, h)])
+}
+
+/// How far a circle of `radius` bows across a chord of length `height`.
+fn sag(radius: f64, height: f64) -> f64 {
+    if height > 2.0 * radius {
+        panic!("Lens height must not be more than twice its radius");
+    }
+    radius - (radius * radius - height * height / 4.0).sqrt()
+}
+
+fn glass(region: Region) -> Solid {
+    Solid {
+        region,
+        material: Box::new(Dielectric::glass()),
+    }
+}
+
+/// Lens with two equally convex faces of `radius`, `thickness` across the middle.
+///
+/// The optical axis runs along `angle`, and the lens is as tall as the two faces
+/// allow, closing to a sharp edge.
+pub fn biconvex_lens(center: Point, angle: f64, radius: f64, thickness: f64) -> Solid {
+    if thickness <= 0.0 || thickness > 2.0 * radius {
+        panic!("Lens thickness must be positive and no more than twice its radius");
+    }
+    let place = placement(&center, angle);
+    let offset = radius - thickness / 2.0;
+    glass(circle(&place, -offset, radius).intersect_with(circle(&place, offset, radius)))
+}
+
+/// Lens with two equally concave faces of `radius`, `thickness` across the
+/// middle and `height` across the faces, along the optical axis `angle`.
+pub fn biconcave_lens(center: Point, angle: f64, radius: f64, thickness: f64, height: f64) -> Solid {
+    if thickness <= 0.0 {
+        panic!("Lens thickness must be positive");
+    }
+    let place = placement(&center, angle);
+    let edge = thickness / 2.0 + sag(radius, height);
+    let offset = thickness / 2.0 + radius;
+    glass(
+        slab(&place, -edge, edge, height)
+            .subtract(circle(&place, -offset, radius))
+            .subtract(circle(&place, offset, radius)),
+    )
+}
+
+/// Lens with one flat face and one convex face of `radius`, `thickness` across the middle.
+///
+/// The flat face is on the side `angle` points away from, and the lens is as
+/// tall as the curved face allows.
+pub fn plano_convex_lens(center: Point, angle: f64, radius: f64, thickness: f64) -> Solid {
+    if thickness <= 0.0 || thickness > radius {
+        panic!("Lens thickness must be positive and no more than its radius");
+    }
+    let place = placement(&center, angle);
+    let h = thickness / 2.0;
+    let height = 2.0 * (radius * radius - (radius - thickness) * (radius - thickness)).sqrt();
+    glass(slab(&place, -h, h, height).intersect_with(circle(&place, h - radius, radius)))
+}
+
+/// Lens with one flat face and one concave face of `radius`, `thickness`
+/// across the middle and `height` across the faces.
+///
+/// The flat face is on the side `angle` points away from.
+pub fn plano_concave_lens(center: Point, angle: f64, radius: f64, thickness: f64, height: f64) -> Solid {
+    if thickness <= 0.0 {
+        panic!("Lens thickness must be positive");
+    }
+    let place = placement(&center, angle);
+    let h = thickness / 2.0;
+    glass(
+        slab(&place, -h, h + sag(radius, height), height)
+            .subtract(circle(&place, h + radius, radius)),
+    )
+}
+
+/// Triangular prism with an apex of `apex` degrees between two faces `side` long.
+///
+/// The apex points along `angle`, and `center` is the middle of the triangle.
+pub fn prism(center: Point, angle: f64, apex: f64, side: f64) -> Solid {
+    if apex <= 0.0 || apex >= 180.0 {
+        panic!("Prism apex must be between 0 and 180 degrees");
+    }
+    let place = placement(&center, angle);
+    let half = apex.to_radians() / 2.0;
+    let (length, width) = (side * half.cos(), side * half.sin());
+    // Centroid is a third of the way from the base to the apex.
+    let base = -length / 3.0;
+    let tip = base + length;
+    glass(polygon(&place, &[(tip, 0.0), (base, -width), (base, width)]))
+}
+
+/// Mirror built from curved segments along the local points `at(t)` with
+/// normals `normal(t)`, for `t` from 0.0 to 1.0.
+fn mirror<P, N>(place: &Transform, angle: f64, at: P, normal: N) -> Shape
+where
+    P: Fn(f64) -> Point,
+    N: Fn(f64) -> Vector,
+{
+    let normal_angle = |t: f64| {
+        let n = normal(t);
+        n.y.atan2(n.x).to_degrees() + angle
+    };
+    let mut shape = Shape::new();
+    for i in 0..MIRROR_SEGMENTS {
+        let (t0, t1) = (i as f64 / MIRROR_SEGMENTS as f64, (i + 1) as f64 / MIRROR_SEGMENTS as f64);
+        let (p0, p1) = (place.apply_point(&at(t0)), place.apply_point(&at(t1)));
+        let a0 = normal_angle(t0);
+        // Turn the short way round, not through the +-180 degree seam.
+        let da = (normal_angle(t1) - a0 + 540.0) % 360.0 - 180.0;
+        shape = shape.with_object(Curve {
+            x0: Sample::Constant(p0.x),
+            y0: Sample::Constant(p0.y),
+            a0: Sample::Constant(a0),
+            dx: Sample::Constant(p1.x - p0.x),
+            dy: Sample::Constant(p1.y - p0.y),
+            da: Sample::Constant(da),
+            material: Box::new(HQZLegacy::new(0.0, 1.0, 0.0)),
+        });
+    }
+    shape
+}
+
+/// Parabolic mirror bringing rays parallel to its axis to a point `focal_length`
+/// in front of it, `aperture` wide.
+///
+/// `focus` is the point rays are brought to, and the mirror faces along `angle`.
+pub fn parabolic_mirror(focus: Point, angle: f64, focal_length: f64, aperture: f64) -> Shape {
+    if focal_length <= 0.0 {
+        panic!("Mirror focal length must be positive");
+    }
+    let place = placement(&focus, angle);
+    let f = focal_length;
+    let y = |t: f64| aperture * (t - 0.5);
+    mirror(
+        &place,
+        angle,
+        |t| Point {
+            x: y(t) * y(t) / (4.0 * f) - f,
+            y: y(t),
+        },
+        |t| Vector {
+            x: 1.0,
+            y: -y(t) / (2.0 * f),
+        },
+    )
+}
+
+/// Elliptical mirror bringing rays from one focus to the other.
+///
+/// The foci are `focal_distance` either side of `center` along `angle`, and
+/// the mirror is the arc of the ellipse, `semi_major` across, between the
+/// parametric angles `start` and `end` in degrees. 0.0 is the end of the
+/// ellipse beyond the focus on the `angle` side.
+pub fn elliptical_mirror(
+    center: Point,
+    angle: f64,
+    semi_major: f64,
+    focal_distance: f64,
+    start: f64,
+    end: f64,
+) -> Shape {
+    if focal_distance < 0.0 || focal_distance >= semi_major {
+        panic!("Mirror focal distance must be between 0.0 and its semi-major axis");
+    }
+    let place = placement(&center, angle);
+    let (a, b) = (semi_major, (semi_major * semi_major - focal_distance * focal_distance).sqrt());
+    let theta = |t: f64| (start + t * (end - start)).to_radians();
+    mirror(
+        &place,
+        angle,
+        |t| Point {
+            x: a * theta(t).cos(),
+            y: b * theta(t).sin(),
+        },
+        |t| Vector {
+            x: -theta(t).cos() / a,
+            y: -theta(t).sin() / b,
+        },
+    )
+}
+
+/// Flat plate reflecting `reflectance` of the rays hitting it and letting the rest through.
+///
+/// The plate is `length` long, running along `angle`.
+pub fn beam_splitter(center: Point, angle: f64, length: f64, reflectance: f64) -> Line {
+    if !(0.0..=1.0).contains(&reflectance) {
+        panic!("Beam splitter reflectance must be between 0.0 and 1.0");
+    }
+    let (sin, cos) = angle.to_radians().sin_cos();
+    Line {
+        x0: Sample::Constant(center.x - cos * length / 2.0),
+        y0: Sample::Constant(center.y - sin * length / 2.0),
+        dx: Sample::Constant(cos * length),
+        dy: Sample::Constant(sin * length),
+        material: Box::new(HQZLegacy::new(0.0, reflectance, 1.0 - reflectance)),
+    }
+}
+
+/// Absorbing barrier `length` long along `angle`, with `count` openings `width`
+/// wide, their centres `spacing` apart, such as a double slit.
+pub fn slits(center: Point, angle: f64, length: f64, width: f64, spacing: f64, count: usize) -> Shape {
+    let span = spacing * (count.max(1) - 1) as f64 + width;
+    if width <= 0.0 || (count > 1 && spacing <= width) || span >= length {
+        panic!("Slits must be narrower than their spacing and fit within the barrier");
+    }
+    let place = placement(&center, angle);
+    let barrier = |from: f64, to: f64| {
+        let p0 = place.apply_point(&Point { x: from, y: 0.0 });
+        let p1 = place.apply_point(&Point { x: to, y: 0.0 });
+        Line {
+            x0: Sample::Constant(p0.x),
+            y0: Sample::Constant(p0.y),
+            dx: Sample::Constant(p1.x - p0.x),
+            dy: Sample::Constant(p1.y - p0.y),
+            material: Box::new(HQZLegacy::new(0.0, 0.0, 0.0)),
+        }
+    };
+
+    let mut shape = Shape::new();
+    let mut from = -length / 2.0;
+    for i in 0..count {
+        let opening = -span / 2.0 + i as f64 * spacing;
+        shape = shape.with_object(barrier(from, opening));
+        from = opening + width;
+    }
+    shape.with_object(barrier(from, length / 2.0))
+}
+
+/// Absorbing barrier `length` long along `angle`, with a single opening `width` wide in the middle.
+pub fn aperture(center: Point, angle: f64, length: f64, width: f64) -> Shape {
+    slits(center, angle, length, width, 0.0, 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object::Object;
+    use pcg_rand::Pcg64Fast;
+    use rand::prelude::*;
+
+    /// Traces a ray through `object` until it misses, returning where it ends up
+    /// heading and how many surfaces it hit, or `None` if absorbed.
+    fn trace(object: &dyn Object, mut origin: Point, mut dir: Vector, wavelength: f64, rng: &mut Pcg64Fast) -> Option<(Point, Vector, usize)> {
+        for hits in 0..100 {
+            let hit = match object.get_hit(&origin, &dir, 0.0, rng) {
+                Some(hit) => hit,
+                None => return Some((origin, dir, hits)),
+            };
+            let out = hit.material.outcome(&dir, &hit.normal, wavelength, hit.alpha, rng)?;
+            origin = hit.point;
+            dir = out.direction;
+        }
+        panic!("Ray trapped in component");
+    }
+
+    /// Traces rays until one passes straight through, in one face and out another.
+    fn refract(object: &dyn Object, origin: Point, dir: Vector, wavelength: f64, rng: &mut Pcg64Fast) -> (Point, Vector) {
+        loop {
+            if let Some((p, d, 2)) = trace(object, origin, dir, wavelength, rng) {
+                if d.x > 0.0 {
+                    return (p, d);
+                }
+            }
+        }
+    }
+
+    /// Where a ray crosses the x axis, from its last point and direction.
+    fn axis_crossing(p: Point, d: Vector) -> f64 {
+        p.x - p.y * d.x / d.y
+    }
+
+    #[test]
+    fn biconvex_lens_focuses() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let lens = biconvex_lens(Point { x: 0.0, y: 0.0 }, 0.0, 200.0, 20.0);
+        // Lensmaker's equation for a thick lens, measured from its back face.
+        let (n, r, d) = (Dielectric::glass().index_at(550.0), 200.0, 20.0);
+        let focal_length = 1.0 / ((n - 1.0) * (2.0 / r - (n - 1.0) * d / (n * r * r)));
+        let back_focus = d / 2.0 + focal_length * (1.0 - (n - 1.0) * d / (n * r));
+
+        for _ in 0..20 {
+            let (p, d) = refract(&lens, Point { x: -100.0, y: 5.0 }, Vector { x: 1.0, y: 0.0 }, 550.0, &mut rng);
+            let x = axis_crossing(p, d);
+            assert!((x - back_focus).abs() < 1.0, "focus at {} expected {}", x, back_focus);
+        }
+    }
+
+    #[test]
+    fn concave_lenses_diverge() {
+        let mut rng = Pcg64Fast::from_entropy();
+        for lens in [
+            biconcave_lens(Point { x: 0.0, y: 0.0 }, 0.0, 100.0, 5.0, 60.0),
+            plano_concave_lens(Point { x: 0.0, y: 0.0 }, 0.0, 100.0, 5.0, 60.0),
+        ]
+        .iter()
+        {
+            let (_, d) = refract(lens, Point { x: -100.0, y: 10.0 }, Vector { x: 1.0, y: 0.0 }, 550.0, &mut rng);
+            assert!(d.y > 0.0);
+        }
+        let b = plano_convex_lens(Point { x: 0.0, y: 0.0 }, 90.0, 100.0, 10.0).bounds();
+        assert!((b.top() + 5.0).abs() < 1e-9 && (b.bottom() - 5.0).abs() < 1e-9);
+        assert!((b.right() - b.left() - 2.0 * (100.0f64 * 100.0 - 90.0 * 90.0).sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn prism_disperses() {
+        let mut rng = Pcg64Fast::from_entropy();
+        // Apex pointing up, light coming in from the left close to minimum deviation.
+        let p = prism(Point { x: 0.0, y: 0.0 }, -90.0, 60.0, 100.0);
+        let deviation = |wavelength: f64, rng: &mut Pcg64Fast| {
+            let (_, d) = refract(&p, Point { x: -100.0, y: 25.0 }, Vector { x: 1.0, y: -0.35 }, wavelength, rng);
+            d.y.atan2(d.x)
+        };
+        // Bent away from the apex, blue more than red.
+        let (blue, red) = (deviation(450.0, &mut rng), deviation(650.0, &mut rng));
+        assert!(red > 0.2);
+        assert!(blue > red + 0.005);
+    }
+
+    #[test]
+    fn parabolic_mirror_focuses() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let focus = Point { x: 100.0, y: 50.0 };
+        let m = parabolic_mirror(focus, 180.0, 80.0, 200.0);
+        for &y in &[-60.0, -20.0, 10.0, 45.0] {
+            let origin = Point { x: 0.0, y: 50.0 + y };
+            let hit = m
+                .get_hit(&origin, &Vector { x: 1.0, y: 0.0 }, 0.0, &mut rng)
+                .expect("Ray should hit the mirror");
+            let out = hit.material.outcome(&Vector { x: 1.0, y: 0.0 }, &hit.normal, 550.0, hit.alpha, &mut rng).unwrap();
+            // The reflected ray passes through the focus.
+            let to_focus = focus - hit.point;
+            assert!(to_focus.cross(&out.direction).abs() / to_focus.magnitude() < 1e-3);
+            assert!(to_focus.dot(&out.direction) > 0.0);
+        }
+    }
+
+    #[test]
+    fn elliptical_mirror_joins_foci() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let m = elliptical_mirror(Point { x: 0.0, y: 0.0 }, 0.0, 100.0, 60.0, -120.0, 120.0);
+        let (a, b) = (Point { x: -60.0, y: 0.0 }, Point { x: 60.0, y: 0.0 });
+        for &deg in &[-50.0f64, -10.0, 20.0, 60.0] {
+            let dir = Vector { x: deg.to_radians().cos(), y: deg.to_radians().sin() };
+            let hit = m.get_hit(&a, &dir, 0.0, &mut rng).expect("Ray should hit the mirror");
+            let out = hit.material.outcome(&dir, &hit.normal, 550.0, hit.alpha, &mut rng).unwrap();
+            let to_focus = b - hit.point;
+            assert!(to_focus.cross(&out.direction).abs() / to_focus.magnitude() < 1e-2);
+            // Normals face into the ellipse.
+            assert!(hit.normal.dot(&(a - hit.point)) > 0.0);
+        }
+    }
+
+    #[test]
+    fn slits_let_light_through_openings() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let double = slits(Point { x: 0.0, y: 0.0 }, 90.0, 100.0, 4.0, 20.0, 2);
+        let passes = |y: f64, rng: &mut Pcg64Fast| {
+            double.get_hit(&Point { x: -10.0, y }, &Vector { x: 1.0, y: 0.0 }, 0.0, rng).is_none()
+        };
+        assert!(passes(-10.0, &mut rng) && passes(10.0, &mut rng));
+        assert!(!passes(0.0, &mut rng) && !passes(-14.0, &mut rng) && !passes(40.0, &mut rng));
+        assert!(passes(60.0, &mut rng));
+
+        let hole = aperture(Point { x: 0.0, y: 0.0 }, 90.0, 100.0, 10.0);
+        assert!(hole.get_hit(&Point { x: -10.0, y: 4.0 }, &Vector { x: 1.0, y: 0.0 }, 0.0, &mut rng).is_none());
+        assert!(hole.get_hit(&Point { x: -10.0, y: 6.0 }, &Vector { x: 1.0, y: 0.0 }, 0.0, &mut rng).is_some());
+    }
+
+    #[test]
+    fn beam_splitter_splits() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let s = beam_splitter(Point { x: 0.0, y: 0.0 }, 45.0, 20.0, 0.5);
+        let dir = Vector { x: 1.0, y: 0.0 };
+        let mut reflected = 0;
+        for _ in 0..1000 {
+            let hit = s.get_hit(&Point { x: -10.0, y: 0.0 }, &dir, 0.0, &mut rng).unwrap();
+            let out = hit.material.outcome(&dir, &hit.normal, 550.0, hit.alpha, &mut rng).unwrap();
+            if out.direction.x.abs() < 1e-9 {
+                reflected += 1;
+            } else {
+                assert_eq!(out.direction, dir);
+            }
+        }
+        assert!(reflected > 430 && reflected < 570);
+    }
+}
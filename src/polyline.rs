@@ -0,0 +1,71 @@
+//! Piecewise linear curves shared by the materials.
+
+/// Piecewise curve backing `Passband::Tabulated`, `Measured` surfaces,
+/// `ThinFilm` thickness profiles and `Texture`s.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polyline {
+    points: Vec<(f64, f64)>,
+}
+
+impl Polyline {
+    /// Creates a curve through `(x, y)` points, which are sorted by x.
+    ///
+    /// Panics if there are no points or any x is NaN.
+    ///
+    /// # Example
+    /// ```
+    /// use rustic_zen::Polyline;
+    ///
+    /// let curve = Polyline::new(&[(700.0, 0.9), (400.0, 0.1)]);
+    /// assert_eq!(curve.points(), &[(400.0, 0.1), (700.0, 0.9)]);
+    /// ```
+    pub fn new(points: &[(f64, f64)]) -> Self {
+        if points.is_empty() {
+            panic!("A polyline needs at least one point");
+        }
+        let mut points = points.to_vec();
+        points.sort_by(|a, b| a.0.partial_cmp(&b.0).expect("Polyline point is NaN"));
+        Polyline { points }
+    }
+
+    /// Points of the curve, sorted by x.
+    pub fn points(&self) -> &[(f64, f64)] {
+        &self.points
+    }
+
+    /// Value of the last point at or before `x`, the first point's value before it.
+    pub(crate) fn step_at(&self, x: f64) -> f64 {
+        let i = self.points.partition_point(|p| p.0 <= x);
+        self.points[i.max(1) - 1].1
+    }
+
+    /// Linearly interpolated value at `x`, held constant beyond the ends.
+    pub(crate) fn value_at(&self, x: f64) -> f64 {
+        let i = self.points.partition_point(|p| p.0 <= x);
+        if i == 0 {
+            return self.points[0].1;
+        }
+        if i == self.points.len() {
+            return self.points[i - 1].1;
+        }
+        let (a, b) = (self.points[i - 1], self.points[i]);
+        a.1 + (b.1 - a.1) * (x - a.0) / (b.0 - a.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Polyline;
+
+    #[test]
+    fn interpolates_and_steps() {
+        let p = Polyline::new(&[(10.0, 1.0), (0.0, 0.0), (20.0, 0.5)]);
+        assert_eq!(p.value_at(-5.0), 0.0);
+        assert_eq!(p.value_at(5.0), 0.5);
+        assert_eq!(p.value_at(15.0), 0.75);
+        assert_eq!(p.value_at(25.0), 0.5);
+        assert_eq!(p.step_at(-5.0), 0.0);
+        assert_eq!(p.step_at(12.0), 1.0);
+        assert_eq!(p.step_at(20.0), 0.5);
+    }
+}
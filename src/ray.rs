@@ -1,84 +1,73 @@
+use bvh::Bvh;
 use geom::{Point, Rect, Vector, Matrix};
 use image::Image;
-use object::Object;
+use medium::Medium;
+use object::{Hit, Object};
 use pcg_rand::{Pcg64Fast};
-use scene::Light;
-use std::f64::consts::PI;
+use light::Emitter;
+use sequence::DIMENSIONS;
 use rand::prelude::*;
 use pcg_rand::seeds::PcgSeeder;
 
+/// Hits closer than this to the start of a ray are ignored, so rays don't
+/// hit the surface they are bouncing off again.
+pub(crate) const SELF_HIT_DISTANCE: f64 = 3.0;
+
 pub struct Ray {
     origin: Point,
     direction: Vector,
     wavelength: f64,
+    energy: f64,
+    time: f64,
     bounces: u32,
     ray_rng: Pcg64Fast,
 }
 
 impl Ray {
     /**
-     * Creates new ray from light source, sampling the light apropriately.
+     * Creates new ray from light source, sampling the light apropriately at the given time.
+     *
+     * `u` holds the uniform numbers each of the light's samples are drawn from.
      */
-    pub fn new(light: &Light, rng: &mut Pcg64Fast) -> Self {
-        let cart_x = light.x.val(rng);
-        let cart_y = light.y.val(rng);
-        let polar_angle = light.polar_angle.val(rng) * (PI / 180.0);
-        let polar_dist = light.polar_distance.val(rng);
-        let origin = Point {
-            x: cart_x + f64::cos(polar_angle) * polar_dist,
-            y: cart_y + f64::sin(polar_angle) * polar_dist,
-        };
-        let ray_angle = light.ray_angle.val(rng) * (PI / 180.0);
-        // Set Angle
-        let direction = Vector {
-            x: f64::cos(ray_angle),
-            y: f64::sin(ray_angle),
-        };
-        // Set Colour
-        let wavelength = light.wavelength.val(rng);
+    pub fn new(light: &dyn Emitter, time: f64, u: &[f64; DIMENSIONS], rng: &mut Pcg64Fast) -> Self {
+        let e = light.emit(time, u, rng);
         // wrap in an object
         let mut pcg = Pcg64Fast::from_seed(PcgSeeder::seed(rng.gen()));
         // PCG's act weird when you initialise them so we're gonna throw away the first value
         pcg.gen::<f64>();
         pcg.gen::<f64>();
         Ray {
-            origin,
-            direction,
-            wavelength,
+            origin: e.origin,
+            direction: e.direction,
+            wavelength: e.wavelength,
+            energy: e.energy,
+            time,
             bounces: 1000,
             ray_rng: pcg,
         }
     }
 
-    pub fn get_origin(&self) -> &Point {
-        return &self.origin;
+    /// Multiplies the energy the ray is drawn with.
+    pub fn scale_energy(&mut self, factor: f64) {
+        self.energy *= factor;
     }
 
     pub fn collision_list(
         &mut self,
-        obj_list: &Vec<Object>,
+        obj_list: &[Box<dyn Object>],
+        bvh: &Bvh,
+        media: &[Medium],
         viewport: Rect,
         image: &mut Image,
     ) -> Option<Self> {
-        // get closest Collision
-        // Mercifully O(N)
-        let mut c_distance = std::f64::MAX;
-        let mut c_hit: Option<Point> = None;
-        let mut c_res: Option<Self> = None;
-        for i in obj_list.iter() {
-            let result = self.bounce(i);
-            match result {
-                None => {}
-                Some(i) => {
-                    let dist = self.origin.distance(i.get_origin());
-                    if dist < c_distance {
-                        c_distance = dist;
-                        c_hit = Some(i.get_origin().clone());
-                        c_res = Some(i);
-                    }
-                }
-            }
-        }
+        // get closest Collision, only the closest object decides what happens to the ray
+        let min_t = SELF_HIT_DISTANCE / self.direction.magnitude();
+        let closest = bvh.closest_hit(obj_list, &self.origin, &self.direction, self.time, &mut self.ray_rng, min_t);
+        let c_hit = closest.map(|(_, h)| h.point);
+        let c_res = match closest {
+            Some((_, h)) => self.bounce_hit(h),
+            None => None,
+        };
 
         let end = match c_hit {
             None =>  // We hit nothing, we need to test on the viewport!
@@ -89,7 +78,32 @@ impl Ray {
             Some(p) => p, //this is the closest point we hit!
         };
 
-        image.draw_line(self.wavelength, self.origin.x, self.origin.y, end.x, end.y);
+        // Any media along the way may scatter the ray before it gets there
+        let length = self.origin.distance(&end);
+        let direction = self.direction.normalized();
+        let mut scatter: Option<(f64, &Medium)> = None;
+        for m in media.iter() {
+            if let Some(t) = m.free_flight(&self.origin, &direction, length, &mut self.ray_rng) {
+                if scatter.is_none_or(|(closest, _)| t < closest) {
+                    scatter = Some((t, m));
+                }
+            }
+        }
+        if let Some((t, medium)) = scatter {
+            let point = Point {
+                x: self.origin.x + t * direction.x,
+                y: self.origin.y + t * direction.y,
+            };
+            image.draw_line(self.wavelength, self.energy, self.origin.x, self.origin.y, point.x, point.y);
+            if self.bounces <= 1 {
+                return None;
+            }
+            return medium
+                .scatter(&direction, &mut self.ray_rng)
+                .map(|d| self.scattered(point, d));
+        }
+
+        image.draw_line(self.wavelength, self.energy, self.origin.x, self.origin.y, end.x, end.y);
 
         // if we have bounces left Return the result else None.
         if self.bounces > 1 {
@@ -100,39 +114,49 @@ impl Ray {
     }
 
     /**
-     * Returns the resulting ray from colliding with object,
-     * returns none if it does not actually hit the object.
-     * Objects are sampled so two identical rays may not have the same outcome.
+     * Returns the resulting ray from the material at a hit,
+     * returns none if the ray is absorbed.
      */
-    pub fn bounce(&mut self, obj: &Object) -> Option<Self> {
-        // Todo get actual ray start. And do an actual collision test
-        let (hit, normal, alpha) = match obj.get_hit(&self.origin, &self.direction, &mut self.ray_rng) {
-            None => return None,
-            Some((hit, normal, alpha)) => (hit, normal, alpha),
-        };
+    fn bounce_hit(&mut self, h: Hit) -> Option<Self> {
+        let (hit, normal, alpha, mat) = (h.point, h.normal, h.alpha, h.material);
 
-        let dist = self.origin.distance(&hit);
-
-        if dist < 3.0 {
-            return None;
-        }
-
-        let mat = obj.get_material();
         let outcome = mat.outcome(&self.direction, &normal, self.wavelength, alpha, &mut self.ray_rng);
-        let direction = match outcome {
+        let outcome = match outcome {
             Some(o) => o,
             None => {
                 return None;
             }
         };
 
+        let energy = self.energy * outcome.energy;
+        if energy <= 0.0 {
+            return None;
+        }
+
         Option::Some(Ray {
             origin: hit,
+            direction: outcome.direction,
+            wavelength: outcome.wavelength,
+            energy,
+            time: self.time,
+            bounces: self.bounces - 1,
+            ray_rng: Pcg64Fast::from_seed(PcgSeeder::seed(self.ray_rng.gen())),
+        })
+    }
+
+    /**
+     * Continues this ray from a scattering event part way along its path.
+     */
+    fn scattered(&mut self, origin: Point, direction: Vector) -> Self {
+        Ray {
+            origin,
             direction,
             wavelength: self.wavelength,
+            energy: self.energy,
+            time: self.time,
             bounces: self.bounces - 1,
             ray_rng: Pcg64Fast::from_seed(PcgSeeder::seed(self.ray_rng.gen())),
-        })
+        }
     }
 
     fn intersect_edge(&self, s1: Point, sd: Vector) -> Option<f64> {
@@ -250,26 +274,22 @@ impl Ray {
             }
         }
 
-        match max_dist {
-            None => {
-                return None;
-            }
-            Some(d) => {
-                return Some(Point {
-                    x: self.origin.x + d * self.direction.x,
-                    y: self.origin.y + d * self.direction.y,
-                });
-            }
-        }
+        max_dist.map(|d| Point {
+            x: self.origin.x + d * self.direction.x,
+            y: self.origin.y + d * self.direction.y,
+        })
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::Ray;
-    use geom::{Point, Rect};
+    use geom::{Point, Rect, Vector};
+    use material::{Material, Outcome};
+    use object::{Line, Object};
     use sampler::Sample;
-    use scene::Light;
+    use light::Light;
+    use sequence::DIMENSIONS;
     use rand::prelude::*;
     use pcg_rand::Pcg64Fast;
 
@@ -287,7 +307,7 @@ mod test {
             wavelength: Sample::Constant(460.0),
         };
 
-        let r = Ray::new(&l, &mut rng);
+        let r = Ray::new(&l, 0.0, &[0.5; DIMENSIONS], &mut rng);
         assert_eq!(r.origin.x.round(), 101.0);
         assert_eq!(r.origin.y.round(), 100.0);
         assert_eq!(r.direction.x.round(), 1.0);
@@ -312,7 +332,7 @@ mod test {
         };
 
         //Firing a ray in x+, 0 from origin
-        let ray = Ray::new(&x_plus_light, &mut rng);
+        let ray = Ray::new(&x_plus_light, 0.0, &[0.5; DIMENSIONS], &mut rng);
 
         // wall from 1,-10 to 11, +10 should be in the way
         let p1 = Point { x: 1.0, y: -10.0 };
@@ -343,7 +363,7 @@ mod test {
         };
 
         //Firing a ray in 0, +y from origin
-        let ray = Ray::new(&x_plus_light, &mut rng);
+        let ray = Ray::new(&x_plus_light, 0.0, &[0.5; DIMENSIONS], &mut rng);
 
         // wall from 1,-10 to 11, +10 should be in the way
         let p1 = Point { x: -10.0, y: 1.0 };
@@ -375,7 +395,7 @@ mod test {
         };
 
         //Firing a diagonal ray +x, +y from origin
-        let ray = Ray::new(&x_plus_light, &mut rng);
+        let ray = Ray::new(&x_plus_light, 0.0, &[0.5; DIMENSIONS], &mut rng);
 
         // wall from 1,-10 to 11, +10 should be in the way
         let p1 = Point { x: -10.0, y: 1.0 };
@@ -408,7 +428,7 @@ mod test {
         };
 
         //Firing a diagonal ray +x, -y from origin
-        let ray = Ray::new(&x_plus_light, &mut rng);
+        let ray = Ray::new(&x_plus_light, 0.0, &[0.5; DIMENSIONS], &mut rng);
 
         // wall from 1,-10 to 11, +10 should be in the way
         let p1 = Point { x: 0.0, y: 0.0 };
@@ -426,4 +446,177 @@ mod test {
         assert_eq!(result.x.round(), 200.0);
         assert_eq!(result.y.round(), 600.0);
     }
+
+    #[test]
+    fn new_at_time() {
+        use animation::{Interpolation, Keyframe};
+
+        let mut rng = Pcg64Fast::from_entropy();
+
+        let l = Light {
+            power: Sample::Constant(1.0),
+            x: Sample::keyframes(&[
+                Keyframe {
+                    time: 0.0,
+                    value: 0.0,
+                    interpolation: Interpolation::Linear,
+                },
+                Keyframe {
+                    time: 1.0,
+                    value: 100.0,
+                    interpolation: Interpolation::Linear,
+                },
+            ]),
+            y: Sample::Constant(100.0),
+            polar_angle: Sample::Constant(0.0),
+            polar_distance: Sample::Constant(0.0),
+            ray_angle: Sample::Constant(0.0),
+            wavelength: Sample::Constant(460.0),
+        };
+
+        let r = Ray::new(&l, 0.25, &[0.5; DIMENSIONS], &mut rng);
+        assert_eq!(r.origin.x, 25.0);
+        assert_eq!(r.time, 0.25);
+    }
+
+    /// Shifts rays to a longer wavelength and halves their energy.
+    struct Fluorescent;
+
+    impl Material for Fluorescent {
+        fn outcome(
+            &self,
+            direction: &Vector,
+            _normal: &Vector,
+            wavelength: f64,
+            _alpha: f64,
+            _rng: &mut Pcg64Fast,
+        ) -> Option<Outcome> {
+            Some(Outcome {
+                direction: *direction,
+                wavelength: wavelength + 100.0,
+                energy: 0.5,
+            })
+        }
+    }
+
+    #[test]
+    fn bounce_changes_wavelength_and_energy() {
+        let mut rng = Pcg64Fast::from_entropy();
+
+        let l = Light {
+            power: Sample::Constant(1.0),
+            x: Sample::Constant(0.0),
+            y: Sample::Constant(0.0),
+            polar_angle: Sample::Constant(0.0),
+            polar_distance: Sample::Constant(0.0),
+            ray_angle: Sample::Constant(0.0),
+            wavelength: Sample::Constant(460.0),
+        };
+
+        let obj = Line {
+            x0: Sample::Constant(10.0),
+            y0: Sample::Constant(-10.0),
+            dx: Sample::Constant(0.0),
+            dy: Sample::Constant(20.0),
+            material: Box::new(Fluorescent),
+        };
+
+        let bounce = |r: &mut Ray, obj: &Line, rng: &mut Pcg64Fast| {
+            let hit = obj.get_hit(&r.origin, &r.direction, r.time, rng)?;
+            r.bounce_hit(hit)
+        };
+
+        let mut r = Ray::new(&l, 0.0, &[0.5; DIMENSIONS], &mut rng);
+        let mut r = bounce(&mut r, &obj, &mut rng).expect("Ray should hit the line");
+        assert_eq!(r.wavelength, 560.0);
+        assert_eq!(r.energy, 0.5);
+
+        let obj = Line {
+            x0: Sample::Constant(20.0),
+            y0: Sample::Constant(-10.0),
+            dx: Sample::Constant(0.0),
+            dy: Sample::Constant(20.0),
+            material: Box::new(Fluorescent),
+        };
+        let r = bounce(&mut r, &obj, &mut rng).expect("Ray should hit the second line");
+        assert_eq!(r.wavelength, 660.0);
+        assert_eq!(r.energy, 0.25);
+    }
+
+    #[test]
+    fn media_scatter_rays() {
+        use bvh::Bvh;
+        use image::Image;
+        use medium::Medium;
+        use region::Region;
+
+        let mut rng = Pcg64Fast::from_entropy();
+        let l = Light {
+            power: Sample::Constant(1.0),
+            x: Sample::Constant(10.0),
+            y: Sample::Constant(50.0),
+            polar_angle: Sample::Constant(0.0),
+            polar_distance: Sample::Constant(0.0),
+            ray_angle: Sample::Constant(0.0),
+            wavelength: Sample::Constant(560.0),
+        };
+        let region = Region::Circle {
+            center: Point { x: 50.0, y: 50.0 },
+            radius: 10.0,
+        };
+        let viewport = Rect::from_points(&Point { x: 0.0, y: 0.0 }, &Point { x: 100.0, y: 100.0 });
+        let mut image = Image::new(100, 100, 1.0);
+
+        // Dense enough that every ray scatters inside the region.
+        let fog = [Medium::new(region.clone(), 1e6)];
+        for _ in 0..100 {
+            let mut r = Ray::new(&l, 0.0, &[0.5; DIMENSIONS], &mut rng);
+            let s = r
+                .collision_list(&[], &Bvh::new(&[]), &fog, viewport, &mut image)
+                .expect("Ray should scatter");
+            assert!((s.origin.x - 40.0).abs() < 1e-3);
+            assert_eq!(s.bounces, r.bounces - 1);
+        }
+
+        let smoke = [Medium::new(region, 1e6).with_albedo(0.0)];
+        let mut r = Ray::new(&l, 0.0, &[0.5; DIMENSIONS], &mut rng);
+        assert!(r.collision_list(&[], &Bvh::new(&[]), &smoke, viewport, &mut image).is_none());
+    }
+
+    #[test]
+    /// Only the closest object decides the outcome, absorbers hide anything behind them.
+    fn closest_object_absorbs() {
+        use bvh::Bvh;
+        use image::Image;
+        use material::HQZLegacy;
+
+        let mut rng = Pcg64Fast::from_entropy();
+        let l = Light {
+            power: Sample::Constant(1.0),
+            x: Sample::Constant(10.0),
+            y: Sample::Constant(50.0),
+            polar_angle: Sample::Constant(0.0),
+            polar_distance: Sample::Constant(0.0),
+            ray_angle: Sample::Constant(0.0),
+            wavelength: Sample::Constant(560.0),
+        };
+        let wall = |x: f64, material: HQZLegacy| -> Box<dyn Object> {
+            Box::new(Line {
+                x0: Sample::Constant(x),
+                y0: Sample::Constant(0.0),
+                dx: Sample::Constant(0.0),
+                dy: Sample::Constant(100.0),
+                material: Box::new(material),
+            })
+        };
+        let objects = [wall(70.0, HQZLegacy::new(0.0, 1.0, 0.0)), wall(50.0, HQZLegacy::new(0.0, 0.0, 0.0))];
+        let bvh = Bvh::new(&objects);
+        let viewport = Rect::from_points(&Point { x: 0.0, y: 0.0 }, &Point { x: 100.0, y: 100.0 });
+        let mut image = Image::new(100, 100, 1.0);
+
+        for _ in 0..100 {
+            let mut r = Ray::new(&l, 0.0, &[0.5; DIMENSIONS], &mut rng);
+            assert!(r.collision_list(&objects, &bvh, &[], viewport, &mut image).is_none());
+        }
+    }
 }
@@ -0,0 +1,571 @@
+//! Closed areas of the scene, and constructive solid geometry over them.
+
+use geom::{Point, Rect, Vector};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/// A closed area of the scene.
+///
+/// Regions can be combined with `union_with()`, `intersect_with()` and
+/// `subtract()` to build more complex shapes, such as a lens from the
+/// intersection of two circles.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Region {
+    /// A circle with the given centre and radius.
+    Circle {
+        /// Centre of the circle.
+        center: Point,
+        /// Radius of the circle.
+        radius: f64,
+    },
+    /// A polygon with the given corners, closed back to the first corner.
+    ///
+    /// Polygons with fewer than three corners enclose nothing.
+    ///
+    /// The polygon may be concave or self intersecting, the inside is decided
+    /// by the even-odd rule.
+    Polygon(Vec<Point>),
+    /// Everything inside either region.
+    Union(Box<Region>, Box<Region>),
+    /// Everything inside both regions.
+    Intersection(Box<Region>, Box<Region>),
+    /// Everything inside the first region but not the second.
+    Difference(Box<Region>, Box<Region>),
+}
+
+impl Region {
+    /// Region covering this one and `other`.
+    pub fn union_with(self, other: Region) -> Region {
+        Region::Union(Box::new(self), Box::new(other))
+    }
+
+    /// Region covering only where this one and `other` overlap.
+    pub fn intersect_with(self, other: Region) -> Region {
+        Region::Intersection(Box::new(self), Box::new(other))
+    }
+
+    /// This region with `other` cut out of it.
+    pub fn subtract(self, other: Region) -> Region {
+        Region::Difference(Box::new(self), Box::new(other))
+    }
+
+    /// Tests if a point is inside the region.
+    pub fn contains(&self, p: &Point) -> bool {
+        match self {
+            Region::Circle { center, radius } => center.distance(p) <= *radius,
+            Region::Polygon(points) => {
+                let mut inside = false;
+                for (i, a) in points.iter().enumerate() {
+                    let b = &points[(i + 1) % points.len()];
+                    if (a.y > p.y) != (b.y > p.y) {
+                        let x = a.x + (p.y - a.y) * (b.x - a.x) / (b.y - a.y);
+                        if p.x < x {
+                            inside = !inside;
+                        }
+                    }
+                }
+                inside
+            }
+            Region::Union(a, b) => a.contains(p) || b.contains(p),
+            Region::Intersection(a, b) => a.contains(p) && b.contains(p),
+            Region::Difference(a, b) => a.contains(p) && !b.contains(p),
+        }
+    }
+
+    /// Returns a rectangle enclosing the region.
+    pub fn bounds(&self) -> Rect {
+        match self {
+            Region::Circle { center, radius } => Rect::centered_with_radius(center, *radius),
+            Region::Polygon(points) => {
+                // A polygon without corners encloses nothing, at the origin.
+                let mut r = Rect::null_at(points.first().unwrap_or(&Point { x: 0.0, y: 0.0 }));
+                for p in points.iter() {
+                    r.expand_to_include(p);
+                }
+                r
+            }
+            Region::Union(a, b) => a.bounds().union_with(&b.bounds()),
+            Region::Intersection(a, b) => {
+                let (a, b) = (a.bounds(), b.bounds());
+                if a.does_intersect(&b) {
+                    a.intersect_with(&b)
+                } else {
+                    a
+                }
+            }
+            Region::Difference(a, _) => a.bounds(),
+        }
+    }
+
+    /// Combines the inside of two regions.
+    fn inside(&self, a: bool, b: bool) -> bool {
+        match self {
+            Region::Union(..) => a || b,
+            Region::Intersection(..) => a && b,
+            Region::Difference(..) => a && !b,
+            _ => unreachable!("Only combined regions have two sides"),
+        }
+    }
+
+    /**
+     * Finds where the ray from `origin` along `direction` crosses the edge of the region.
+     *
+     * Returns if the origin is inside the region, and the distance to each
+     * crossing in front of it, in multiples of `direction`, sorted by distance.
+     * Each crossing comes with the normal to the edge there, facing either way.
+     */
+    pub(crate) fn crossings(&self, origin: &Point, direction: &Vector) -> (bool, Vec<(f64, Vector)>) {
+        match self {
+            Region::Circle { center, radius } => {
+                let oc = *origin - *center;
+                let a = direction.dot(direction);
+                let b = oc.dot(direction);
+                let c = oc.dot(&oc) - radius * radius;
+                let disc = b * b - a * c;
+                if disc <= 0.0 {
+                    return (false, vec![]);
+                }
+                let root = disc.sqrt();
+                let (t0, t1) = ((-b - root) / a, (-b + root) / a);
+                // Inside if the ray enters at or behind the origin, consistent with
+                // the crossings kept, even when the origin is right on the edge.
+                let inside = t0 <= 0.0 && t1 > 0.0;
+                let crossings = [t0, t1]
+                    .iter()
+                    .filter(|t| **t > 0.0)
+                    .map(|&t| {
+                        let normal = Vector {
+                            x: oc.x + t * direction.x,
+                            y: oc.y + t * direction.y,
+                        };
+                        (t, normal)
+                    })
+                    .collect();
+                (inside, crossings)
+            }
+            Region::Polygon(points) if points.len() < 3 => (false, vec![]),
+            Region::Polygon(points) => {
+                // Counting the crossings behind the origin, rather than using
+                // contains(), keeps the inside consistent with the crossings found.
+                let mut inside = false;
+                let mut crossings = vec![];
+                for (i, a) in points.iter().enumerate() {
+                    let edge = points[(i + 1) % points.len()] - *a;
+                    let denom = direction.cross(&edge);
+                    if denom == 0.0 {
+                        continue;
+                    }
+                    let w = *a - *origin;
+                    let t = w.cross(&edge) / denom;
+                    let s = w.cross(direction) / denom;
+                    if !(0.0..1.0).contains(&s) {
+                        continue;
+                    }
+                    if t > 0.0 {
+                        crossings.push((t, Vector { x: -edge.y, y: edge.x }));
+                    } else {
+                        inside = !inside;
+                    }
+                }
+                crossings.sort_by(|a, b| a.0.partial_cmp(&b.0).expect("Region crossing is NaN"));
+                (inside, crossings)
+            }
+            Region::Union(a, b) | Region::Intersection(a, b) | Region::Difference(a, b) => {
+                let (mut in_a, a) = a.crossings(origin, direction);
+                let (mut in_b, b) = b.crossings(origin, direction);
+                let inside = self.inside(in_a, in_b);
+
+                // Walk both sets of crossings in order, keeping those where the combined inside changes.
+                let mut was_inside = inside;
+                let mut crossings = vec![];
+                let (mut i, mut j) = (0, 0);
+                while i < a.len() || j < b.len() {
+                    let crossing = if j == b.len() || (i < a.len() && a[i].0 <= b[j].0) {
+                        in_a = !in_a;
+                        i += 1;
+                        a[i - 1]
+                    } else {
+                        in_b = !in_b;
+                        j += 1;
+                        b[j - 1]
+                    };
+                    let now_inside = self.inside(in_a, in_b);
+                    if now_inside != was_inside {
+                        crossings.push(crossing);
+                        was_inside = now_inside;
+                    }
+                }
+                (inside, crossings)
+            }
+        }
+    }
+
+    /// Spans of distance along the ray from `origin` in the unit `direction`
+    /// which are inside the region, up to a distance of `max`, sorted by distance.
+    pub(crate) fn spans(&self, origin: &Point, direction: &Vector, max: f64) -> Vec<(f64, f64)> {
+        let (inside, crossings) = self.crossings(origin, direction);
+        let mut spans = vec![];
+        let mut start = if inside { Some(0.0) } else { None };
+        for (t, _) in crossings {
+            if t >= max {
+                break;
+            }
+            start = match start {
+                Some(s) => {
+                    spans.push((s, t));
+                    None
+                }
+                None => Some(t),
+            };
+        }
+        if let Some(s) = start {
+            spans.push((s, max));
+        }
+        spans
+    }
+
+    /// Total length of the region's edge.
+    pub(crate) fn edge_length(&self) -> f64 {
+        self.edges().iter().map(Edge::length).sum()
+    }
+
+    /// The point `alpha` of the way round the region's edge, with the outward
+    /// normal there, or `None` if the region has no edge.
+    pub(crate) fn edge_at(&self, alpha: f64) -> Option<(Point, Vector)> {
+        let edges = self.edges();
+        let total: f64 = edges.iter().map(Edge::length).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut along = alpha.clamp(0.0, 1.0) * total;
+        let mut edge = edges[edges.len() - 1];
+        for e in edges.iter() {
+            if along <= e.length() {
+                edge = *e;
+                break;
+            }
+            along -= e.length();
+        }
+        let (point, normal) = edge.at((along / edge.length()).clamp(0.0, 1.0));
+        let outside = point + Vector {
+            x: normal.x * self.epsilon(),
+            y: normal.y * self.epsilon(),
+        };
+        Some((point, if self.contains(&outside) { -normal } else { normal }))
+    }
+
+    /// Small distance used to test either side of an edge.
+    fn epsilon(&self) -> f64 {
+        let b = self.bounds();
+        (b.right() - b.left() + b.bottom() - b.top()) * 1e-9
+    }
+
+    /// Circles and polygons the region is built from.
+    fn leaves<'a>(&'a self, leaves: &mut Vec<&'a Region>) {
+        match self {
+            Region::Polygon(points) if points.len() < 3 => {}
+            Region::Circle { .. } | Region::Polygon(_) => leaves.push(self),
+            Region::Union(a, b) | Region::Intersection(a, b) | Region::Difference(a, b) => {
+                a.leaves(leaves);
+                b.leaves(leaves);
+            }
+        }
+    }
+
+    /// Pieces of the region's edge. Each circle and polygon it's built from
+    /// is split where the others cross it, and the pieces with the inside of
+    /// the region on only one side are kept.
+    fn edges(&self) -> Vec<Edge> {
+        let mut leaves = vec![];
+        self.leaves(&mut leaves);
+        let mut edges = vec![];
+        for (i, leaf) in leaves.iter().enumerate() {
+            let others = || leaves.iter().enumerate().filter(move |(j, _)| *j != i).map(|(_, l)| *l);
+            match leaf {
+                Region::Circle { center, radius } => {
+                    let mut angles: Vec<f64> = others().flat_map(|o| o.circle_crossings(center, *radius)).collect();
+                    angles.sort_by(|a, b| a.partial_cmp(b).expect("Region crossing is NaN"));
+                    if angles.is_empty() {
+                        angles.push(0.0);
+                    }
+                    for (k, start) in angles.iter().enumerate() {
+                        let end = angles.get(k + 1).cloned().unwrap_or(angles[0] + 2.0 * PI);
+                        edges.push(Edge::Arc {
+                            center: *center,
+                            radius: *radius,
+                            start: *start,
+                            sweep: end - start,
+                        });
+                    }
+                }
+                Region::Polygon(points) => {
+                    for (k, a) in points.iter().enumerate() {
+                        let b = points[(k + 1) % points.len()];
+                        let mut splits = vec![0.0, 1.0];
+                        for o in others() {
+                            splits.extend(o.crossings(a, &(b - *a)).1.iter().map(|c| c.0).filter(|t| *t < 1.0));
+                        }
+                        splits.sort_by(|a, b| a.partial_cmp(b).expect("Region crossing is NaN"));
+                        let along = |t: f64| *a + Vector {
+                            x: (b.x - a.x) * t,
+                            y: (b.y - a.y) * t,
+                        };
+                        for w in splits.windows(2) {
+                            edges.push(Edge::Segment(along(w[0]), along(w[1])));
+                        }
+                    }
+                }
+                _ => unreachable!("Only circles and polygons are leaves"),
+            }
+        }
+        edges.retain(|e| e.length() > 0.0);
+        if leaves.len() > 1 {
+            let epsilon = self.epsilon();
+            edges.retain(|e| {
+                let (p, n) = e.at(0.5);
+                let side = |sign: f64| {
+                    self.contains(&(p + Vector {
+                        x: sign * epsilon * n.x,
+                        y: sign * epsilon * n.y,
+                    }))
+                };
+                side(1.0) != side(-1.0)
+            });
+        }
+        edges
+    }
+
+    /// Angles round the circle at `center` where this circle or polygon's edge crosses it.
+    fn circle_crossings(&self, center: &Point, radius: f64) -> Vec<f64> {
+        match self {
+            Region::Circle { center: c, radius: r } => {
+                let d = *c - *center;
+                let distance = d.magnitude();
+                if distance == 0.0 || distance >= radius + r || distance <= (radius - r).abs() {
+                    return vec![];
+                }
+                // Distance from `center` to the chord joining the crossings, along `d`.
+                let a = (radius * radius - r * r + distance * distance) / (2.0 * distance);
+                let spread = (a / radius).clamp(-1.0, 1.0).acos();
+                let towards = d.y.atan2(d.x);
+                vec![towards - spread, towards + spread]
+            }
+            Region::Polygon(points) => {
+                let mut angles = vec![];
+                for (k, p) in points.iter().enumerate() {
+                    let edge = points[(k + 1) % points.len()] - *p;
+                    let w = *p - *center;
+                    let a = edge.dot(&edge);
+                    let b = w.dot(&edge);
+                    let c = w.dot(&w) - radius * radius;
+                    let disc = b * b - a * c;
+                    if a == 0.0 || disc <= 0.0 {
+                        continue;
+                    }
+                    for t in [(-b - disc.sqrt()) / a, (-b + disc.sqrt()) / a] {
+                        if (0.0..1.0).contains(&t) {
+                            angles.push((w.y + t * edge.y).atan2(w.x + t * edge.x));
+                        }
+                    }
+                }
+                angles
+            }
+            _ => unreachable!("Only circles and polygons are leaves"),
+        }
+    }
+}
+
+/// A piece of the edge of a `Region`.
+#[derive(Copy, Clone, Debug)]
+enum Edge {
+    /// A straight edge between two points.
+    Segment(Point, Point),
+    /// Part of a circle, from the `start` angle round by `sweep` radians.
+    Arc {
+        center: Point,
+        radius: f64,
+        start: f64,
+        sweep: f64,
+    },
+}
+
+impl Edge {
+    fn length(&self) -> f64 {
+        match self {
+            Edge::Segment(a, b) => a.distance(b),
+            Edge::Arc { radius, sweep, .. } => radius * sweep,
+        }
+    }
+
+    /// Point `t` of the way along the edge, with a unit normal facing either way.
+    fn at(&self, t: f64) -> (Point, Vector) {
+        match self {
+            Edge::Segment(a, b) => {
+                let d = *b - *a;
+                let point = *a + Vector { x: d.x * t, y: d.y * t };
+                (point, Vector { x: -d.y, y: d.x }.normalized())
+            }
+            Edge::Arc {
+                center,
+                radius,
+                start,
+                sweep,
+            } => {
+                let (sin, cos) = (start + t * sweep).sin_cos();
+                let point = *center + Vector { x: cos * radius, y: sin * radius };
+                (point, Vector { x: cos, y: sin })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Region;
+    use geom::{Point, Vector};
+
+    fn square() -> Region {
+        Region::Polygon(vec![
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 10.0, y: 0.0 },
+            Point { x: 10.0, y: 10.0 },
+            Point { x: 0.0, y: 10.0 },
+        ])
+    }
+
+    #[test]
+    fn circle_spans() {
+        let c = Region::Circle {
+            center: Point { x: 10.0, y: 0.0 },
+            radius: 2.0,
+        };
+        let dir = Vector { x: 1.0, y: 0.0 };
+        assert_eq!(c.spans(&Point { x: 0.0, y: 0.0 }, &dir, 100.0), vec![(8.0, 12.0)]);
+        assert_eq!(c.spans(&Point { x: 10.0, y: 0.0 }, &dir, 100.0), vec![(0.0, 2.0)]);
+        assert_eq!(c.spans(&Point { x: 0.0, y: 0.0 }, &dir, 9.0), vec![(8.0, 9.0)]);
+        assert!(c.spans(&Point { x: 0.0, y: 5.0 }, &dir, 100.0).is_empty());
+        assert!(c.contains(&Point { x: 11.0, y: 1.0 }));
+    }
+
+    #[test]
+    fn polygon_spans() {
+        let s = square();
+        let dir = Vector { x: 1.0, y: 0.0 };
+        assert_eq!(s.spans(&Point { x: -5.0, y: 5.0 }, &dir, 100.0), vec![(5.0, 15.0)]);
+        assert_eq!(s.spans(&Point { x: 5.0, y: 5.0 }, &dir, 100.0), vec![(0.0, 5.0)]);
+        assert_eq!(s.spans(&Point { x: -5.0, y: 5.0 }, &dir, 8.0), vec![(5.0, 8.0)]);
+        assert!(s.contains(&Point { x: 5.0, y: 5.0 }));
+        assert!(!s.contains(&Point { x: 15.0, y: 5.0 }));
+
+        // Concave U shape, the ray passes through both arms.
+        let u = Region::Polygon(vec![
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 3.0, y: 0.0 },
+            Point { x: 3.0, y: 5.0 },
+            Point { x: 6.0, y: 5.0 },
+            Point { x: 6.0, y: 0.0 },
+            Point { x: 9.0, y: 0.0 },
+            Point { x: 9.0, y: 10.0 },
+            Point { x: 0.0, y: 10.0 },
+        ]);
+        let spans = u.spans(&Point { x: -1.0, y: 2.0 }, &dir, 100.0);
+        assert_eq!(spans, vec![(1.0, 4.0), (7.0, 10.0)]);
+    }
+
+    #[test]
+    fn combined_spans() {
+        let dir = Vector { x: 1.0, y: 0.0 };
+        let origin = Point { x: -10.0, y: 5.0 };
+        let circle = Region::Circle {
+            center: Point { x: 10.0, y: 5.0 },
+            radius: 4.0,
+        };
+
+        let union = square().union_with(circle.clone());
+        assert_eq!(union.spans(&origin, &dir, 100.0), vec![(10.0, 24.0)]);
+        assert!(union.contains(&Point { x: 13.0, y: 5.0 }));
+
+        let both = square().intersect_with(circle.clone());
+        assert_eq!(both.spans(&origin, &dir, 100.0), vec![(16.0, 20.0)]);
+        assert!(!both.contains(&Point { x: 5.0, y: 5.0 }));
+
+        let cut = square().subtract(circle);
+        assert_eq!(cut.spans(&origin, &dir, 100.0), vec![(10.0, 16.0)]);
+        assert!(cut.contains(&Point { x: 5.0, y: 5.0 }));
+        assert!(!cut.contains(&Point { x: 8.0, y: 5.0 }));
+
+        let b = cut.bounds();
+        assert_eq!((b.left(), b.right(), b.top(), b.bottom()), (0.0, 10.0, 0.0, 10.0));
+    }
+
+    #[test]
+    fn combined_normals_come_from_the_edge_crossed() {
+        let dir = Vector { x: 1.0, y: 0.0 };
+        let ring = Region::Circle {
+            center: Point { x: 0.0, y: 0.0 },
+            radius: 10.0,
+        }
+        .subtract(Region::Circle {
+            center: Point { x: 0.0, y: 0.0 },
+            radius: 5.0,
+        });
+        let (inside, crossings) = ring.crossings(&Point { x: -20.0, y: 0.0 }, &dir);
+        assert!(!inside);
+        let t: Vec<f64> = crossings.iter().map(|c| c.0).collect();
+        assert_eq!(t, vec![10.0, 15.0, 25.0, 30.0]);
+        for c in crossings {
+            assert_eq!(c.1.y, 0.0);
+        }
+
+        // Starting in the hole, the first edge is the inner circle.
+        let (inside, crossings) = ring.crossings(&Point { x: 0.0, y: 0.0 }, &dir);
+        assert!(!inside);
+        assert_eq!(crossings[0].0, 5.0);
+    }
+
+    #[test]
+    fn degenerate_polygons_enclose_nothing() {
+        let dir = Vector { x: 1.0, y: 0.0 };
+        let origin = Point { x: -5.0, y: 0.0 };
+        let empty = Region::Polygon(vec![]);
+        let b = empty.bounds();
+        assert_eq!((b.left(), b.right(), b.top(), b.bottom()), (0.0, 0.0, 0.0, 0.0));
+        assert!(empty.spans(&origin, &dir, 100.0).is_empty());
+        assert!(!empty.contains(&Point { x: 0.0, y: 0.0 }));
+
+        let line = Region::Polygon(vec![Point { x: 0.0, y: -1.0 }, Point { x: 0.0, y: 1.0 }]);
+        assert!(line.spans(&origin, &dir, 100.0).is_empty());
+        assert_eq!(line.edge_at(0.5), None);
+    }
+
+    #[test]
+    fn edges_follow_the_combined_outline() {
+        let cut = square().subtract(Region::Circle {
+            center: Point { x: 10.0, y: 5.0 },
+            radius: 4.0,
+        });
+        // Three whole sides, the two ends of the fourth, and half the circle.
+        let length = 32.0 + 4.0 * std::f64::consts::PI;
+        assert!((cut.edge_length() - length).abs() < 1e-9);
+        for i in 0..100 {
+            let (p, n) = cut.edge_at((i as f64 + 0.5) / 100.0).unwrap();
+            let on_square = [p.x, p.y, p.x - 10.0, p.y - 10.0].iter().any(|d| d.abs() < 1e-9);
+            let on_circle = (p.distance(&Point { x: 10.0, y: 5.0 }) - 4.0).abs() < 1e-9;
+            assert!(on_square || on_circle, "{:?} is off the edge", p);
+            let step = |d: f64| p + Vector { x: n.x * d, y: n.y * d };
+            assert!(!cut.contains(&step(0.01)) && cut.contains(&step(-0.01)), "{:?} faces inwards", p);
+        }
+
+        let lens = Region::Circle {
+            center: Point { x: -6.0, y: 0.0 },
+            radius: 10.0,
+        }
+        .intersect_with(Region::Circle {
+            center: Point { x: 6.0, y: 0.0 },
+            radius: 10.0,
+        });
+        assert!((lens.edge_length() - 40.0 * 0.6f64.acos()).abs() < 1e-9);
+    }
+}
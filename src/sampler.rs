@@ -1,16 +1,31 @@
 //! This module provides rustic-zen's sampler implementation, which
 //! is needed for instanciating lights and objects.
 
+use animation::{Keyframe, Timeline};
 use pcg_rand::Pcg64Fast;
-use spectrum::blackbody_wavelength;
+use spectrum::{blackbody_bounds, blackbody_mean, blackbody_wavelength};
 use std::f64;
 use rand::Rng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use std::convert::TryFrom;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "SampleDef", into = "SampleDef")
+)]
 /// Samples a stochastically sampled value, which may be:
 ///  - a constant
 ///  - linear range between two values
 ///  - A Blackbody Curve of temperature K
+///  - a normal distribution, optionally truncated to a range
+///  - a weighted choice between discrete values
+///  - a piecewise linear probability density
+///  - an exponential distribution
+///  - a keyframed value which changes over time
 pub enum Sample {
     /// A constant Value
     Constant(f64),
@@ -24,20 +39,318 @@ pub enum Sample {
     /// rustic-zen is about going fast not holding your hand and these samplers
     /// are in the critical path
     Range(f64, f64),
+    /// A value sampled from a normal distribution with the given mean and
+    /// standard deviation.
+    ///
+    /// The far tails are cut off at roughly six standard deviations so that
+    /// the distribution has finite bounds.
+    Normal(f64, f64),
+    /// A normal distribution with the given mean and standard deviation,
+    /// truncated to the given range.
+    ///
+    /// As with `Range` the larger bound must be the third argument and the smaller the fourth.
+    TruncatedNormal(f64, f64, f64, f64),
+    /// A weighted choice between a set of values, build this with `Sample::discrete()`.
+    Discrete(Table),
+    /// A value sampled from a piecewise linear probability density,
+    /// build this with `Sample::piecewise()`.
+    Piecewise(Table),
+    /// A value sampled from an exponential distribution with the given rate (lambda),
+    /// giving values from 0.0 upwards with a mean of `1.0 / lambda`, build this
+    /// with `Sample::exponential()`.
+    ///
+    /// The tail is cut off at roughly `21.0 / lambda` so that the distribution
+    /// has finite bounds.
+    Exponential(f64),
+    /// A value which changes over time, build this with `Sample::keyframes()`.
+    ///
+    /// When sampled without a time, such as through `val()`, the value at time 0.0 is used.
+    Keyframes(Timeline),
+}
+
+/// Precomputed lookup table backing the `Discrete` and `Piecewise` variants of `Sample`.
+///
+/// Tables are built by `Sample::discrete()` and `Sample::piecewise()`, which
+/// normalise the weights up front so sampling is a binary search.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Table {
+    points: Vec<(f64, f64)>,
+    cdf: Vec<f64>,
+}
+
+/// How a `Sample` is written in scene files, constants are bare numbers.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum SampleDef {
+    Constant(f64),
+    Distribution(Distribution),
+}
+
+/// Every other `Sample`, with tables written as the points they were built from.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+enum Distribution {
+    Blackbody(f64),
+    Range(f64, f64),
+    Normal(f64, f64),
+    TruncatedNormal(f64, f64, f64, f64),
+    Discrete(Vec<(f64, f64)>),
+    Piecewise(Vec<(f64, f64)>),
+    Exponential(f64),
+    Keyframes(Vec<Keyframe>),
+}
+
+/// Samples read from a file are checked the same as the constructors,
+/// but report the problem rather than panicking.
+#[cfg(feature = "serde")]
+impl TryFrom<SampleDef> for Sample {
+    type Error = &'static str;
+
+    fn try_from(def: SampleDef) -> Result<Self, Self::Error> {
+        let d = match def {
+            SampleDef::Constant(c) => return Ok(Sample::Constant(c)),
+            SampleDef::Distribution(d) => d,
+        };
+        Ok(match d {
+            Distribution::Blackbody(k) => Sample::Blackbody(k),
+            Distribution::Range(u, l) => {
+                if u <= l {
+                    return Err("Range sample upper bound must be above the lower bound");
+                }
+                Sample::Range(u, l)
+            }
+            Distribution::Normal(m, s) => Sample::Normal(m, s),
+            Distribution::TruncatedNormal(m, s, u, l) => Sample::TruncatedNormal(m, s, u, l),
+            Distribution::Discrete(v) => Sample::try_discrete(&v)?,
+            Distribution::Piecewise(p) => Sample::try_piecewise(&p)?,
+            Distribution::Exponential(r) => Sample::try_exponential(r)?,
+            Distribution::Keyframes(k) => Sample::Keyframes(Timeline::try_new(&k)?),
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Sample> for SampleDef {
+    fn from(s: Sample) -> Self {
+        SampleDef::Distribution(match s {
+            Sample::Constant(c) => return SampleDef::Constant(c),
+            Sample::Blackbody(k) => Distribution::Blackbody(k),
+            Sample::Range(u, l) => Distribution::Range(u, l),
+            Sample::Normal(m, s) => Distribution::Normal(m, s),
+            Sample::TruncatedNormal(m, s, u, l) => Distribution::TruncatedNormal(m, s, u, l),
+            Sample::Discrete(t) => Distribution::Discrete(t.points),
+            Sample::Piecewise(t) => Distribution::Piecewise(t.points),
+            Sample::Exponential(r) => Distribution::Exponential(r),
+            Sample::Keyframes(t) => Distribution::Keyframes(t.keys().to_vec()),
+        })
+    }
 }
 
 impl Sample {
+    /// Creates a `Sample` which picks one of the given values, weighted by the
+    /// second element of each pair.
+    ///
+    /// Weights need not sum to one, but must not be negative, and at least one
+    /// must be positive.
+    ///
+    /// # Example
+    /// Emit light at exactly three angles, the middle one twice as often:
+    /// ```
+    /// use rustic_zen::prelude::Sample;
+    ///
+    /// let s = Sample::discrete(&[(30.0, 1.0), (45.0, 2.0), (60.0, 1.0)]);
+    /// assert_eq!(s.bounds(), (60.0, 30.0));
+    /// ```
+    pub fn discrete(values: &[(f64, f64)]) -> Self {
+        Self::try_discrete(values).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// `discrete()`, returning why the values are invalid instead of panicking.
+    fn try_discrete(values: &[(f64, f64)]) -> Result<Self, &'static str> {
+        let mut cdf = Vec::with_capacity(values.len());
+        let mut total = 0.0;
+        for &(_, weight) in values {
+            if weight < 0.0 {
+                return Err("Discrete sample weights must not be negative");
+            }
+            total += weight;
+            cdf.push(total);
+        }
+        if total <= 0.0 {
+            return Err("Discrete sample needs at least one positive weight");
+        }
+        for c in cdf.iter_mut() {
+            *c /= total;
+        }
+        Ok(Sample::Discrete(Table {
+            points: values.to_vec(),
+            cdf,
+        }))
+    }
+
+    /// Creates a `Sample` from a table of `(x, density)` points, the density is
+    /// linearly interpolated between points and zero outside of them.
+    ///
+    /// Points must be sorted by x, densities must not be negative, and the
+    /// table must enclose some area.
+    ///
+    /// # Example
+    /// A triangular distribution peaking at 0.0:
+    /// ```
+    /// use rustic_zen::prelude::Sample;
+    ///
+    /// let s = Sample::piecewise(&[(-1.0, 0.0), (0.0, 1.0), (1.0, 0.0)]);
+    /// assert_eq!(s.bounds(), (1.0, -1.0));
+    /// ```
+    pub fn piecewise(points: &[(f64, f64)]) -> Self {
+        Self::try_piecewise(points).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// `piecewise()`, returning why the points are invalid instead of panicking.
+    pub(crate) fn try_piecewise(points: &[(f64, f64)]) -> Result<Self, &'static str> {
+        if points.len() < 2 {
+            return Err("Piecewise sample needs at least two points");
+        }
+        let mut cdf = Vec::with_capacity(points.len());
+        let mut total = 0.0;
+        cdf.push(total);
+        for w in points.windows(2) {
+            let ((x0, p0), (x1, p1)) = (w[0], w[1]);
+            if x1 < x0 {
+                return Err("Piecewise sample points must be sorted by x");
+            }
+            if p0 < 0.0 || p1 < 0.0 {
+                return Err("Piecewise sample densities must not be negative");
+            }
+            total += (x1 - x0) * (p0 + p1) / 2.0;
+            cdf.push(total);
+        }
+        if total <= 0.0 {
+            return Err("Piecewise sample encloses no area");
+        }
+        for c in cdf.iter_mut() {
+            *c /= total;
+        }
+        Ok(Sample::Piecewise(Table {
+            points: points.iter().map(|&(x, p)| (x, p / total)).collect(),
+            cdf,
+        }))
+    }
+
+    /// Creates a `Sample` from an exponential distribution with the given rate,
+    /// which must be positive.
+    ///
+    /// # Example
+    /// Distances with a mean of 50.0:
+    /// ```
+    /// use rustic_zen::prelude::Sample;
+    ///
+    /// let s = Sample::exponential(0.02);
+    /// assert_eq!(s.expected(), 50.0);
+    /// ```
+    pub fn exponential(rate: f64) -> Self {
+        Self::try_exponential(rate).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// `exponential()`, returning why the rate is invalid instead of panicking.
+    fn try_exponential(rate: f64) -> Result<Self, &'static str> {
+        if !rate.is_finite() || rate <= 0.0 {
+            return Err("Exponential sample rate must be positive and finite");
+        }
+        Ok(Sample::Exponential(rate))
+    }
+
+    /// Creates a `Sample` which moves between the given keyframes over time.
+    ///
+    /// Keyframes may be given in any order, they are sorted by time.
+    ///
+    /// # Example
+    /// A light that slides right over one second, easing in and out:
+    /// ```
+    /// use rustic_zen::prelude::{Keyframe, Sample};
+    /// use rustic_zen::prelude::Interpolation::EaseInOut;
+    ///
+    /// let x = Sample::keyframes(&[
+    ///     Keyframe { time: 0.0, value: 100.0, interpolation: EaseInOut },
+    ///     Keyframe { time: 1.0, value: 500.0, interpolation: EaseInOut },
+    /// ]);
+    /// assert_eq!(x.expected_at(0.5), 300.0);
+    /// assert_eq!(x.bounds(), (500.0, 100.0));
+    /// ```
+    pub fn keyframes(keys: &[Keyframe]) -> Self {
+        Sample::Keyframes(Timeline::new(keys))
+    }
+
     /// Returns next value of this sampler
     pub fn val(&self, sampler: &mut Pcg64Fast) -> f64 {
+        self.val_at(0.0, sampler)
+    }
+
+    /// Returns next value of this sampler at the given time.
+    pub fn val_at(&self, time: f64, sampler: &mut Pcg64Fast) -> f64 {
         match self {
-            Sample::Constant(i) => i.clone(),
-            Sample::Blackbody(k) => blackbody_wavelength(k.clone(), sampler.gen_range(0.0f64, 1.0f64)),
+            Sample::Constant(i) => *i,
+            Sample::Blackbody(k) => blackbody_wavelength(*k, sampler.gen_range(0.0f64, 1.0f64)),
             Sample::Range(l, u) => sampler.gen_range(u, l),
+            Sample::Keyframes(t) => t.value_at(time),
+            _ => self.val_from(time, sampler.gen_range(0.0f64, 1.0f64)),
+        }
+    }
+
+    /// Returns the value of this sampler at the given time, for a given
+    /// uniformly distributed number `u` in `[0, 1)`.
+    ///
+    /// This maps `u` through the inverse of the distribution, so evenly spread
+    /// values of `u` give evenly spread samples. `Keyframes` ignore `u`.
+    ///
+    /// # Example
+    /// ```
+    /// use rustic_zen::prelude::Sample;
+    ///
+    /// let f = Sample::Range(10.0, 0.0);
+    /// assert_eq!(f.val_from(0.0, 0.25), 2.5);
+    /// ```
+    pub fn val_from(&self, time: f64, u: f64) -> f64 {
+        match self {
+            Sample::Constant(i) => *i,
+            Sample::Keyframes(t) => t.value_at(time),
+            Sample::Blackbody(k) => blackbody_wavelength(*k, u),
+            Sample::Range(l, u0) => u0 + (l - u0) * u,
+            Sample::Normal(mean, std_dev) => {
+                mean + std_dev * normal_quantile(u.clamp(TAIL, 1.0 - TAIL))
+            }
+            Sample::TruncatedNormal(mean, std_dev, upper, lower) => {
+                let a = normal_cdf((lower - mean) / std_dev);
+                let b = normal_cdf((upper - mean) / std_dev);
+                let x = mean + std_dev * normal_quantile(a + u * (b - a));
+                // The tails of the quantile function are not perfectly accurate
+                x.max(*lower).min(*upper)
+            }
+            Sample::Discrete(t) => {
+                let i = t.search(u);
+                t.points[i].0
+            }
+            Sample::Piecewise(t) => {
+                let i = t.search(u);
+                let (x0, p0) = t.points[i - 1];
+                let (x1, p1) = t.points[i];
+                let width = x1 - x0;
+                // Solve the area under this trapezoid for the distance along it,
+                // this form stays stable when the segment is flat.
+                let target = u - t.cdf[i - 1];
+                let slope = (p1 - p0) / width;
+                let d = 2.0 * target / (p0 + f64::sqrt(p0 * p0 + 2.0 * slope * target));
+                x0 + d.max(0.0).min(width)
+            }
+            Sample::Exponential(lambda) => -f64::ln(1.0 - u.min(1.0 - TAIL)) / lambda,
         }
     }
 
     /// Returns upper and lower bounds of this Sample.
     ///
+    /// Every sampled value lies within these bounds, and they are always finite.
+    ///
     /// # Example
     /// ```
     /// use rustic_zen::prelude::Sample;
@@ -49,19 +362,177 @@ impl Sample {
     /// ```
     pub fn bounds(&self) -> (f64, f64) {
         match self {
-            Sample::Constant(i) => (i.clone(), i.clone()),
-            //Sample::Blackbody(k) => (k, k), //TODO Actually work out what these are.
-            Sample::Range(u, l) => (u.clone(), l.clone()),
-            _ => (f64::MIN, f64::MAX),
+            Sample::Constant(i) => (*i, *i),
+            Sample::Range(u, l) => (*u, *l),
+            Sample::Normal(..) => (self.val_from(0.0, 1.0), self.val_from(0.0, 0.0)),
+            Sample::TruncatedNormal(_, _, u, l) => (*u, *l),
+            Sample::Discrete(t) => t.points.iter().fold(
+                (f64::NEG_INFINITY, f64::INFINITY),
+                |(u, l), &(x, _)| (u.max(x), l.min(x)),
+            ),
+            Sample::Piecewise(t) => (t.points[t.points.len() - 1].0, t.points[0].0),
+            Sample::Exponential(_) => (self.val_from(0.0, 1.0), 0.0),
+            Sample::Blackbody(k) => blackbody_bounds(*k),
+            Sample::Keyframes(t) => t.bounds(),
         }
     }
+
+    /// Returns the expected (mean) value of this Sample.
+    ///
+    /// This is used to weight lights by their power, it is not cheap for
+    /// `Blackbody` so cache it rather than calling it per ray.
+    ///
+    /// # Example
+    /// ```
+    /// use rustic_zen::prelude::Sample;
+    ///
+    /// let f = Sample::Range(3.0, 1.0);
+    /// assert_eq!(f.expected(), 2.0);
+    /// ```
+    pub fn expected(&self) -> f64 {
+        self.expected_at(0.0)
+    }
+
+    /// Returns the expected (mean) value of this Sample at the given time.
+    pub fn expected_at(&self, time: f64) -> f64 {
+        match self {
+            Sample::Keyframes(t) => t.value_at(time),
+            Sample::Constant(i) => *i,
+            Sample::Range(u, l) => (u + l) / 2.0,
+            Sample::Normal(mean, _) => *mean,
+            Sample::TruncatedNormal(mean, std_dev, upper, lower) => {
+                let a = (lower - mean) / std_dev;
+                let b = (upper - mean) / std_dev;
+                let z = normal_cdf(b) - normal_cdf(a);
+                if z <= 0.0 {
+                    // Window is so far into a tail that the mass rounds to zero
+                    return mean.max(*lower).min(*upper);
+                }
+                mean + std_dev * (normal_pdf(a) - normal_pdf(b)) / z
+            }
+            Sample::Discrete(t) => {
+                let mut last = 0.0;
+                let mut sum = 0.0;
+                for (&(x, _), &c) in t.points.iter().zip(t.cdf.iter()) {
+                    sum += x * (c - last);
+                    last = c;
+                }
+                sum
+            }
+            Sample::Piecewise(t) => t
+                .points
+                .windows(2)
+                .map(|w| {
+                    let ((x0, p0), (x1, p1)) = (w[0], w[1]);
+                    (x1 - x0) * (p0 * (2.0 * x0 + x1) + p1 * (x0 + 2.0 * x1)) / 6.0
+                })
+                .sum(),
+            Sample::Exponential(lambda) => 1.0 / lambda,
+            Sample::Blackbody(k) => blackbody_mean(*k),
+        }
+    }
+}
+
+/// Probability cut off each open tail of `Normal` and `Exponential`,
+/// this keeps their bounds finite.
+const TAIL: f64 = 1e-9;
+
+impl Table {
+    /// Index of the first entry whose cumulative weight exceeds `u`.
+    fn search(&self, u: f64) -> usize {
+        self.cdf.partition_point(|&c| c <= u).min(self.cdf.len() - 1)
+    }
+}
+
+/// Standard normal probability density function.
+fn normal_pdf(x: f64) -> f64 {
+    f64::exp(-0.5 * x * x) / f64::sqrt(2.0 * f64::consts::PI)
+}
+
+/// Standard normal cumulative distribution function.
+///
+/// Uses the complementary error function approximation from Numerical Recipes,
+/// which has a fractional error below 1.2e-7 everywhere.
+fn normal_cdf(x: f64) -> f64 {
+    let z = f64::abs(x) / f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let poly = -z * z - 1.265_512_23
+        + t * (1.000_023_68
+            + t * (0.374_091_96
+                + t * (0.096_784_18
+                    + t * (-0.186_288_06
+                        + t * (0.278_868_07
+                            + t * (-1.135_203_98
+                                + t * (1.488_515_87 + t * (-0.822_152_23 + t * 0.170_872_77))))))));
+    let erfc = t * f64::exp(poly);
+    if x >= 0.0 {
+        1.0 - 0.5 * erfc
+    } else {
+        0.5 * erfc
+    }
+}
+
+/// Inverse of the standard normal cumulative distribution function.
+///
+/// This is Peter Acklam's rational approximation, relative error is below 1.15e-9.
+fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+    const P_LOW: f64 = 0.024_25;
+
+    if p <= 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if p >= 1.0 {
+        return f64::INFINITY;
+    }
+    if p < P_LOW {
+        let q = f64::sqrt(-2.0 * f64::ln(p));
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    } else if p <= 1.0 - P_LOW {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    } else {
+        let q = f64::sqrt(-2.0 * f64::ln(1.0 - p));
+        -(((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::Sample;
     use pcg_rand::Pcg64Fast;
-
     use rand::prelude::*;
 
     #[test]
@@ -114,9 +585,7 @@ mod tests {
         let mut f1: f64 = stdrng.gen();
         let mut f2: f64 = stdrng.gen();
         if f1 < f2 {
-            let tmp = f1;
-            f1 = f2;
-            f2 = tmp;
+            std::mem::swap(&mut f1, &mut f2);
         }
         let s = Sample::Range(f1, f2);
 
@@ -148,4 +617,137 @@ mod tests {
         // Check s can be sampled without panicing
         s.val(&mut rng);
     }
+
+    #[test]
+    fn normal_mean() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let s = Sample::Normal(10.0, 2.0);
+
+        let n = 100_000;
+        let mut sum = 0.0;
+        let mut sum_sq = 0.0;
+        for _ in 0..n {
+            let y = s.val(&mut rng);
+            sum += y;
+            sum_sq += y * y;
+        }
+        let mean = sum / n as f64;
+        let std_dev = f64::sqrt(sum_sq / n as f64 - mean * mean);
+        assert!((mean - 10.0).abs() < 0.05);
+        assert!((std_dev - 2.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn truncated_normal_in_bounds() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let s = Sample::TruncatedNormal(0.0, 1.0, 0.5, -2.0);
+        assert_eq!(s.bounds(), (0.5, -2.0));
+
+        for _ in 0..100_000 {
+            let y = s.val(&mut rng);
+            assert!(y <= 0.5);
+            assert!(y >= -2.0);
+        }
+    }
+
+    #[test]
+    fn discrete_weights() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let s = Sample::discrete(&[(1.0, 1.0), (2.0, 0.0), (3.0, 3.0)]);
+        assert_eq!(s.bounds(), (3.0, 1.0));
+
+        let n = 100_000;
+        let mut threes = 0;
+        for _ in 0..n {
+            let y = s.val(&mut rng);
+            assert!(y == 1.0 || y == 3.0);
+            if y == 3.0 {
+                threes += 1;
+            }
+        }
+        let ratio = threes as f64 / n as f64;
+        assert!((ratio - 0.75).abs() < 0.01);
+    }
+
+    #[test]
+    #[should_panic]
+    fn discrete_needs_weight() {
+        Sample::discrete(&[(1.0, 0.0)]);
+    }
+
+    #[test]
+    fn piecewise_triangle() {
+        let mut rng = Pcg64Fast::from_entropy();
+        // Density rises linearly from 0 to 2, so the mean is 4/3.
+        let s = Sample::piecewise(&[(0.0, 0.0), (2.0, 1.0)]);
+        assert_eq!(s.bounds(), (2.0, 0.0));
+
+        let n = 100_000;
+        let mut sum = 0.0;
+        for _ in 0..n {
+            let y = s.val(&mut rng);
+            assert!(y >= 0.0);
+            assert!(y <= 2.0);
+            sum += y;
+        }
+        let mean = sum / n as f64;
+        assert!((mean - 4.0 / 3.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn piecewise_skips_gaps() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let s = Sample::piecewise(&[(0.0, 1.0), (1.0, 1.0), (1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (3.0, 1.0)]);
+
+        for _ in 0..100_000 {
+            let y = s.val(&mut rng);
+            assert!(y <= 1.0 || y >= 2.0);
+        }
+    }
+
+    #[test]
+    fn exponential_mean() {
+        let mut rng = Pcg64Fast::from_entropy();
+        let s = Sample::exponential(0.5);
+        assert_eq!(s.bounds().1, 0.0);
+
+        let n = 100_000;
+        let mut sum = 0.0;
+        for _ in 0..n {
+            let y = s.val(&mut rng);
+            assert!(y >= 0.0);
+            sum += y;
+        }
+        let mean = sum / n as f64;
+        assert!((mean - 2.0).abs() < 0.05);
+    }
+
+    #[test]
+    #[should_panic]
+    fn exponential_needs_positive_rate() {
+        Sample::exponential(0.0);
+    }
+
+    #[test]
+    fn keyframes_at_time() {
+        use animation::{Interpolation, Keyframe};
+
+        let mut rng = Pcg64Fast::from_entropy();
+        let s = Sample::keyframes(&[
+            Keyframe {
+                time: 0.0,
+                value: 1.0,
+                interpolation: Interpolation::Linear,
+            },
+            Keyframe {
+                time: 2.0,
+                value: 3.0,
+                interpolation: Interpolation::Linear,
+            },
+        ]);
+        assert_eq!(s.val(&mut rng), 1.0);
+        assert_eq!(s.val_at(1.0, &mut rng), 2.0);
+        assert_eq!(s.expected_at(5.0), 3.0);
+        assert_eq!(s.bounds(), (3.0, 1.0));
+    }
 }
@@ -1,53 +1,30 @@
+use bvh::Bvh;
 use geom::{Point, Rect};
+use group::Group;
+#[cfg(feature = "serde")]
+use hqz::{HqzExport, HqzWriter};
+#[cfg(feature = "serde")]
+use scene_file::{SceneFile, SceneFileError, SceneFileWriter};
 use image::Image;
+use light::Emitter;
+use medium::Medium;
 use object::Object;
 use ray::Ray;
 use sampler::Sample;
 use pcg_rand::Pcg64Fast;
 use rand::prelude::*;
 use pcg_rand::seeds::PcgSeeder;
-
-/// Data only struct which defines a Light Source
-///
-/// # Examples
-/// Make a warm white small round light:
-/// ```
-/// use rustic_zen::prelude::{Light, Sample};
-///
-/// Light {
-///     power: Sample::Constant(1.0),
-///     x: Sample::Constant(512.0),
-///     y: Sample::Constant(512.0),
-///     polar_angle: Sample::Constant(0.0),
-///     polar_distance: Sample::Constant(0.0),
-///     ray_angle: Sample::Range(360.0, 0.0),
-///     wavelength: Sample::Blackbody(5800.0),
-/// };
-/// ```
-#[derive(Clone, Copy)]
-pub struct Light {
-    /// Brightness of this light relative to other lights in the scene
-    pub power: Sample,
-    /// x coordinate of the light.
-    pub x: Sample,
-    /// y coordinate of the light.
-    pub y: Sample,
-    /// distance from x,y that photon will spawn
-    pub polar_distance: Sample,
-    /// angle that polar_distance will be at
-    pub polar_angle: Sample,
-    /// Angle which spawned ray will be at
-    pub ray_angle: Sample,
-    /// Wavelength of spawned ray
-    pub wavelength: Sample,
-}
+use sequence::{Sampling, Sequence, LIGHT, TIME};
+use std::ops::Range;
 
 /// Holds scene Configuration and logic
 pub struct Scene {
-    lights: Vec<Light>,
-    objects: Vec<Object>,
+    lights: Vec<Box<dyn Emitter>>,
+    objects: Vec<Box<dyn Object>>,
+    media: Vec<Medium>,
     seed: u128, //current seed
-    total_light_power: f64,
+    shutter: (f64, f64), // open and close time, relative to the render time
+    sampling: Sampling,
     resolution_x: usize,
     resolution_y: usize,
     viewport: Rect,
@@ -61,26 +38,72 @@ impl Scene {
             seed: 0xDEADBEEF00000000F00DBABE00000000, //It just can't be 0
             lights: vec![],
             objects: vec![],
+            media: vec![],
+            shutter: (0.0, 0.0),
+            sampling: Sampling::Random,
             viewport: Rect::from_points(&Point{ x: 0.0, y: 0.0 }, &Point { x: resolution_x as f64, y: resolution_y as f64 }),
             resolution_x,
             resolution_y,
-            total_light_power: 0.0,
         }
     }
 
     /// Adds Light to the scene - Chainable varient
-    pub fn with_light(mut self, light: Light) -> Self {
-        self.total_light_power += light.power.bounds().1;
+    ///
+    /// Any `Emitter` can be added, such as a `Light`, `AreaLight`, `Beam`,
+    /// `Spotlight` or `ProfileLight`. Lights are chosen in proportion to the
+    /// expected value of their power.
+    pub fn with_light<E: Emitter + 'static>(self, light: E) -> Self {
+        self.with_boxed_light(Box::new(light))
+    }
+
+    /// Adds an already boxed light to the scene - Chainable varient
+    pub(crate) fn with_boxed_light(mut self, light: Box<dyn Emitter>) -> Self {
         self.lights.push(light);
         self
     }
 
     /// Adds object to the scene - Chainable varient
-    pub fn with_object(mut self, object: Object) -> Self {
+    ///
+    /// Any `Object` can be added, such as a `Line`, `Curve` or user defined geometry.
+    pub fn with_object<O: Object + 'static>(self, object: O) -> Self {
+        self.with_boxed_object(Box::new(object))
+    }
+
+    /// Adds an already boxed object to the scene - Chainable varient
+    pub(crate) fn with_boxed_object(mut self, object: Box<dyn Object>) -> Self {
         self.objects.push(object);
         self
     }
 
+    /// Adds a group of objects and lights to the scene - Chainable varient
+    ///
+    /// A group of only lights adds no geometry.
+    pub fn with_group(mut self, mut group: Group) -> Self {
+        self.lights.extend(group.take_lights());
+        if !group.objects.is_empty() {
+            self.objects.push(Box::new(group));
+        }
+        self
+    }
+
+    /// Adds a participating medium to the scene - Chainable varient
+    ///
+    /// Rays crossing the medium's region may scatter part way across it,
+    /// making beams visible. Overlapping media each scatter independently.
+    pub fn with_medium(mut self, medium: Medium) -> Self {
+        self.media.push(medium);
+        self
+    }
+
+    /// Sets the area rays are traced within - Chainable varient
+    ///
+    /// Defaults to the image, from (0, 0) to the resolution. A larger viewport
+    /// lets rays bounce off objects outside the image and back into view.
+    pub fn with_viewport(mut self, viewport: Rect) -> Self {
+        self.viewport = viewport;
+        self
+    }
+
     /// Sets the seed for the scene random number generator - Chainable varient
     pub fn with_seed(mut self, seed: u128) -> Self {
         if seed == 0 {
@@ -90,26 +113,191 @@ impl Scene {
         self
     }
 
-    fn choose_light(&self, rng: &mut Pcg64Fast) -> &Light {
-        let sample = Sample::Range(self.total_light_power, 0.0);
-        let threshold = sample.val(rng);
-        let mut sum: f64 = 0.0;
-        for light in &self.lights {
-            sum += light.power.val(rng);
-            if threshold <= sum {
-                return light;
-            }
+    /// Cumulative expected power of each light at the given time, used to pick lights.
+    fn light_power(&self, time: f64) -> Vec<f64> {
+        let mut power = Vec::with_capacity(self.lights.len());
+        self.fill_light_power(time, &mut power);
+        power
+    }
+
+    /// Refills `power` with the cumulative power of each light at `time`, without reallocating.
+    fn fill_light_power(&self, time: f64, power: &mut Vec<f64>) {
+        let mut total = 0.0;
+        power.clear();
+        power.extend(self.lights.iter().map(|l| {
+            total += l.power(time);
+            total
+        }));
+    }
+
+    /// Sets the shutter interval - Chainable varient
+    ///
+    /// Each ray is traced at a random time between `open` and `close`, relative to the
+    /// time being rendered, so anything keyframed to move during that interval is
+    /// motion blurred. By default the shutter is instant and nothing blurs.
+    ///
+    /// # Example
+    /// Blur over the first half of each frame of a 25fps animation:
+    /// ```
+    /// use rustic_zen::prelude::Scene;
+    ///
+    /// let s = Scene::new(1920, 1080).with_shutter(0.0, 0.5 / 25.0);
+    /// ```
+    pub fn with_shutter(mut self, open: f64, close: f64) -> Self {
+        if close < open {
+            panic!("Shutter must open before it closes");
+        }
+        self.shutter = (open, close);
+        self
+    }
+
+    /// Sets how the numbers used to spawn rays are generated - Chainable varient
+    ///
+    /// Low discrepancy sequences spread rays more evenly over the lights, giving
+    /// smoother previews for the same number of rays. Defaults to `Sampling::Random`.
+    ///
+    /// # Example
+    /// ```
+    /// use rustic_zen::prelude::{Sampling, Scene};
+    ///
+    /// let s = Scene::new(1920, 1080).with_sampling(Sampling::Sobol);
+    /// ```
+    pub fn with_sampling(mut self, sampling: Sampling) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Writes the scene in the JSON format read by the original HQZ renderer,
+    /// set to trace `rays` rays at `exposure`.
+    ///
+    /// Only `Light`s, `Line`s, `Curve`s and `HQZLegacy` materials can be
+    /// represented, everything else is left out or approximated and listed
+    /// in the export's `unsupported` descriptions.
+    ///
+    /// # Example
+    /// ```
+    /// use rustic_zen::prelude::{HQZLegacy, Line, Sample, Scene};
+    ///
+    /// let export = Scene::new(1920, 1080)
+    ///     .with_object(Line {
+    ///         x0: Sample::Constant(0.0),
+    ///         y0: Sample::Constant(1000.0),
+    ///         dx: Sample::Constant(1920.0),
+    ///         dy: Sample::Range(10.0, -10.0),
+    ///         material: Box::new(HQZLegacy::new(0.0, 1.0, 0.0)),
+    ///     })
+    ///     .to_hqz(1_000_000, 0.5);
+    /// assert!(export.unsupported.is_empty());
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn to_hqz(&self, rays: usize, exposure: f64) -> HqzExport {
+        let mut writer = HqzWriter::new((self.resolution_x, self.resolution_y), &self.viewport, rays, exposure);
+        for (i, l) in self.lights.iter().enumerate() {
+            writer.light(i, l.as_ref());
+        }
+        for (i, o) in self.objects.iter().enumerate() {
+            writer.object(i, o.as_ref());
+        }
+        if !self.media.is_empty() {
+            writer.unsupported(format!("{} media left out, HQZ has no participating media", self.media.len()));
+        }
+        if self.shutter.0 != self.shutter.1 {
+            writer.unsupported("Shutter interval left out, HQZ has no motion blur".to_string());
+        }
+        writer.finish()
+    }
+
+    /// Describes the scene as a `SceneFile`, so it can be saved as JSON.
+    ///
+    /// Custom lights and objects can't be saved, and custom materials only if
+    /// they were made by a `MaterialRegistry`, which the file refers to them by.
+    /// Anything else is reported as `SceneFileError::Unsupported`.
+    ///
+    /// # Example
+    /// ```
+    /// use rustic_zen::prelude::{Lambertian, Line, Sample, Scene};
+    ///
+    /// let json = Scene::new(1920, 1080)
+    ///     .with_object(Line {
+    ///         x0: Sample::Constant(0.0),
+    ///         y0: Sample::Constant(1000.0),
+    ///         dx: Sample::Constant(1920.0),
+    ///         dy: Sample::Range(10.0, -10.0),
+    ///         material: Box::new(Lambertian::new(0.5)),
+    ///     })
+    ///     .to_scene_file()
+    ///     .unwrap()
+    ///     .to_json();
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn to_scene_file(&self) -> Result<SceneFile, SceneFileError> {
+        let mut writer = SceneFileWriter::new(
+            (self.resolution_x, self.resolution_y),
+            &self.viewport,
+            self.seed,
+            self.shutter,
+            self.sampling,
+        );
+        for (i, l) in self.lights.iter().enumerate() {
+            writer.light(i, l.as_ref())?;
+        }
+        for (i, o) in self.objects.iter().enumerate() {
+            writer.object(i, o.as_ref())?;
+        }
+        for m in self.media.iter() {
+            writer.medium(m);
+        }
+        Ok(writer.finish())
+    }
+
+    /// Picks a time within the shutter interval for a ray.
+    fn sample_time(&self, time: f64, u: f64) -> f64 {
+        let (open, close) = self.shutter;
+        if open == close {
+            return time + open;
         }
-        return self.lights.last().expect("Scene has no lights");
+        time + Sample::Range(close, open).val_from(time, u)
     }
 
-    fn trace_ray(&self, img: &mut Image, rng: &mut Pcg64Fast) {
-        let l = self.choose_light(rng);
-        let mut ray = Some(Ray::new(l, rng));
+    fn choose_light(&self, light_power: &[f64], u: f64) -> &dyn Emitter {
+        let total = light_power.last().cloned().unwrap_or(0.0);
+        let sample = Sample::Range(total, 0.0);
+        let threshold = sample.val_from(0.0, u);
+        let i = light_power.partition_point(|p| *p <= threshold);
+        self.lights
+            .get(i)
+            .or_else(|| self.lights.last())
+            .expect("Scene has no lights")
+            .as_ref()
+    }
+
+    fn trace_ray(
+        &self,
+        img: &mut Image,
+        bvh: &Bvh,
+        light_power: &mut Vec<f64>,
+        time: f64,
+        sequence: &mut Sequence,
+        rng: &mut Pcg64Fast,
+    ) {
+        let u = sequence.next(rng);
+        let time = self.sample_time(time, u[TIME]);
+        // Light power can change while the shutter is open, pick from the power at this ray's time.
+        if self.shutter.0 != self.shutter.1 {
+            self.fill_light_power(time, light_power);
+        }
+        let l = self.choose_light(light_power, u[LIGHT]);
+        let mut ray = Ray::new(l, time, &u, rng);
+        // The image is normalised by the power at mid-shutter, so rays from brighter
+        // moments of the shutter carry more energy and dimmer ones less.
+        if self.shutter.0 != self.shutter.1 && img.light_power() > 0.0 {
+            ray.scale_energy(light_power.last().cloned().unwrap_or(0.0) / img.light_power());
+        }
+        let mut ray = Some(ray);
         while ray.is_some() {
             ray = ray
                 .unwrap()
-                .collision_list(&self.objects, self.viewport, img);
+                .collision_list(&self.objects, bvh, &self.media, self.viewport, img);
         }
     }
 
@@ -118,30 +306,86 @@ impl Scene {
     /// Naturally this call is very expensive. It also consumes the Renderer
     /// and returns an Image class containing the rendered image data.
     pub fn render(self, rays: usize) -> Image {
+        self.render_at(0.0, rays)
+    }
+
+    /// Renders the scene as it is at the given time.
+    ///
+    /// Every keyframed `Sample` in the scene is evaluated at `time`, or at times
+    /// spread across the shutter interval if one is set with `with_shutter()`.
+    /// The scene is not consumed so it can be rendered again at another time.
+    pub fn render_at(&self, time: f64, rays: usize) -> Image {
+        let (open, close) = self.shutter;
+        let mut light_power = self.light_power(time + (open + close) / 2.0);
+        let total_light_power = light_power.last().cloned().unwrap_or(0.0);
         let mut rng = Pcg64Fast::from_seed(PcgSeeder::seed(self.seed));
-        let mut image = Image::new(self.resolution_x, self.resolution_y, self.total_light_power);
+        let mut sequence = Sequence::new(self.sampling, rays, &mut rng);
+        let mut image = Image::new(self.resolution_x, self.resolution_y, total_light_power);
+        let bvh = Bvh::new(&self.objects);
         for _i in 0..rays {
-            self.trace_ray(&mut image, &mut rng);
+            self.trace_ray(&mut image, &bvh, &mut light_power, time, &mut sequence, &mut rng);
         }
 
         // return rendered image.
         image
     }
+
+    /// Renders a numbered sequence of frames, frame `n` is rendered at time `n / frame_rate`.
+    ///
+    /// Every frame is rendered with the scene seed, so parts of the scene that
+    /// do not move keep the same noise from frame to frame instead of flickering.
+    /// Each finished frame is handed to `output` along with its number,
+    /// which is where it should be encoded and saved.
+    ///
+    /// # Example
+    /// ```
+    /// use rustic_zen::prelude::*;
+    /// use rustic_zen::prelude::Interpolation::Linear;
+    ///
+    /// let l = Light {
+    ///     power: Sample::Constant(1.0),
+    ///     x: Sample::keyframes(&[
+    ///         Keyframe { time: 0.0, value: 10.0, interpolation: Linear },
+    ///         Keyframe { time: 1.0, value: 90.0, interpolation: Linear },
+    ///     ]),
+    ///     y: Sample::Constant(50.0),
+    ///     polar_angle: Sample::Constant(0.0),
+    ///     polar_distance: Sample::Constant(0.0),
+    ///     ray_angle: Sample::Range(360.0, 0.0),
+    ///     wavelength: Sample::Blackbody(5800.0),
+    /// };
+    ///
+    /// let s = Scene::new(100, 100).with_light(l);
+    /// s.render_frames(0..4, 4.0, 100, |n, image| {
+    ///     let _data = image.to_rgb8(0.5, 1.0);
+    ///     // save _data as frame n here.
+    /// });
+    /// ```
+    pub fn render_frames<F>(&self, frames: Range<usize>, frame_rate: f64, rays: usize, mut output: F)
+    where
+        F: FnMut(usize, Image),
+    {
+        for n in frames {
+            let image = self.render_at(n as f64 / frame_rate, rays);
+            output(n, image);
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::Scene;
     use material::HQZLegacy;
-    use object::Object;
+    use object::Line;
     use sampler::Sample;
-    use scene::Light;
+    use light::Light;
+    use sequence::Sampling;
 
     #[test]
     fn nrt_works() {
         let m = Box::new(HQZLegacy::new(0.3, 0.3, 0.3));
 
-        let obj = Object::Line {
+        let obj = Line {
             x0: Sample::Constant(0.0),
             y0: Sample::Constant(0.0),
             dx: Sample::Constant(10.0),
@@ -170,4 +414,230 @@ mod tests {
     fn seed_eq_zero() {
         let _r = Scene::new(1920, 1080).with_seed(0);
     }
+
+    #[test]
+    fn lights_weighted_by_expected_power() {
+        use pcg_rand::Pcg64Fast;
+        use rand::prelude::*;
+
+        let light = |power| Light {
+            power,
+            x: Sample::Constant(10.0),
+            y: Sample::Constant(10.0),
+            polar_angle: Sample::Constant(0.0),
+            polar_distance: Sample::Constant(0.0),
+            ray_angle: Sample::Range(360.0, 0.0),
+            wavelength: Sample::Blackbody(5800.0),
+        };
+
+        // Expected powers of 2.0, 1.0 and 0.0
+        let r = Scene::new(1920, 1080)
+            .with_light(light(Sample::Range(3.0, 1.0)))
+            .with_light(light(Sample::Constant(1.0)))
+            .with_light(light(Sample::Constant(0.0)));
+        let power = r.light_power(0.0);
+        assert_eq!(power, vec![2.0, 3.0, 3.0]);
+
+        let mut rng = Pcg64Fast::from_entropy();
+        let n = 100_000;
+        let mut counts = [0; 3];
+        for _ in 0..n {
+            let l = r.choose_light(&power, rng.gen_range(0.0, 1.0));
+            let i = r.lights.iter().position(|x| std::ptr::addr_eq(x.as_ref(), l)).unwrap();
+            counts[i] += 1;
+        }
+        let first = counts[0] as f64 / n as f64;
+        assert!((first - 2.0 / 3.0).abs() < 0.01);
+        assert_eq!(counts[2], 0);
+    }
+
+    #[test]
+    fn keyframed_power() {
+        use animation::{Interpolation, Keyframe};
+
+        let power = Sample::keyframes(&[
+            Keyframe {
+                time: 0.0,
+                value: 0.0,
+                interpolation: Interpolation::Linear,
+            },
+            Keyframe {
+                time: 1.0,
+                value: 4.0,
+                interpolation: Interpolation::Linear,
+            },
+        ]);
+        let l = Light {
+            power,
+            x: Sample::Constant(10.0),
+            y: Sample::Constant(10.0),
+            polar_angle: Sample::Constant(0.0),
+            polar_distance: Sample::Constant(0.0),
+            ray_angle: Sample::Range(360.0, 0.0),
+            wavelength: Sample::Blackbody(5800.0),
+        };
+
+        let r = Scene::new(100, 100).with_light(l);
+        assert_eq!(r.light_power(0.5), vec![2.0]);
+
+        let mut frames = vec![];
+        r.render_frames(1..3, 2.0, 10, |n, _| frames.push(n));
+        assert_eq!(frames, vec![1, 2]);
+    }
+
+    #[test]
+    fn shutter_spreads_time() {
+        use pcg_rand::Pcg64Fast;
+        use rand::prelude::*;
+
+        let mut rng = Pcg64Fast::from_entropy();
+        let r = Scene::new(100, 100);
+        assert_eq!(r.sample_time(2.0, rng.gen_range(0.0, 1.0)), 2.0);
+
+        let r = r.with_shutter(0.0, 0.5);
+        let mut lowest = f64::MAX;
+        let mut highest = f64::MIN;
+        for _ in 0..1000 {
+            let t = r.sample_time(2.0, rng.gen_range(0.0, 1.0));
+            assert!(t >= 2.0);
+            assert!(t <= 2.5);
+            lowest = lowest.min(t);
+            highest = highest.max(t);
+        }
+        assert!(highest - lowest > 0.4);
+    }
+
+    #[test]
+    fn moving_light_smears() {
+        use animation::{Interpolation, Keyframe};
+
+        let x = Sample::keyframes(&[
+            Keyframe {
+                time: 0.0,
+                value: 10.0,
+                interpolation: Interpolation::Linear,
+            },
+            Keyframe {
+                time: 1.0,
+                value: 90.0,
+                interpolation: Interpolation::Linear,
+            },
+        ]);
+        let l = Light {
+            power: Sample::Constant(1.0),
+            x,
+            y: Sample::Constant(50.0),
+            polar_angle: Sample::Constant(0.0),
+            polar_distance: Sample::Constant(0.0),
+            ray_angle: Sample::Range(360.0, 0.0),
+            wavelength: Sample::Blackbody(5800.0),
+        };
+
+        // Brightness around a point, from the green channel.
+        let around = |rgb: &[f32], x: usize, y: usize| -> f32 {
+            let mut sum = 0.0;
+            for py in y - 2..=y + 2 {
+                for px in x - 2..=x + 2 {
+                    sum += rgb[(py * 100 + px) * 3 + 1];
+                }
+            }
+            sum
+        };
+
+        let still = Scene::new(100, 100).with_light(l.clone()).render_at(0.0, 20_000).to_rgb_f32(0.5);
+        assert!(around(&still, 90, 50) < around(&still, 10, 50) / 20.0);
+
+        // Open for the whole move, the light smears from one end to the other.
+        let blurred = Scene::new(100, 100)
+            .with_light(l)
+            .with_shutter(0.0, 1.0)
+            .render_at(0.0, 20_000)
+            .to_rgb_f32(0.5);
+        let (start, end) = (around(&blurred, 10, 50), around(&blurred, 90, 50));
+        assert!(start > 0.0 && end > 0.0);
+        assert!(end > start / 3.0 && start > end / 3.0, "start {} end {}", start, end);
+    }
+
+    #[test]
+    fn shutter_follows_keyframed_power() {
+        use animation::{Interpolation, Keyframe};
+
+        let key = |time, value| Keyframe {
+            time,
+            value,
+            interpolation: Interpolation::Linear,
+        };
+        let light = |power| Light {
+            power,
+            x: Sample::Constant(50.0),
+            y: Sample::Constant(50.0),
+            polar_angle: Sample::Constant(0.0),
+            polar_distance: Sample::Constant(0.0),
+            ray_angle: Sample::Range(360.0, 0.0),
+            wavelength: Sample::Constant(560.0),
+        };
+        let brightness = |s: Scene| -> f64 {
+            s.render_at(0.0, 20_000).to_rgb_f32(0.5).iter().map(|c| *c as f64).sum()
+        };
+
+        // Power is 1.0 at mid-shutter, but averages 1.5 while the shutter is open.
+        let power = Sample::keyframes(&[key(0.0, 1.0), key(0.5, 1.0), key(1.0, 3.0)]);
+        let blurred = brightness(Scene::new(100, 100).with_light(light(power)).with_shutter(0.0, 1.0));
+        let still = brightness(Scene::new(100, 100).with_light(light(Sample::Constant(1.0))));
+        let ratio = blurred / still;
+        assert!((ratio - 1.5).abs() < 0.1, "ratio {}", ratio);
+    }
+
+    #[test]
+    #[should_panic]
+    fn shutter_backwards() {
+        let _r = Scene::new(1920, 1080).with_shutter(1.0, 0.0);
+    }
+
+    /// Renders a point light with `rays` rays, normalised per ray.
+    fn render_light(sampling: Sampling, rays: usize, seed: u128) -> Vec<f64> {
+        let l = Light {
+            power: Sample::Constant(1.0),
+            x: Sample::Constant(32.0),
+            y: Sample::Constant(32.0),
+            polar_angle: Sample::Constant(0.0),
+            polar_distance: Sample::Constant(0.0),
+            ray_angle: Sample::Range(360.0, 0.0),
+            wavelength: Sample::Constant(560.0),
+        };
+        Scene::new(64, 64)
+            .with_light(l)
+            .with_seed(seed)
+            .with_sampling(sampling)
+            .render(rays)
+            .to_rgb_f32(0.5)
+            .iter()
+            .skip(1)
+            .step_by(3)
+            .map(|g| *g as f64)
+            .collect()
+    }
+
+    #[test]
+    fn low_discrepancy_previews_converge_faster() {
+        let reference = render_light(Sampling::Sobol, 200_000, 1);
+        let error = |sampling| {
+            let mut sum = 0.0;
+            for seed in 2..6 {
+                let img = render_light(sampling, 2_000, seed);
+                sum += img
+                    .iter()
+                    .zip(reference.iter())
+                    .map(|(a, b)| (a - b) * (a - b))
+                    .sum::<f64>();
+            }
+            sum
+        };
+
+        let random = error(Sampling::Random);
+        for s in &[Sampling::Stratified, Sampling::Halton, Sampling::Sobol, Sampling::R2] {
+            let e = error(*s);
+            assert!(e < random / 10.0, "{:?} error {} vs random {}", s, e, random);
+        }
+    }
 }
@@ -0,0 +1,1484 @@
+//! Native scene file format, enabled by the `serde` feature.
+//!
+//! A `SceneFile` describes a scene as plain data, so it can be saved and loaded
+//! as JSON and built into a `Scene` to render. Materials are defined once by
+//! name and objects refer to them, as in HQZ scenes.
+//!
+//! # Format
+//! ```json
+//! {
+//!   "resolution": [1920, 1080],
+//!   "viewport": [{ "x": -100.0, "y": -100.0 }, { "x": 2020.0, "y": 1180.0 }],
+//!   "seed": 1234,
+//!   "materials": {
+//!     "mirror": { "type": "HQZLegacy", "diffuse": 0.0, "reflective": 1.0, "transmissive": 0.0 },
+//!     "glass": { "type": "Dielectric", "index": 1.5046, "dispersion": 0.0042 }
+//!   },
+//!   "lights": [
+//!     { "type": "Light", "power": 1.0, "x": 960.0, "y": 540.0, "polar_angle": 0.0,
+//!       "polar_distance": 0.0, "ray_angle": { "Range": [360.0, 0.0] }, "wavelength": { "Blackbody": 5800.0 } }
+//!   ],
+//!   "objects": [
+//!     { "type": "Line", "x0": 0.0, "y0": 900.0, "dx": 1920.0, "dy": 0.0, "material": "mirror" },
+//!     { "type": "Solid", "region": { "Circle": { "center": { "x": 960.0, "y": 300.0 }, "radius": 100.0 } },
+//!       "material": "glass" }
+//!   ]
+//! }
+//! ```
+//!
+//! - `resolution` is required, everything else is optional.
+//! - `viewport` is the top left and bottom right corner of the area rays are traced in.
+//! - `shutter` is the `[open, close]` interval and `sampling` the name of a `Sampling`.
+//! - `Sample`s are written as a bare number for a constant, or as the name of the
+//!   variant holding its parameters, such as `{ "Normal": [500.0, 20.0] }`.
+//!   `Discrete` and `Piecewise` samples hold their `[x, weight]` points, and
+//!   `Keyframes` a list of `{ "time", "value", "interpolation" }` keyframes.
+//! - Lights are a `Light`, `Beam`, `Spotlight`, `ProfileLight` or `AreaLight`, objects a
+//!   `Line`, `Curve`, `Solid`, `Group` or `Instance`, with the same parameters as their
+//!   constructors. Transforms are written as their `matrix` and `offset`.
+//! - Materials are `HQZLegacy`, `Lambertian`, `Glossy`, `Dielectric`, `ThinFilm`,
+//!   `Grating`, `Filter`, `Dichroic`, `Measured`, `Mix`, `TwoSided`, `Layered`, `Blend`
+//!   or `AlphaSelect`. A `Named` material, or a name used by an object that isn't in
+//!   `materials`, is looked up in the `MaterialRegistry`.
+//! - `shapes` are lists of objects by name, placed by `Instance`s, and `media` the
+//!   scene's participating media.
+//!
+//! Parameters are checked as the scene is built, the same as the constructors,
+//! and invalid values are reported as a `SceneFileError`.
+//!
+//! Scenes are saved with `Scene::to_scene_file()`. Custom objects and lights can't
+//! be saved, and custom materials only if they were made by a `MaterialRegistry`.
+
+use geom::{Point, Rect, Transform};
+use group::{Group, Transformed};
+use instance::{Instance, Shape};
+use light::{AreaLight, Beam, Emitter, Light, ProfileLight, Spotlight};
+use material::{
+    AlphaSelect, Blend, Dichroic, Dielectric, Filter, Glossy, Grating, HQZLegacy, Lambertian, Layered,
+    Material, Measured, Mix, Outcome, Passband, Texture, ThinFilm, TwoSided,
+};
+use geom::Vector;
+use medium::{Medium, Phase};
+use object::{Curve, Line, Object, Solid};
+use pcg_rand::Pcg64Fast;
+use polyline::Polyline;
+use region::Region;
+use sampler::Sample;
+use scene::Scene;
+use sequence::Sampling;
+use serde::{Deserialize, Serialize};
+use serde_json;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+/// Deepest materials can be nested, which also stops named materials referring to each other forever.
+const MAX_MATERIAL_DEPTH: usize = 32;
+
+/// Description of a `Scene` which can be saved and loaded.
+///
+/// # Example
+/// ```
+/// use rustic_zen::{MaterialRegistry, SceneFile};
+///
+/// let file = SceneFile::from_json(r#"{
+///     "resolution": [100, 100],
+///     "lights": [{ "type": "Beam", "x": 10.0, "y": 50.0, "direction": 0.0,
+///                  "width": 10.0, "wavelength": 550.0 }],
+///     "objects": [{ "type": "Line", "x0": 90.0, "y0": 0.0, "dx": 0.0, "dy": 100.0,
+///                   "material": "matte" }],
+///     "materials": { "matte": { "type": "Lambertian", "albedo": 0.8 } }
+/// }"#).unwrap();
+///
+/// let image = file.to_scene(&MaterialRegistry::new()).unwrap().render(100);
+/// let saved = file.to_json();
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneFile {
+    /// Width and height of the rendered image.
+    pub resolution: (usize, usize),
+    /// Top left and bottom right corners of the area rays are traced in,
+    /// defaults to the image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub viewport: Option<(Point, Point)>,
+    /// Seed for the scene random number generator.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u128>,
+    /// Open and close time of the shutter, see `Scene::with_shutter()`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shutter: Option<(f64, f64)>,
+    /// How rays are spawned from the lights, see `Scene::with_sampling()`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<Sampling>,
+    /// Materials by name.
+    #[serde(default)]
+    pub materials: BTreeMap<String, MaterialDesc>,
+    /// Shapes by name, placed by `ObjectDesc::Instance`s.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub shapes: BTreeMap<String, Vec<ObjectDesc>>,
+    /// Lights in the scene.
+    #[serde(default)]
+    pub lights: Vec<LightDesc>,
+    /// Objects in the scene.
+    #[serde(default)]
+    pub objects: Vec<ObjectDesc>,
+    /// Participating media in the scene.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub media: Vec<MediumDesc>,
+}
+
+/// A light in a `SceneFile`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[allow(missing_docs)]
+pub enum LightDesc {
+    /// See `Light`.
+    Light(Light),
+    /// See `Beam::new()`.
+    Beam {
+        x: Sample,
+        y: Sample,
+        direction: Sample,
+        width: f64,
+        wavelength: Sample,
+        #[serde(default = "unit_power")]
+        power: Sample,
+    },
+    /// See `Spotlight::new()`.
+    Spotlight {
+        x: Sample,
+        y: Sample,
+        direction: Sample,
+        inner: f64,
+        outer: f64,
+        wavelength: Sample,
+        #[serde(default = "unit_power")]
+        power: Sample,
+    },
+    /// See `ProfileLight::new()`.
+    ProfileLight {
+        x: Sample,
+        y: Sample,
+        direction: Sample,
+        profile: Vec<(f64, f64)>,
+        wavelength: Sample,
+        #[serde(default = "unit_power")]
+        power: Sample,
+    },
+    /// See `AreaLight::new()`.
+    AreaLight {
+        object: Box<ObjectDesc>,
+        power: Sample,
+        wavelength: Sample,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        profile: Option<Sample>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ray_angle: Option<Sample>,
+        #[serde(default)]
+        one_sided: bool,
+    },
+}
+
+fn unit_power() -> Sample {
+    Sample::Constant(1.0)
+}
+
+/// An object in a `SceneFile`, with the name of its material.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[allow(missing_docs)]
+pub enum ObjectDesc {
+    /// See `Line`.
+    Line {
+        x0: Sample,
+        y0: Sample,
+        dx: Sample,
+        dy: Sample,
+        material: String,
+    },
+    /// See `Curve`.
+    Curve {
+        x0: Sample,
+        y0: Sample,
+        a0: Sample,
+        dx: Sample,
+        dy: Sample,
+        da: Sample,
+        material: String,
+    },
+    /// See `Solid`.
+    Solid { region: Region, material: String },
+    /// See `Group`, its lights are added to the scene.
+    Group {
+        transform: Transform,
+        #[serde(default)]
+        objects: Vec<ObjectDesc>,
+        #[serde(default)]
+        lights: Vec<LightDesc>,
+    },
+    /// See `Instance::new()`, placing one of the file's `shapes`.
+    Instance {
+        shape: String,
+        transform: Transform,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        material: Option<String>,
+    },
+}
+
+/// A material in a `SceneFile`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[allow(missing_docs)]
+pub enum MaterialDesc {
+    /// See `HQZLegacy::new()`.
+    HQZLegacy {
+        diffuse: f64,
+        reflective: f64,
+        transmissive: f64,
+        #[serde(default)]
+        lambertian: bool,
+    },
+    /// See `Lambertian::new()`.
+    Lambertian { albedo: f64 },
+    /// See `Glossy::new()`.
+    Glossy {
+        reflectance: f64,
+        exponent: f64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        wavelength_dependence: Option<(f64, f64)>,
+    },
+    /// See `Dielectric::new()`.
+    Dielectric {
+        index: f64,
+        #[serde(default)]
+        dispersion: f64,
+    },
+    /// See `ThinFilm::new()`.
+    ThinFilm {
+        thickness: f64,
+        film_index: f64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        surrounding_index: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        thickness_profile: Option<Vec<(f64, f64)>>,
+    },
+    /// See `Grating::new()`.
+    Grating {
+        spacing: f64,
+        #[serde(default)]
+        transmissive: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        orders: Option<Vec<(i32, f64)>>,
+    },
+    /// See `Filter::new()`.
+    Filter {
+        passband: PassbandDesc,
+        #[serde(default)]
+        edge_width: f64,
+    },
+    /// See `Dichroic::new()`.
+    Dichroic {
+        passband: PassbandDesc,
+        #[serde(default)]
+        edge_width: f64,
+    },
+    /// See `Measured::new()`.
+    Measured {
+        transmittance: Vec<(f64, f64)>,
+        reflectance: Vec<(f64, f64)>,
+    },
+    /// See `Mix::new()`.
+    Mix { materials: Vec<(f64, MaterialDesc)> },
+    /// See `TwoSided::new()`.
+    TwoSided {
+        front: Box<MaterialDesc>,
+        back: Box<MaterialDesc>,
+    },
+    /// See `Layered::new()`.
+    Layered {
+        coat: Box<MaterialDesc>,
+        base: Box<MaterialDesc>,
+    },
+    /// See `Blend::new()`.
+    Blend {
+        a: Box<MaterialDesc>,
+        b: Box<MaterialDesc>,
+        texture: TextureDesc,
+    },
+    /// See `AlphaSelect::new()`, each range is `[start, end, material]`.
+    AlphaSelect {
+        default: Box<MaterialDesc>,
+        #[serde(default)]
+        ranges: Vec<(f64, f64, MaterialDesc)>,
+    },
+    /// Another material in the file, or one from the `MaterialRegistry`.
+    Named { name: String },
+}
+
+/// A `Passband` in a `SceneFile`, tabulated passbands hold their `[wavelength, transmittance]` points.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[allow(missing_docs)]
+pub enum PassbandDesc {
+    BandPass(f64, f64),
+    LongPass(f64),
+    ShortPass(f64),
+    Tabulated(Vec<(f64, f64)>),
+}
+
+/// A `Texture` in a `SceneFile`, stripes and gradients hold their `[alpha, value]` points.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[allow(missing_docs)]
+pub enum TextureDesc {
+    Constant(f64),
+    Stripes(Vec<(f64, f64)>),
+    Gradient(Vec<(f64, f64)>),
+    Periodic { texture: Box<TextureDesc>, period: f64 },
+}
+
+/// A participating medium in a `SceneFile`, see `Medium::new()`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MediumDesc {
+    /// Area filled by the medium.
+    pub region: Region,
+    /// Chance of scattering per pixel travelled.
+    pub density: f64,
+    /// Probability a ray survives scattering, see `Medium::with_albedo()`.
+    #[serde(default = "unit_albedo")]
+    pub albedo: f64,
+    /// Phase function used when rays scatter, see `Medium::with_phase()`.
+    #[serde(default = "isotropic")]
+    pub phase: Phase,
+}
+
+fn unit_albedo() -> f64 {
+    1.0
+}
+
+fn isotropic() -> Phase {
+    Phase::Isotropic
+}
+
+/// Custom materials which scene files can refer to by name.
+///
+/// Materials made by the registry remember their name, so scenes using them
+/// can be saved with `Scene::to_scene_file()`.
+///
+/// # Example
+/// ```
+/// use rustic_zen::prelude::HQZLegacy;
+/// use rustic_zen::MaterialRegistry;
+///
+/// let registry = MaterialRegistry::new()
+///     .with_material("chrome", || Box::new(HQZLegacy::new(0.0, 0.95, 0.0)));
+/// let chrome = registry.material("chrome").unwrap();
+/// ```
+#[derive(Default)]
+pub struct MaterialRegistry {
+    factories: HashMap<String, Box<dyn Fn() -> Box<dyn Material>>>,
+}
+
+impl MaterialRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Names a material, `factory` makes a new one for each object using it - Chainable varient
+    pub fn with_material<F>(mut self, name: &str, factory: F) -> Self
+    where
+        F: Fn() -> Box<dyn Material> + 'static,
+    {
+        self.factories.insert(name.to_string(), Box::new(factory));
+        self
+    }
+
+    /// Makes the material called `name`, or `None` if it isn't registered.
+    pub fn material(&self, name: &str) -> Option<Box<dyn Material>> {
+        self.factories.get(name).map(|f| {
+            Box::new(Registered {
+                name: name.to_string(),
+                material: f(),
+            }) as Box<dyn Material>
+        })
+    }
+}
+
+/// A material made by a `MaterialRegistry`, which is saved by name.
+struct Registered {
+    name: String,
+    material: Box<dyn Material>,
+}
+
+impl Material for Registered {
+    fn outcome(
+        &self,
+        direction: &Vector,
+        normal: &Vector,
+        wavelength: f64,
+        alpha: f64,
+        rng: &mut Pcg64Fast,
+    ) -> Option<Outcome> {
+        self.material.outcome(direction, normal, wavelength, alpha, rng)
+    }
+}
+
+/// Why a `SceneFile` could not be read, built or written.
+#[derive(Debug)]
+pub enum SceneFileError {
+    /// The file is not valid JSON, or doesn't match the format.
+    Syntax(serde_json::Error),
+    /// A material name is in neither the file nor the registry.
+    UnknownMaterial(String),
+    /// Materials are nested too deeply, usually because named materials refer to each other.
+    MaterialLoop(String),
+    /// A value is out of range, such as a negative refractive index.
+    Invalid(String),
+    /// Part of a scene can't be written to a file, such as a custom light.
+    Unsupported(String),
+}
+
+impl fmt::Display for SceneFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneFileError::Syntax(e) => write!(f, "Invalid scene file: {}", e),
+            SceneFileError::UnknownMaterial(name) => write!(f, "Unknown material \"{}\"", name),
+            SceneFileError::MaterialLoop(name) => {
+                write!(f, "Material \"{}\" is nested too deeply, does it refer to itself?", name)
+            }
+            SceneFileError::Invalid(reason) => write!(f, "Invalid scene: {}", reason),
+            SceneFileError::Unsupported(what) => write!(f, "Can't save scene: {}", what),
+        }
+    }
+}
+
+impl Error for SceneFileError {}
+
+/// Fails with `reason` unless `ok`, for values the constructors would panic on.
+fn check(ok: bool, reason: &str) -> Result<(), SceneFileError> {
+    if ok {
+        Ok(())
+    } else {
+        Err(SceneFileError::Invalid(reason.to_string()))
+    }
+}
+
+/// Checks weights of `what` are not negative and at least one is positive.
+fn check_weights<I: Iterator<Item = f64> + Clone>(weights: I, what: &str) -> Result<(), SceneFileError> {
+    check(weights.clone().all(|w| w >= 0.0), &format!("{} weights must not be negative", what))?;
+    check(weights.sum::<f64>() > 0.0, &format!("{} needs at least one positive weight", what))
+}
+
+/// Checks points of a curve of `what` can be built into a `Polyline`, with values within 0.0 to 1.0.
+fn check_fractions(points: &[(f64, f64)], what: &str) -> Result<(), SceneFileError> {
+    check(!points.is_empty(), &format!("{} needs at least one point", what))?;
+    check(points.iter().all(|p| !p.0.is_nan()), &format!("{} point is NaN", what))?;
+    check(
+        points.iter().all(|p| (0.0..=1.0).contains(&p.1)),
+        &format!("{} values must be between 0.0 and 1.0", what),
+    )
+}
+
+fn check_transform(transform: &Transform) -> Result<(), SceneFileError> {
+    check(transform.inverse().is_some(), "Transforms must be invertible")
+}
+
+/// Checks every polygon in `region` has enough corners to enclose something.
+fn check_region(region: &Region) -> Result<(), SceneFileError> {
+    match region {
+        Region::Circle { .. } => Ok(()),
+        Region::Polygon(points) => check(points.len() >= 3, "Polygons need at least three corners"),
+        Region::Union(a, b) | Region::Intersection(a, b) | Region::Difference(a, b) => {
+            check_region(a)?;
+            check_region(b)
+        }
+    }
+}
+
+impl SceneFile {
+    /// Reads a scene file from JSON.
+    pub fn from_json(json: &str) -> Result<Self, SceneFileError> {
+        serde_json::from_str(json).map_err(SceneFileError::Syntax)
+    }
+
+    /// Writes the scene file as JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Scene files always serialise")
+    }
+
+    /// Builds the scene described, looking up any materials not in the file in `registry`.
+    pub fn to_scene(&self, registry: &MaterialRegistry) -> Result<Scene, SceneFileError> {
+        check(self.resolution.0 > 0 && self.resolution.1 > 0, "Resolution must not be zero")?;
+        let mut scene = Scene::new(self.resolution.0, self.resolution.1);
+        if let Some((top_left, bottom_right)) = self.viewport {
+            scene = scene.with_viewport(Rect::from_points(&top_left, &bottom_right));
+        }
+        if let Some(seed) = self.seed {
+            check(seed != 0, "Seed must not be 0")?;
+            scene = scene.with_seed(seed);
+        }
+        if let Some((open, close)) = self.shutter {
+            check(open <= close, "Shutter must open before it closes")?;
+            scene = scene.with_shutter(open, close);
+        }
+        if let Some(sampling) = self.sampling {
+            scene = scene.with_sampling(sampling);
+        }
+
+        let mut loader = Loader {
+            file: self,
+            registry,
+            shapes: HashMap::new(),
+        };
+        for l in self.lights.iter() {
+            scene = scene.with_boxed_light(loader.light(l)?);
+        }
+        for o in self.objects.iter() {
+            scene = match o {
+                ObjectDesc::Group { transform, objects, lights } => {
+                    scene.with_group(loader.group(transform, objects, lights, 0)?)
+                }
+                _ => scene.with_boxed_object(loader.object(o, 0)?),
+            };
+        }
+        for m in self.media.iter() {
+            check_region(&m.region)?;
+            check(m.density >= 0.0, "Medium density must not be negative")?;
+            check((0.0..=1.0).contains(&m.albedo), "Medium albedo must be between 0.0 and 1.0")?;
+            scene = scene.with_medium(
+                Medium::new(m.region.clone(), m.density)
+                    .with_albedo(m.albedo)
+                    .with_phase(m.phase),
+            );
+        }
+        Ok(scene)
+    }
+
+    /// Material called `name`, from the file if it's defined there, otherwise the registry.
+    fn material(&self, name: &str, registry: &MaterialRegistry, depth: usize) -> Result<Box<dyn Material>, SceneFileError> {
+        if depth > MAX_MATERIAL_DEPTH {
+            return Err(SceneFileError::MaterialLoop(name.to_string()));
+        }
+        match self.materials.get(name) {
+            Some(desc) => self.build_material(desc, registry, depth + 1),
+            None => registry
+                .material(name)
+                .ok_or_else(|| SceneFileError::UnknownMaterial(name.to_string())),
+        }
+    }
+
+    fn build_material(&self, desc: &MaterialDesc, registry: &MaterialRegistry, depth: usize) -> Result<Box<dyn Material>, SceneFileError> {
+        if depth > MAX_MATERIAL_DEPTH {
+            return Err(SceneFileError::MaterialLoop(format!("{:?}", desc)));
+        }
+        let build = |d: &MaterialDesc| self.build_material(d, registry, depth + 1);
+        Ok(match desc {
+            MaterialDesc::HQZLegacy { diffuse, reflective, transmissive, lambertian } => {
+                check(
+                    diffuse + reflective + transmissive <= 1.0,
+                    "HQZLegacy coefficients must not add up to more than 1.0",
+                )?;
+                let m = HQZLegacy::new(*diffuse, *reflective, *transmissive);
+                if *lambertian {
+                    Box::new(m.with_lambertian_diffuse())
+                } else {
+                    Box::new(m)
+                }
+            }
+            MaterialDesc::Lambertian { albedo } => {
+                check((0.0..=1.0).contains(albedo), "Lambertian albedo must be between 0.0 and 1.0")?;
+                Box::new(Lambertian::new(*albedo))
+            }
+            MaterialDesc::Glossy { reflectance, exponent, wavelength_dependence } => {
+                check((0.0..=1.0).contains(reflectance), "Glossy reflectance must be between 0.0 and 1.0")?;
+                check(*exponent >= 0.0, "Glossy exponent must not be negative")?;
+                let m = Glossy::new(*reflectance, *exponent);
+                match wavelength_dependence {
+                    Some((reference, power)) => Box::new(m.with_wavelength_dependence(*reference, *power)),
+                    None => Box::new(m),
+                }
+            }
+            MaterialDesc::Dielectric { index, dispersion } => {
+                check(*index > 0.0, "Dielectric index must be positive")?;
+                Box::new(Dielectric::new(*index).with_dispersion(*dispersion))
+            }
+            MaterialDesc::ThinFilm { thickness, film_index, surrounding_index, thickness_profile } => {
+                check(*thickness >= 0.0, "ThinFilm thickness must not be negative")?;
+                let mut m = ThinFilm::new(*thickness, *film_index);
+                if let Some(i) = surrounding_index {
+                    m = m.with_surrounding_index(*i);
+                }
+                if let Some(profile) = thickness_profile {
+                    check(!profile.is_empty(), "ThinFilm thickness profile needs at least one point")?;
+                    check(
+                        profile.iter().all(|p| !p.0.is_nan() && p.1 >= 0.0),
+                        "ThinFilm thickness must not be negative",
+                    )?;
+                    m = m.with_thickness_profile(profile);
+                }
+                Box::new(m)
+            }
+            MaterialDesc::Grating { spacing, transmissive, orders } => {
+                check(*spacing > 0.0, "Grating line spacing must be positive")?;
+                let mut m = Grating::new(*spacing);
+                if *transmissive {
+                    m = m.transmissive();
+                }
+                if let Some(orders) = orders {
+                    check_weights(orders.iter().map(|o| o.1), "Grating order")?;
+                    m = m.with_orders(orders);
+                }
+                Box::new(m)
+            }
+            MaterialDesc::Filter { passband, edge_width } => {
+                Box::new(Filter::new(build_passband(passband)?).with_edge_width(*edge_width))
+            }
+            MaterialDesc::Dichroic { passband, edge_width } => {
+                Box::new(Dichroic::new(build_passband(passband)?).with_edge_width(*edge_width))
+            }
+            MaterialDesc::Measured { transmittance, reflectance } => {
+                check_fractions(transmittance, "Measured transmittance")?;
+                check_fractions(reflectance, "Measured reflectance")?;
+                let (t, r) = (Polyline::new(transmittance), Polyline::new(reflectance));
+                check(
+                    transmittance
+                        .iter()
+                        .chain(reflectance.iter())
+                        .all(|p| t.value_at(p.0) + r.value_at(p.0) <= 1.0),
+                    "Measured transmittance and reflectance add up to more than 1.0",
+                )?;
+                Box::new(Measured::new(transmittance, reflectance))
+            }
+            MaterialDesc::Mix { materials } => {
+                check_weights(materials.iter().map(|m| m.0), "Mix")?;
+                Box::new(Mix::new(
+                    materials
+                        .iter()
+                        .map(|(w, d)| Ok((*w, build(d)?)))
+                        .collect::<Result<_, SceneFileError>>()?,
+                ))
+            }
+            MaterialDesc::TwoSided { front, back } => Box::new(TwoSided::new(build(front)?, build(back)?)),
+            MaterialDesc::Layered { coat, base } => Box::new(Layered::new(build(coat)?, build(base)?)),
+            MaterialDesc::Blend { a, b, texture } => Box::new(Blend::new(build(a)?, build(b)?, build_texture(texture)?)),
+            MaterialDesc::AlphaSelect { default, ranges } => {
+                let mut m = AlphaSelect::new(build(default)?);
+                for (start, end, desc) in ranges.iter() {
+                    check(start <= end, "AlphaSelect range must not end before it starts")?;
+                    m = m.with_range(*start, *end, build(desc)?);
+                }
+                Box::new(m)
+            }
+            MaterialDesc::Named { name } => self.material(name, registry, depth)?,
+        })
+    }
+}
+
+fn build_passband(desc: &PassbandDesc) -> Result<Passband, SceneFileError> {
+    Ok(match desc {
+        PassbandDesc::BandPass(lower, upper) => Passband::BandPass(*lower, *upper),
+        PassbandDesc::LongPass(cutoff) => Passband::LongPass(*cutoff),
+        PassbandDesc::ShortPass(cutoff) => Passband::ShortPass(*cutoff),
+        PassbandDesc::Tabulated(points) => {
+            check_fractions(points, "Tabulated passband")?;
+            Passband::tabulated(points)
+        }
+    })
+}
+
+fn build_texture(desc: &TextureDesc) -> Result<Texture, SceneFileError> {
+    Ok(match desc {
+        TextureDesc::Constant(v) => Texture::Constant(*v),
+        TextureDesc::Stripes(points) => {
+            check_fractions(points, "Texture")?;
+            Texture::stripes(points)
+        }
+        TextureDesc::Gradient(points) => {
+            check_fractions(points, "Texture")?;
+            Texture::gradient(points)
+        }
+        TextureDesc::Periodic { texture, period } => {
+            check(*period > 0.0, "Texture period must be positive")?;
+            Texture::periodic(build_texture(texture)?, *period)
+        }
+    })
+}
+
+/// Builds the lights and objects of a `SceneFile`, making each shape once for all its instances.
+struct Loader<'a> {
+    file: &'a SceneFile,
+    registry: &'a MaterialRegistry,
+    shapes: HashMap<String, Rc<dyn Object>>,
+}
+
+impl<'a> Loader<'a> {
+    fn material(&self, name: &str) -> Result<Box<dyn Material>, SceneFileError> {
+        self.file.material(name, self.registry, 0)
+    }
+
+    fn light(&mut self, desc: &LightDesc) -> Result<Box<dyn Emitter>, SceneFileError> {
+        Ok(match desc.clone() {
+            LightDesc::Light(light) => Box::new(light),
+            LightDesc::Beam { x, y, direction, width, wavelength, power } => {
+                check(width >= 0.0, "Beam width must not be negative")?;
+                Box::new(Beam::new(x, y, direction, width, wavelength).with_power(power))
+            }
+            LightDesc::Spotlight { x, y, direction, inner, outer, wavelength, power } => {
+                check(
+                    0.0 <= inner && inner <= outer && outer <= 180.0,
+                    "Spotlight angles must satisfy 0.0 <= inner <= outer <= 180.0",
+                )?;
+                Box::new(Spotlight::new(x, y, direction, inner, outer, wavelength).with_power(power))
+            }
+            LightDesc::ProfileLight { x, y, direction, profile, wavelength, power } => {
+                let light = ProfileLight::try_new(x, y, direction, &profile, wavelength)
+                    .map_err(|e| SceneFileError::Invalid(e.to_string()))?;
+                Box::new(light.with_power(power))
+            }
+            LightDesc::AreaLight { object, power, wavelength, profile, ray_angle, one_sided } => {
+                let object = self.object(&object, 0)?;
+                check(AreaLight::can_emit(object.as_ref()), "AreaLight object has no edge to emit from")?;
+                let mut light = AreaLight::from_boxed(object, power, wavelength);
+                if let Some(profile) = profile {
+                    light = light.with_profile(profile);
+                }
+                if let Some(ray_angle) = ray_angle {
+                    light = light.with_ray_angle(ray_angle);
+                }
+                if one_sided {
+                    light = light.one_sided();
+                }
+                Box::new(light)
+            }
+        })
+    }
+
+    /// Builds any object but a group, which must be added with its lights.
+    ///
+    /// `depth` counts how many shapes the object is nested in.
+    fn object(&mut self, desc: &ObjectDesc, depth: usize) -> Result<Box<dyn Object>, SceneFileError> {
+        Ok(match desc.clone() {
+            ObjectDesc::Line { x0, y0, dx, dy, material } => Box::new(Line {
+                x0,
+                y0,
+                dx,
+                dy,
+                material: self.material(&material)?,
+            }),
+            ObjectDesc::Curve { x0, y0, a0, dx, dy, da, material } => Box::new(Curve {
+                x0,
+                y0,
+                a0,
+                dx,
+                dy,
+                da,
+                material: self.material(&material)?,
+            }),
+            ObjectDesc::Solid { region, material } => {
+                check_region(&region)?;
+                Box::new(Solid {
+                    region,
+                    material: self.material(&material)?,
+                })
+            }
+            ObjectDesc::Group { transform, objects, lights } => {
+                check(lights.is_empty(), "Groups within shapes and area lights can't hold lights")?;
+                Box::new(self.group(&transform, &objects, &[], depth)?)
+            }
+            ObjectDesc::Instance { shape, transform, material } => {
+                check_transform(&transform)?;
+                let mut instance = Instance::new(self.shape(&shape, depth)?, transform);
+                if let Some(material) = material {
+                    instance = instance.with_material(self.material(&material)?);
+                }
+                Box::new(instance)
+            }
+        })
+    }
+
+    fn group(
+        &mut self,
+        transform: &Transform,
+        objects: &[ObjectDesc],
+        lights: &[LightDesc],
+        depth: usize,
+    ) -> Result<Group, SceneFileError> {
+        check_transform(transform)?;
+        let mut group = Group::new(*transform);
+        for l in lights.iter() {
+            group = group.with_boxed_light(self.light(l)?);
+        }
+        for o in objects.iter() {
+            group = match o {
+                ObjectDesc::Group { transform, objects, lights } => {
+                    group.with_group(self.group(transform, objects, lights, depth)?)
+                }
+                _ => group.with_boxed_object(self.object(o, depth)?),
+            };
+        }
+        Ok(group)
+    }
+
+    /// The shape called `name`, built the first time an instance uses it.
+    fn shape(&mut self, name: &str, depth: usize) -> Result<Rc<dyn Object>, SceneFileError> {
+        if let Some(shape) = self.shapes.get(name) {
+            return Ok(shape.clone());
+        }
+        let file = self.file;
+        let objects = file
+            .shapes
+            .get(name)
+            .ok_or_else(|| SceneFileError::Invalid(format!("Unknown shape \"{}\"", name)))?;
+        if depth > MAX_MATERIAL_DEPTH {
+            return Err(SceneFileError::Invalid(format!(
+                "Shape \"{}\" is nested too deeply, does it refer to itself?",
+                name
+            )));
+        }
+        let mut shape = Shape::new();
+        for o in objects.iter() {
+            shape = shape.with_boxed_object(self.object(o, depth + 1)?);
+        }
+        let shape: Rc<dyn Object> = Rc::new(shape);
+        self.shapes.insert(name.to_string(), shape.clone());
+        Ok(shape)
+    }
+}
+
+/// Builds up a `SceneFile` from the parts of a scene, made by `Scene::to_scene_file()`.
+pub(crate) struct SceneFileWriter {
+    file: SceneFile,
+    /// Names of the shapes written so far, by the address of the shared geometry.
+    shape_names: Vec<(*const (), String)>,
+}
+
+impl SceneFileWriter {
+    pub(crate) fn new(
+        resolution: (usize, usize),
+        viewport: &Rect,
+        seed: u128,
+        shutter: (f64, f64),
+        sampling: Sampling,
+    ) -> Self {
+        Self {
+            file: SceneFile {
+                resolution,
+                viewport: Some((viewport.top_left(), viewport.bottom_right())),
+                seed: Some(seed),
+                shutter: Some(shutter).filter(|s| s.0 != s.1),
+                sampling: Some(sampling).filter(|s| *s != Sampling::Random),
+                materials: BTreeMap::new(),
+                shapes: BTreeMap::new(),
+                lights: vec![],
+                objects: vec![],
+                media: vec![],
+            },
+            shape_names: vec![],
+        }
+    }
+
+    pub(crate) fn light(&mut self, index: usize, light: &dyn Emitter) -> Result<(), SceneFileError> {
+        let name = format!("Light {}", index);
+        // Lights of groups are kept apart from the group's objects, and saved as a group of just them.
+        match light.as_any().downcast_ref::<Transformed>() {
+            Some(t) => {
+                let group = self.transformed(&name, t)?;
+                self.file.objects.push(group);
+            }
+            None => {
+                let light = self.light_desc(&name, light)?;
+                self.file.lights.push(light);
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn object(&mut self, index: usize, object: &dyn Object) -> Result<(), SceneFileError> {
+        let object = self.object_desc(&format!("Object {}", index), object)?;
+        self.file.objects.push(object);
+        Ok(())
+    }
+
+    pub(crate) fn medium(&mut self, medium: &Medium) {
+        self.file.media.push(MediumDesc {
+            region: medium.region.clone(),
+            density: medium.density,
+            albedo: medium.albedo,
+            phase: medium.phase,
+        });
+    }
+
+    pub(crate) fn finish(self) -> SceneFile {
+        self.file
+    }
+
+    fn transformed(&mut self, name: &str, t: &Transformed) -> Result<ObjectDesc, SceneFileError> {
+        let (objects, lights) = match t.light.as_ref().as_any().downcast_ref::<Transformed>() {
+            Some(inner) => (vec![self.transformed(name, inner)?], vec![]),
+            None => (vec![], vec![self.light_desc(name, t.light.as_ref())?]),
+        };
+        Ok(ObjectDesc::Group {
+            transform: t.transform,
+            objects,
+            lights,
+        })
+    }
+
+    fn light_desc(&mut self, name: &str, light: &dyn Emitter) -> Result<LightDesc, SceneFileError> {
+        let light = light.as_any();
+        if let Some(l) = light.downcast_ref::<Light>() {
+            return Ok(LightDesc::Light(l.clone()));
+        }
+        if let Some(b) = light.downcast_ref::<Beam>() {
+            return Ok(LightDesc::Beam {
+                x: b.x.clone(),
+                y: b.y.clone(),
+                direction: b.direction.clone(),
+                width: b.width,
+                wavelength: b.wavelength.clone(),
+                power: b.power.clone(),
+            });
+        }
+        if let Some(s) = light.downcast_ref::<Spotlight>() {
+            return Ok(LightDesc::Spotlight {
+                x: s.x.clone(),
+                y: s.y.clone(),
+                direction: s.direction.clone(),
+                inner: s.angles().0,
+                outer: s.angles().1,
+                wavelength: s.wavelength.clone(),
+                power: s.power.clone(),
+            });
+        }
+        if let Some(p) = light.downcast_ref::<ProfileLight>() {
+            return Ok(LightDesc::ProfileLight {
+                x: p.x.clone(),
+                y: p.y.clone(),
+                direction: p.direction.clone(),
+                profile: p.profile().to_vec(),
+                wavelength: p.wavelength.clone(),
+                power: p.power.clone(),
+            });
+        }
+        if let Some(a) = light.downcast_ref::<AreaLight>() {
+            return Ok(LightDesc::AreaLight {
+                object: Box::new(self.object_desc(name, a.object.as_ref())?),
+                power: a.power.clone(),
+                wavelength: a.wavelength.clone(),
+                profile: Some(a.profile.clone()),
+                ray_angle: a.ray_angle.clone(),
+                one_sided: a.one_sided,
+            });
+        }
+        Err(SceneFileError::Unsupported(format!("{} is not a built in light", name)))
+    }
+
+    fn object_desc(&mut self, name: &str, object: &dyn Object) -> Result<ObjectDesc, SceneFileError> {
+        let object = object.as_any();
+        if let Some(l) = object.downcast_ref::<Line>() {
+            return Ok(ObjectDesc::Line {
+                x0: l.x0.clone(),
+                y0: l.y0.clone(),
+                dx: l.dx.clone(),
+                dy: l.dy.clone(),
+                material: self.material_name(name, l.material.as_ref())?,
+            });
+        }
+        if let Some(c) = object.downcast_ref::<Curve>() {
+            return Ok(ObjectDesc::Curve {
+                x0: c.x0.clone(),
+                y0: c.y0.clone(),
+                a0: c.a0.clone(),
+                dx: c.dx.clone(),
+                dy: c.dy.clone(),
+                da: c.da.clone(),
+                material: self.material_name(name, c.material.as_ref())?,
+            });
+        }
+        if let Some(s) = object.downcast_ref::<Solid>() {
+            return Ok(ObjectDesc::Solid {
+                region: s.region.clone(),
+                material: self.material_name(name, s.material.as_ref())?,
+            });
+        }
+        if let Some(g) = object.downcast_ref::<Group>() {
+            return Ok(ObjectDesc::Group {
+                transform: g.transform,
+                objects: self.object_descs(name, &g.objects)?,
+                lights: vec![],
+            });
+        }
+        if let Some(s) = object.downcast_ref::<Shape>() {
+            // A shape placed directly in the scene, without an instance.
+            return Ok(ObjectDesc::Group {
+                transform: Transform::identity(),
+                objects: self.object_descs(name, &s.objects)?,
+                lights: vec![],
+            });
+        }
+        if let Some(i) = object.downcast_ref::<Instance>() {
+            let material = match i.material {
+                Some(ref m) => Some(self.material_name(name, m.as_ref())?),
+                None => None,
+            };
+            return Ok(ObjectDesc::Instance {
+                shape: self.shape_name(name, &i.shape)?,
+                transform: i.transform,
+                material,
+            });
+        }
+        Err(SceneFileError::Unsupported(format!("{} is not a built in object", name)))
+    }
+
+    fn object_descs(&mut self, name: &str, objects: &[Box<dyn Object>]) -> Result<Vec<ObjectDesc>, SceneFileError> {
+        objects.iter().map(|o| self.object_desc(name, o.as_ref())).collect()
+    }
+
+    /// Name of the shared `shape` in the file, writing it the first time it's seen.
+    fn shape_name(&mut self, name: &str, shape: &Rc<dyn Object>) -> Result<String, SceneFileError> {
+        let address = Rc::as_ptr(shape) as *const ();
+        if let Some((_, n)) = self.shape_names.iter().find(|(a, _)| *a == address) {
+            return Ok(n.clone());
+        }
+        let objects = match (**shape).as_any().downcast_ref::<Shape>() {
+            Some(s) => self.object_descs(name, &s.objects)?,
+            None => vec![self.object_desc(name, shape.as_ref())?],
+        };
+        let shape_name = format!("shape{}", self.shape_names.len() + 1);
+        self.file.shapes.insert(shape_name.clone(), objects);
+        self.shape_names.push((address, shape_name.clone()));
+        Ok(shape_name)
+    }
+
+    /// Name of `material` in the file, reusing an identical material if one is already written.
+    fn material_name(&mut self, name: &str, material: &dyn Material) -> Result<String, SceneFileError> {
+        let desc = material_desc(name, material)?;
+        if let MaterialDesc::Named { name } = desc {
+            return Ok(name);
+        }
+        if let Some((n, _)) = self.file.materials.iter().find(|(_, d)| **d == desc) {
+            return Ok(n.clone());
+        }
+        let material_name = format!("material{}", self.file.materials.len() + 1);
+        self.file.materials.insert(material_name.clone(), desc);
+        Ok(material_name)
+    }
+}
+
+/// Description of a built in material, or the name of one made by a `MaterialRegistry`.
+fn material_desc(name: &str, material: &dyn Material) -> Result<MaterialDesc, SceneFileError> {
+    let m = material.as_any();
+    let desc = |m: &dyn Material| material_desc(name, m);
+    // Mix and Grating keep cumulative weights, which are turned back into the weights given.
+    let weights = |cumulative: &mut dyn Iterator<Item = f64>| {
+        let mut last = 0.0;
+        cumulative
+            .map(|c| {
+                let w = c - last;
+                last = c;
+                w
+            })
+            .collect::<Vec<f64>>()
+    };
+    Ok(if let Some(r) = m.downcast_ref::<Registered>() {
+        MaterialDesc::Named { name: r.name.clone() }
+    } else if let Some(h) = m.downcast_ref::<HQZLegacy>() {
+        MaterialDesc::HQZLegacy {
+            diffuse: h.d,
+            reflective: h.r,
+            transmissive: h.t,
+            lambertian: h.lambertian,
+        }
+    } else if let Some(l) = m.downcast_ref::<Lambertian>() {
+        MaterialDesc::Lambertian { albedo: l.albedo }
+    } else if let Some(g) = m.downcast_ref::<Glossy>() {
+        MaterialDesc::Glossy {
+            reflectance: g.reflectance,
+            exponent: g.exponent,
+            wavelength_dependence: g.dispersion,
+        }
+    } else if let Some(d) = m.downcast_ref::<Dielectric>() {
+        MaterialDesc::Dielectric {
+            index: d.index,
+            dispersion: d.dispersion,
+        }
+    } else if let Some(t) = m.downcast_ref::<ThinFilm>() {
+        let points = t.thickness.points();
+        MaterialDesc::ThinFilm {
+            thickness: points[0].1,
+            film_index: t.film_index,
+            surrounding_index: Some(t.surrounding_index).filter(|i| *i != 1.0),
+            thickness_profile: Some(points.to_vec()).filter(|p| p.len() > 1),
+        }
+    } else if let Some(g) = m.downcast_ref::<Grating>() {
+        let w = weights(&mut g.orders.iter().map(|o| o.1));
+        MaterialDesc::Grating {
+            spacing: g.spacing,
+            transmissive: g.transmissive,
+            orders: Some(g.orders.iter().zip(w).map(|(o, w)| (o.0, w)).collect()),
+        }
+    } else if let Some(f) = m.downcast_ref::<Filter>() {
+        MaterialDesc::Filter {
+            passband: passband_desc(&f.passband),
+            edge_width: f.edge,
+        }
+    } else if let Some(d) = m.downcast_ref::<Dichroic>() {
+        MaterialDesc::Dichroic {
+            passband: passband_desc(&d.passband),
+            edge_width: d.edge,
+        }
+    } else if let Some(s) = m.downcast_ref::<Measured>() {
+        MaterialDesc::Measured {
+            transmittance: s.transmittance.points().to_vec(),
+            reflectance: s.reflectance.points().to_vec(),
+        }
+    } else if let Some(mix) = m.downcast_ref::<Mix>() {
+        let w = weights(&mut mix.materials.iter().map(|m| m.0));
+        MaterialDesc::Mix {
+            materials: mix
+                .materials
+                .iter()
+                .zip(w)
+                .map(|(m, w)| Ok((w, desc(m.1.as_ref())?)))
+                .collect::<Result<_, SceneFileError>>()?,
+        }
+    } else if let Some(t) = m.downcast_ref::<TwoSided>() {
+        MaterialDesc::TwoSided {
+            front: Box::new(desc(t.front.as_ref())?),
+            back: Box::new(desc(t.back.as_ref())?),
+        }
+    } else if let Some(l) = m.downcast_ref::<Layered>() {
+        MaterialDesc::Layered {
+            coat: Box::new(desc(l.coat.as_ref())?),
+            base: Box::new(desc(l.base.as_ref())?),
+        }
+    } else if let Some(b) = m.downcast_ref::<Blend>() {
+        MaterialDesc::Blend {
+            a: Box::new(desc(b.a.as_ref())?),
+            b: Box::new(desc(b.b.as_ref())?),
+            texture: texture_desc(&b.texture),
+        }
+    } else if let Some(s) = m.downcast_ref::<AlphaSelect>() {
+        MaterialDesc::AlphaSelect {
+            default: Box::new(desc(s.default.as_ref())?),
+            ranges: s
+                .ranges
+                .iter()
+                .map(|r| Ok((r.0, r.1, desc(r.2.as_ref())?)))
+                .collect::<Result<_, SceneFileError>>()?,
+        }
+    } else {
+        return Err(SceneFileError::Unsupported(format!(
+            "{} has a custom material, make it with a MaterialRegistry to save it by name",
+            name
+        )));
+    })
+}
+
+fn passband_desc(passband: &Passband) -> PassbandDesc {
+    match passband {
+        Passband::BandPass(lower, upper) => PassbandDesc::BandPass(*lower, *upper),
+        Passband::LongPass(cutoff) => PassbandDesc::LongPass(*cutoff),
+        Passband::ShortPass(cutoff) => PassbandDesc::ShortPass(*cutoff),
+        Passband::Tabulated(curve) => PassbandDesc::Tabulated(curve.points().to_vec()),
+    }
+}
+
+fn texture_desc(texture: &Texture) -> TextureDesc {
+    match texture {
+        Texture::Constant(v) => TextureDesc::Constant(*v),
+        Texture::Stripes(curve) => TextureDesc::Stripes(curve.points().to_vec()),
+        Texture::Gradient(curve) => TextureDesc::Gradient(curve.points().to_vec()),
+        Texture::Periodic(t, period) => TextureDesc::Periodic {
+            texture: Box::new(texture_desc(t)),
+            period: *period,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MaterialRegistry, ObjectDesc, SceneFile, SceneFileError};
+    use geom::{Point, Transform, Vector};
+    use group::Group;
+    use instance::{Instance, Shape};
+    use light::{AreaLight, Beam};
+    use material::{AlphaSelect, Blend, Filter, HQZLegacy, Lambertian, Material, Outcome, Passband, Texture};
+    use medium::{Medium, Phase};
+    use object::{Line, Object, Solid};
+    use pcg_rand::Pcg64Fast;
+    use region::Region;
+    use sampler::Sample;
+    use scene::Scene;
+    use sequence::Sampling;
+    use std::rc::Rc;
+
+    const SCENE: &str = r#"{
+        "resolution": [200, 100],
+        "viewport": [{ "x": -10.0, "y": -10.0 }, { "x": 210.0, "y": 110.0 }],
+        "seed": 99,
+        "materials": {
+            "mirror": { "type": "HQZLegacy", "diffuse": 0.0, "reflective": 1.0, "transmissive": 0.0 },
+            "coated": { "type": "Layered", "coat": { "type": "Named", "name": "mirror" },
+                        "base": { "type": "Lambertian", "albedo": 0.5 } }
+        },
+        "lights": [
+            { "type": "Light", "power": 1.0, "x": 100.0, "y": 50.0, "polar_angle": 0.0,
+              "polar_distance": { "Normal": [0.0, 2.0] }, "ray_angle": { "Range": [360.0, 0.0] },
+              "wavelength": { "Blackbody": 5800.0 } },
+            { "type": "Spotlight", "x": 10.0, "y": 10.0, "direction": 45.0, "inner": 10.0,
+              "outer": 20.0, "wavelength": { "Discrete": [[450.0, 1.0], [650.0, 2.0]] }, "power": 2.0 }
+        ],
+        "objects": [
+            { "type": "Line", "x0": 0.0, "y0": 90.0, "dx": 200.0, "dy": 0.0, "material": "coated" },
+            { "type": "Curve", "x0": 150.0, "y0": 0.0, "a0": 180.0, "dx": 0.0, "dy": 100.0,
+              "da": { "Keyframes": [{ "time": 0.0, "value": 0.0, "interpolation": "Linear" },
+                                    { "time": 1.0, "value": 30.0, "interpolation": { "Bezier": [0.4, 0.0, 0.6, 1.0] } }] },
+              "material": "chrome" },
+            { "type": "Solid", "region": { "Intersection": [
+                { "Circle": { "center": { "x": 40.0, "y": 50.0 }, "radius": 30.0 } },
+                { "Circle": { "center": { "x": 60.0, "y": 50.0 }, "radius": 30.0 } }] },
+              "material": "mirror" }
+        ]
+    }"#;
+
+    fn registry() -> MaterialRegistry {
+        MaterialRegistry::new().with_material("chrome", || Box::new(HQZLegacy::new(0.0, 0.9, 0.0)))
+    }
+
+    #[test]
+    fn round_trip() {
+        let file = SceneFile::from_json(SCENE).unwrap();
+        assert_eq!(file.resolution, (200, 100));
+        assert_eq!(file.seed, Some(99));
+        assert_eq!((file.lights.len(), file.objects.len(), file.materials.len()), (2, 3, 2));
+
+        let saved = file.to_json();
+        let reloaded = SceneFile::from_json(&saved).unwrap();
+        assert_eq!(reloaded.to_json(), saved);
+        // Constants are written as bare numbers.
+        assert!(saved.contains("\"x0\": 0.0"));
+
+        let image = reloaded.to_scene(&registry()).unwrap().render(1000);
+        assert_eq!(image.to_rgb8(0.5, 1.0).len(), 200 * 100 * 3);
+    }
+
+    #[test]
+    fn materials_must_exist() {
+        let file = SceneFile::from_json(SCENE).unwrap();
+        match file.to_scene(&MaterialRegistry::new()) {
+            Err(SceneFileError::UnknownMaterial(name)) => assert_eq!(name, "chrome"),
+            _ => panic!("Scene built without the chrome material"),
+        }
+
+        let looped = SCENE.replace(
+            r#"{ "type": "Named", "name": "mirror" }"#,
+            r#"{ "type": "Named", "name": "coated" }"#,
+        );
+        let file = SceneFile::from_json(&looped).unwrap();
+        assert!(matches!(file.to_scene(&registry()), Err(SceneFileError::MaterialLoop(_))));
+
+        assert!(matches!(SceneFile::from_json("{}"), Err(SceneFileError::Syntax(_))));
+    }
+
+    #[test]
+    fn invalid_values_are_errors() {
+        let invalid = |json: &str| match SceneFile::from_json(json).unwrap().to_scene(&registry()) {
+            Err(SceneFileError::Invalid(reason)) => reason,
+            _ => panic!("Scene built from {}", json),
+        };
+        assert_eq!(invalid(&SCENE.replace(r#""seed": 99"#, r#""seed": 0"#)), "Seed must not be 0");
+        assert_eq!(
+            invalid(&SCENE.replace(r#""resolution": [200, 100]"#, r#""resolution": [0, 0]"#)),
+            "Resolution must not be zero"
+        );
+        let circle = r#"{ "Circle": { "center": { "x": 60.0, "y": 50.0 }, "radius": 30.0 } }"#;
+        assert_eq!(
+            invalid(&SCENE.replace(circle, r#"{ "Polygon": [] }"#)),
+            "Polygons need at least three corners"
+        );
+        let profile = |points: &str| {
+            SCENE.replace(
+                r#""type": "Spotlight", "x": 10.0, "y": 10.0, "direction": 45.0, "inner": 10.0,
+              "outer": 20.0,"#,
+                &format!(r#""type": "ProfileLight", "x": 10.0, "y": 10.0, "direction": 45.0, "profile": {},"#, points),
+            )
+        };
+        assert_eq!(
+            invalid(&profile("[[0.0, 0.0], [90.0, 0.0]]")),
+            "ProfileLight needs some intensity over a range of angles"
+        );
+        assert_eq!(invalid(&profile("[[90.0, 1.0], [0.0, 1.0]]")), "ProfileLight angles must be sorted");
+        assert_eq!(
+            invalid(&profile("[[0.0, 1.0], [200.0, 1.0]]")),
+            "ProfileLight angles must be between 0.0 and 180.0"
+        );
+        let glass = |desc: &str| {
+            format!(
+                r#"{{ "resolution": [10, 10], "materials": {{ "glass": {} }},
+                    "objects": [{{ "type": "Line", "x0": 0.0, "y0": 0.0, "dx": 1.0, "dy": 0.0, "material": "glass" }}] }}"#,
+                desc
+            )
+        };
+        assert_eq!(
+            invalid(&glass(r#"{ "type": "Dielectric", "index": -1.5 }"#)),
+            "Dielectric index must be positive"
+        );
+        assert_eq!(
+            invalid(&glass(r#"{ "type": "Grating", "spacing": 1000.0, "orders": [[0, 1.0], [1, -0.5]] }"#)),
+            "Grating order weights must not be negative"
+        );
+        assert_eq!(
+            invalid(&glass(r#"{ "type": "Mix", "materials": [[0.0, { "type": "Lambertian", "albedo": 0.5 }]] }"#)),
+            "Mix needs at least one positive weight"
+        );
+
+        // Samples are checked as they are read.
+        let bad_sample = SCENE.replace(r#"[[450.0, 1.0], [650.0, 2.0]]"#, r#"[[450.0, -1.0], [650.0, 2.0]]"#);
+        assert!(matches!(SceneFile::from_json(&bad_sample), Err(SceneFileError::Syntax(_))));
+        let bad_range = SCENE.replace(r#"{ "Range": [360.0, 0.0] }"#, r#"{ "Range": [0.0, 360.0] }"#);
+        assert!(matches!(SceneFile::from_json(&bad_range), Err(SceneFileError::Syntax(_))));
+        let bad_rate = SCENE.replace(r#"{ "Normal": [0.0, 2.0] }"#, r#"{ "Exponential": 0.0 }"#);
+        assert!(matches!(SceneFile::from_json(&bad_rate), Err(SceneFileError::Syntax(_))));
+    }
+
+    fn line(x0: f64, material: Box<dyn Material>) -> Line {
+        Line {
+            x0: Sample::Constant(x0),
+            y0: Sample::Constant(0.0),
+            dx: Sample::Constant(0.0),
+            dy: Sample::Range(100.0, 90.0),
+            material,
+        }
+    }
+
+    #[test]
+    fn saves_scene() {
+        let shape: Rc<dyn Object> = Rc::new(
+            Shape::new()
+                .with_object(line(0.0, Box::new(Lambertian::new(0.5))))
+                .with_object(line(5.0, Box::new(Lambertian::new(0.5)))),
+        );
+        let registry = registry();
+        let scene = Scene::new(200, 100)
+            .with_seed(42)
+            .with_shutter(0.0, 0.5)
+            .with_sampling(Sampling::Sobol)
+            .with_light(Beam::new(
+                Sample::Constant(0.0),
+                Sample::Constant(50.0),
+                Sample::Constant(0.0),
+                10.0,
+                Sample::Blackbody(5800.0),
+            ))
+            .with_light(
+                AreaLight::new(
+                    line(190.0, Box::new(Lambertian::new(0.0))),
+                    Sample::Constant(2.0),
+                    Sample::Constant(600.0),
+                )
+                .one_sided(),
+            )
+            .with_group(
+                Group::new(Transform::rotation(10.0))
+                    .with_light(Beam::new(
+                        Sample::Constant(20.0),
+                        Sample::Constant(20.0),
+                        Sample::Constant(90.0),
+                        5.0,
+                        Sample::Constant(500.0),
+                    ))
+                    .with_object(line(100.0, Box::new(Filter::new(Passband::BandPass(500.0, 550.0)))))
+                    .with_group(Group::new(Transform::translation(5.0, 0.0)).with_object(line(
+                        110.0,
+                        Box::new(Blend::new(
+                            Box::new(Lambertian::new(0.2)),
+                            Box::new(HQZLegacy::new(0.0, 1.0, 0.0)),
+                            Texture::periodic(Texture::stripes(&[(0.0, 0.0), (0.5, 1.0)]), 0.25),
+                        )),
+                    ))),
+            )
+            .with_object(Instance::new(shape.clone(), Transform::translation(50.0, 0.0)))
+            .with_object(
+                Instance::new(shape, Transform::translation(60.0, 0.0)).with_material(registry.material("chrome").unwrap()),
+            )
+            .with_object(Solid {
+                region: Region::Circle {
+                    center: Point { x: 150.0, y: 50.0 },
+                    radius: 10.0,
+                },
+                material: Box::new(
+                    AlphaSelect::new(Box::new(Lambertian::new(0.5)))
+                        .with_range(0.0, 0.5, Box::new(HQZLegacy::new(0.0, 0.0, 1.0))),
+                ),
+            })
+            .with_medium(
+                Medium::new(
+                    Region::Circle {
+                        center: Point { x: 100.0, y: 50.0 },
+                        radius: 20.0,
+                    },
+                    0.01,
+                )
+                .with_phase(Phase::HenyeyGreenstein(0.5)),
+            );
+
+        let file = scene.to_scene_file().unwrap();
+        assert_eq!(file.shapes.len(), 1, "Instances share their shape");
+        assert!(matches!(file.objects[0], ObjectDesc::Group { ref lights, .. } if lights.len() == 1));
+        assert_eq!(file.lights.len(), 2);
+        assert_eq!((file.shutter, file.sampling), (Some((0.0, 0.5)), Some(Sampling::Sobol)));
+
+        let saved = file.to_json();
+        let reloaded = SceneFile::from_json(&saved).unwrap().to_scene(&registry).unwrap();
+        assert_eq!(reloaded.to_scene_file().unwrap().to_json(), saved);
+        let image = reloaded.render(1000);
+        assert_eq!(image.to_rgb8(0.5, 1.0).len(), 200 * 100 * 3);
+    }
+
+    struct Custom;
+
+    impl Material for Custom {
+        fn outcome(&self, _: &Vector, _: &Vector, _: f64, _: f64, _: &mut Pcg64Fast) -> Option<Outcome> {
+            None
+        }
+    }
+
+    #[test]
+    fn custom_materials_need_a_registry() {
+        let scene = Scene::new(100, 100).with_object(line(50.0, Box::new(Custom)));
+        assert!(matches!(scene.to_scene_file(), Err(SceneFileError::Unsupported(_))));
+
+        let registry = MaterialRegistry::new().with_material("custom", || Box::new(Custom));
+        let scene = Scene::new(100, 100).with_object(line(50.0, registry.material("custom").unwrap()));
+        let file = scene.to_scene_file().unwrap();
+        assert!(file.materials.is_empty());
+        assert!(matches!(file.objects[0], ObjectDesc::Line { ref material, .. } if material == "custom"));
+    }
+
+    #[test]
+    fn shapes_must_exist() {
+        let invalid = |json: &str| match SceneFile::from_json(json).unwrap().to_scene(&registry()) {
+            Err(SceneFileError::Invalid(reason)) => reason,
+            _ => panic!("Scene built from {}", json),
+        };
+        let file = |shape: &str, scale: f64| {
+            let transform = format!(
+                r#"{{ "matrix": {{ "a1": {0}, "b1": 0.0, "a2": 0.0, "b2": {0} }}, "offset": {{ "x": 0.0, "y": 0.0 }} }}"#,
+                scale
+            );
+            format!(
+                r#"{{ "resolution": [10, 10],
+                    "shapes": {{ "loop": [{{ "type": "Instance", "shape": "loop", "transform": {0} }}] }},
+                    "objects": [{{ "type": "Instance", "shape": "{1}", "transform": {0} }}] }}"#,
+                transform, shape
+            )
+        };
+        assert_eq!(invalid(&file("missing", 1.0)), "Unknown shape \"missing\"");
+        assert_eq!(
+            invalid(&file("loop", 1.0)),
+            "Shape \"loop\" is nested too deeply, does it refer to itself?"
+        );
+        assert_eq!(invalid(&file("loop", 0.0)), "Transforms must be invertible");
+    }
+}
@@ -0,0 +1,343 @@
+//! Sources of the uniform numbers used to spawn rays.
+//!
+//! Every ray needs a handful of uniform numbers to pick a light and sample
+//! where, when, which way and what colour it is emitted. Taking these from a
+//! low discrepancy sequence instead of the PCG spreads rays more evenly,
+//! which makes low ray count previews noticeably smoother.
+
+use pcg_rand::Pcg64Fast;
+use rand::Rng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Number of uniform numbers used to spawn each ray.
+pub const DIMENSIONS: usize = 8;
+
+/// Dimension used to choose the light.
+pub const LIGHT: usize = 0;
+/// Dimension used for the light x coordinate.
+pub const X: usize = 1;
+/// Dimension used for the light y coordinate.
+pub const Y: usize = 2;
+/// Dimension used for the polar angle.
+pub const POLAR_ANGLE: usize = 3;
+/// Dimension used for the polar distance.
+pub const POLAR_DISTANCE: usize = 4;
+/// Dimension used for the ray angle.
+pub const RAY_ANGLE: usize = 5;
+/// Dimension used for the wavelength.
+pub const WAVELENGTH: usize = 6;
+/// Dimension used for the time within the shutter interval.
+pub const TIME: usize = 7;
+
+/// How the numbers used to spawn rays from lights are generated.
+///
+/// Only the emission of rays is affected, what happens to a ray after it
+/// leaves the light is always driven by the PCG.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Sampling {
+    /// Independent random numbers from the scene's PCG, this is the default.
+    #[default]
+    Random,
+    /// Jittered stratification, each dimension is split into one stratum per
+    /// ray and the strata are shuffled between dimensions (latin hypercube).
+    Stratified,
+    /// The Halton sequence, using the first eight primes as bases.
+    Halton,
+    /// The Sobol sequence, using Joe and Kuo's direction numbers.
+    Sobol,
+    /// Martin Roberts' R2 sequence generalised to the number of dimensions in use.
+    R2,
+}
+
+/// Generates the numbers for each ray of a render.
+///
+/// Sequences are randomised from the scene's PCG, a random shift for Halton
+/// and R2 and a random digital shift for Sobol, so different seeds still give
+/// different (but equally well spread) renders.
+pub struct Sequence {
+    sampling: Sampling,
+    count: u32,
+    index: u32,
+    shift: [f64; DIMENSIONS],
+    scramble: [u32; DIMENSIONS],
+    alpha: [f64; DIMENSIONS],
+    directions: [[u32; 32]; DIMENSIONS],
+}
+
+const PRIMES: [u32; DIMENSIONS] = [2, 3, 5, 7, 11, 13, 17, 19];
+
+/// Joe and Kuo's primitive polynomials and initial direction numbers for
+/// Sobol dimensions 2 to 8, as `(degree, coefficients, initial numbers)`.
+const SOBOL_POLYNOMIALS: [(usize, u32, [u32; 5]); DIMENSIONS - 1] = [
+    (1, 0, [1, 0, 0, 0, 0]),
+    (2, 1, [1, 3, 0, 0, 0]),
+    (3, 1, [1, 3, 1, 0, 0]),
+    (3, 2, [1, 1, 1, 0, 0]),
+    (4, 1, [1, 1, 3, 3, 0]),
+    (4, 4, [1, 3, 5, 13, 0]),
+    (5, 2, [1, 1, 5, 5, 17]),
+];
+
+impl Sequence {
+    /// Creates a sequence for a render of `count` rays.
+    pub fn new(sampling: Sampling, count: usize, rng: &mut Pcg64Fast) -> Self {
+        let mut shift = [0.0; DIMENSIONS];
+        let mut scramble = [0; DIMENSIONS];
+        let mut alpha = [0.0; DIMENSIONS];
+        let mut directions = [[0; 32]; DIMENSIONS];
+        if sampling != Sampling::Random {
+            let g = r2_generator();
+            for d in 0..DIMENSIONS {
+                shift[d] = rng.gen_range(0.0, 1.0);
+                scramble[d] = rng.gen();
+                alpha[d] = g.powi(-(d as i32 + 1));
+                directions[d] = direction_numbers(d);
+            }
+        }
+        Sequence {
+            sampling,
+            count: count.clamp(1, u32::MAX as usize) as u32,
+            index: 0,
+            shift,
+            scramble,
+            alpha,
+            directions,
+        }
+    }
+
+    /// Returns the numbers for the next ray, each within `[0, 1)`.
+    pub fn next(&mut self, rng: &mut Pcg64Fast) -> [f64; DIMENSIONS] {
+        let mut u = [0.0; DIMENSIONS];
+        let i = self.index;
+        self.index = self.index.wrapping_add(1);
+        match self.sampling {
+            Sampling::Random => {
+                for x in u.iter_mut() {
+                    *x = rng.gen_range(0.0, 1.0);
+                }
+            }
+            Sampling::Stratified => {
+                let n = self.count;
+                for (d, x) in u.iter_mut().enumerate() {
+                    let stratum = permute(i % n, n, self.scramble[d]);
+                    let jitter: f64 = rng.gen_range(0.0, 1.0);
+                    *x = (stratum as f64 + jitter) / n as f64;
+                }
+            }
+            Sampling::Halton => {
+                for (d, x) in u.iter_mut().enumerate() {
+                    *x = wrap(radical_inverse(i.wrapping_add(1), PRIMES[d]) + self.shift[d]);
+                }
+            }
+            Sampling::Sobol => {
+                for (d, x) in u.iter_mut().enumerate() {
+                    *x = (sobol(i, &self.directions[d]) ^ self.scramble[d]) as f64 / 4_294_967_296.0;
+                }
+            }
+            Sampling::R2 => {
+                for (d, x) in u.iter_mut().enumerate() {
+                    *x = wrap(0.5 + self.alpha[d] * (i as f64 + 1.0) + self.shift[d]);
+                }
+            }
+        }
+        u
+    }
+}
+
+/// Fractional part, keeping the result within `[0, 1)`.
+fn wrap(x: f64) -> f64 {
+    let f = x - x.floor();
+    if f >= 1.0 {
+        0.0
+    } else {
+        f
+    }
+}
+
+/// Reflects the digits of `i` in the given base about the radix point.
+fn radical_inverse(mut i: u32, base: u32) -> f64 {
+    let inv = 1.0 / base as f64;
+    let mut f = inv;
+    let mut result = 0.0;
+    while i > 0 {
+        result += (i % base) as f64 * f;
+        i /= base;
+        f *= inv;
+    }
+    result
+}
+
+/// Point `i` of the Sobol dimension with direction numbers `v`, as a 32 bit fraction.
+fn sobol(i: u32, v: &[u32; 32]) -> u32 {
+    let mut x = 0;
+    let mut bits = i;
+    let mut k = 0;
+    while bits > 0 {
+        if bits & 1 == 1 {
+            x ^= v[k];
+        }
+        bits >>= 1;
+        k += 1;
+    }
+    x
+}
+
+/// Direction numbers for Sobol dimension `d`.
+fn direction_numbers(d: usize) -> [u32; 32] {
+    let mut v = [0u32; 32];
+    if d == 0 {
+        for (k, x) in v.iter_mut().enumerate() {
+            *x = 1 << (31 - k);
+        }
+        return v;
+    }
+
+    let (s, a, m) = SOBOL_POLYNOMIALS[d - 1];
+    for k in 0..s {
+        v[k] = m[k] << (31 - k);
+    }
+    for k in s..32 {
+        v[k] = v[k - s] ^ (v[k - s] >> s);
+        for j in 1..s {
+            if (a >> (s - 1 - j)) & 1 == 1 {
+                v[k] ^= v[k - j];
+            }
+        }
+    }
+    v
+}
+
+/// The generalised golden ratio for `DIMENSIONS`, the positive root of `x^(d+1) = x + 1`.
+fn r2_generator() -> f64 {
+    let mut x: f64 = 2.0;
+    for _ in 0..30 {
+        let f = x.powi(DIMENSIONS as i32 + 1) - x - 1.0;
+        let df = (DIMENSIONS as f64 + 1.0) * x.powi(DIMENSIONS as i32) - 1.0;
+        x -= f / df;
+    }
+    x
+}
+
+/// A pseudo random permutation of `0..l`, indexed by `i` and keyed by `p`.
+///
+/// This is Andrew Kensler's hash from "Correlated Multi-Jittered Sampling",
+/// it needs no storage so stratification works for any number of rays.
+fn permute(mut i: u32, l: u32, p: u32) -> u32 {
+    let mut w = l - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < l {
+            break;
+        }
+    }
+    ((i as u64 + p as u64) % l as u64) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{direction_numbers, permute, sobol, Sampling, Sequence, DIMENSIONS};
+    use pcg_rand::seeds::PcgSeeder;
+    use pcg_rand::Pcg64Fast;
+    use rand::prelude::*;
+
+    /// Smooth test function over every dimension, its integral is exactly 1.0.
+    fn integrand(u: &[f64; DIMENSIONS]) -> f64 {
+        u.iter()
+            .enumerate()
+            .map(|(d, x)| 1.0 + (x - 0.5) * (d as f64 + 1.0) / DIMENSIONS as f64)
+            .product()
+    }
+
+    /// Root mean square error integrating `integrand` over several randomisations.
+    fn rms_error(sampling: Sampling, n: usize) -> f64 {
+        let trials = 16;
+        let mut sum_sq = 0.0;
+        for t in 0..trials {
+            let mut rng = Pcg64Fast::from_seed(PcgSeeder::seed(t as u128 + 1));
+            let mut seq = Sequence::new(sampling, n, &mut rng);
+            let mut sum = 0.0;
+            for _ in 0..n {
+                let u = seq.next(&mut rng);
+                for x in u.iter() {
+                    assert!(*x >= 0.0 && *x < 1.0);
+                }
+                sum += integrand(&u);
+            }
+            let err = sum / n as f64 - 1.0;
+            sum_sq += err * err;
+        }
+        (sum_sq / trials as f64).sqrt()
+    }
+
+    #[test]
+    fn sequences_beat_random() {
+        let n = 4096;
+        let random = rms_error(Sampling::Random, n);
+        for s in &[Sampling::Stratified, Sampling::Halton, Sampling::Sobol, Sampling::R2] {
+            let err = rms_error(*s, n);
+            assert!(err < random / 2.0, "{:?} error {} vs random {}", s, err, random);
+        }
+    }
+
+    #[test]
+    fn sobol_first_dimensions() {
+        // Unscrambled, the first Sobol points are well known.
+        let expect = [[0.0, 0.0], [0.5, 0.5], [0.25, 0.75], [0.75, 0.25]];
+        let v0 = direction_numbers(0);
+        let v1 = direction_numbers(1);
+        for (i, e) in expect.iter().enumerate() {
+            assert_eq!(sobol(i as u32, &v0) as f64 / 4_294_967_296.0, e[0]);
+            assert_eq!(sobol(i as u32, &v1) as f64 / 4_294_967_296.0, e[1]);
+        }
+    }
+
+    #[test]
+    fn stratified_covers_every_stratum() {
+        let n = 1000;
+        let mut rng = Pcg64Fast::from_entropy();
+        let mut seq = Sequence::new(Sampling::Stratified, n, &mut rng);
+        let mut seen = vec![[false; DIMENSIONS]; n];
+        for _ in 0..n {
+            let u = seq.next(&mut rng);
+            for d in 0..DIMENSIONS {
+                seen[(u[d] * n as f64) as usize][d] = true;
+            }
+        }
+        assert!(seen.iter().all(|s| s.iter().all(|x| *x)));
+    }
+
+    #[test]
+    fn permute_is_bijection() {
+        for &l in &[1, 7, 64, 1000] {
+            let mut seen = vec![false; l as usize];
+            for i in 0..l {
+                seen[permute(i, l, 0xdead_beef) as usize] = true;
+            }
+            assert!(seen.iter().all(|x| *x));
+        }
+    }
+}
@@ -1,6 +1,6 @@
 pub const BLACKBODY_CDF_TEMP: f64 = 10000.0;
 
-pub const BLACKBODY_CDF_DATA: [f64; 10000] = [
+pub static BLACKBODY_CDF_DATA: [f64; 10000] = [
     0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000,
     0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000,
     0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000,
@@ -18,7 +18,7 @@ pub fn wavelength_to_colour(nm: f64) -> (f64, f64, f64) {
     }
 
     // Case: Light outside of visible spectrum
-    if nm < FIRST_WAVELENGTH || nm > LAST_WAVELENGTH {
+    if !(FIRST_WAVELENGTH..=LAST_WAVELENGTH).contains(&nm) {
         return (0.0, 0.0, 0.0);
     }
 
@@ -35,30 +35,51 @@ pub fn wavelength_to_colour(nm: f64) -> (f64, f64, f64) {
     let g: f64 = inv * c1.1 as f64 + frac * c2.1 as f64;
     let b: f64 = inv * c1.2 as f64 + frac * c2.2 as f64;
 
-    return (r, g, b);
+    (r, g, b)
 }
 
 pub fn blackbody_wavelength(temp: f64, noise: f64) -> f64 {
     let index: usize = (1..BLACKBODY_CDF_DATA.len())
-        .find(|x| BLACKBODY_CDF_DATA[*x] >= noise)
+        .find(|x| BLACKBODY_CDF_DATA[*x] > noise)
         .expect("Blackbody Index out of range");
 
     let lower: f64 = BLACKBODY_CDF_DATA[index - 1];
     let upper: f64 = BLACKBODY_CDF_DATA[index];
 
     // Linear interpolation
-    let mut lerp: f64 = index as f64 + (noise - lower) / (upper - lower);
-    if lerp.is_nan() {
-        lerp = 0.0;
-    }
+    let lerp: f64 = index as f64 + (noise - lower) / (upper - lower);
 
     // Scale to 'temperature' using Wein's displacement law
-    return lerp * (BLACKBODY_CDF_TEMP / temp);
+    lerp * (BLACKBODY_CDF_TEMP / temp)
+}
+
+/// Upper and lower bounds of `blackbody_wavelength` for noise in `[0, 1)`,
+/// found from the first and last steps of the CDF.
+pub fn blackbody_bounds(temp: f64) -> (f64, f64) {
+    // The sentinel is never reached, so ignore it.
+    let data = &BLACKBODY_CDF_DATA[..BLACKBODY_CDF_DATA.len() - 1];
+    let first = data.iter().position(|c| *c > 0.0).expect("Blackbody CDF is empty");
+    let last = data.iter().position(|c| *c >= 1.0).expect("Blackbody CDF never reaches 1.0");
+
+    let scale = BLACKBODY_CDF_TEMP / temp;
+    ((last + 1) as f64 * scale, first as f64 * scale)
+}
+
+/// Mean of `blackbody_wavelength` for uniform noise in `[0, 1)`.
+pub fn blackbody_mean(temp: f64) -> f64 {
+    let data = &BLACKBODY_CDF_DATA[..BLACKBODY_CDF_DATA.len() - 1];
+    let mut mean = 0.0;
+    for i in 1..data.len() {
+        // Each step of the CDF is interpolated linearly from index to index + 1
+        mean += (data[i] - data[i - 1]) * (i as f64 + 0.5);
+    }
+
+    mean * (BLACKBODY_CDF_TEMP / temp)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::wavelength_to_colour;
+    use super::{blackbody_bounds, blackbody_mean, blackbody_wavelength, wavelength_to_colour};
 
     #[test]
     fn match_colours() {
@@ -77,4 +98,33 @@ mod tests {
         assert_eq!(g, -981.0);
         assert_eq!(b, 14817.0);
     }
+
+    #[test]
+    fn blackbody_within_bounds() {
+        let temp = 5800.0;
+        let (upper, lower) = blackbody_bounds(temp);
+        assert!(upper.is_finite());
+        assert!(lower > 0.0);
+
+        for i in 0..10_000 {
+            let noise = i as f64 / 10_000.0;
+            let w = blackbody_wavelength(temp, noise);
+            assert!(w >= lower, "{} < {}", w, lower);
+            assert!(w <= upper, "{} > {}", w, upper);
+        }
+        let w = blackbody_wavelength(temp, 0.999_999_999);
+        assert!(w <= upper);
+    }
+
+    #[test]
+    fn blackbody_mean_matches_samples() {
+        let temp = 5800.0;
+        let n = 100_000;
+        let mut sum = 0.0;
+        for i in 0..n {
+            sum += blackbody_wavelength(temp, (i as f64 + 0.5) / n as f64);
+        }
+        let mean = sum / n as f64;
+        assert!((mean - blackbody_mean(temp)).abs() / mean < 0.001);
+    }
 }